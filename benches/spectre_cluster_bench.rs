@@ -1,6 +1,6 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use spectre::{
-    tiles::{Anchor, SpectreCluster},
+    tiles::{Anchor, Skeleton, SpectreCluster},
     utils::{Aabb, Angle, HexVec},
 };
 
@@ -121,10 +121,89 @@ fn bench_spectres_in_position(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_with_anchor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("with_anchor");
+    group.sample_size(50);
+
+    // Test construction of fully loaded clusters at different levels and angles
+    for level in [3, 4, 5].iter() {
+        group.bench_with_input(BenchmarkId::new("level", level), level, |b, &level| {
+            b.iter(|| {
+                for angle in 0..12 {
+                    black_box(SpectreCluster::with_anchor(
+                        Anchor::Anchor1,
+                        HexVec::ZERO,
+                        Angle::new(angle),
+                        level,
+                    ));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_to_spectre_cluster(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_spectre_cluster");
+    group.sample_size(50);
+
+    // Test partial loading of a large cluster, as done on expansion
+    let skeleton = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 10);
+    for (bbox_name, bbox) in create_test_bboxes() {
+        group.bench_with_input(
+            BenchmarkId::new("level_10", bbox_name),
+            &bbox,
+            |b, bbox| b.iter(|| black_box(skeleton.to_spectre_cluster(bbox))),
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_update_pan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_pan");
+    group.sample_size(20);
+
+    // Load and unload subtrees while panning back and forth, as TilesController does every frame
+    let skeleton = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 10);
+    let path: Vec<_> = (0..40)
+        .chain((0..40).rev())
+        .map(|i| {
+            let x = i as f32 * 10.0;
+            Aabb::new(x - 40.0, -25.0, x + 40.0, 25.0)
+        })
+        .collect();
+    group.bench_function("level_10", |b| {
+        b.iter_batched(
+            || skeleton.to_spectre_cluster(&path[0]),
+            |mut cluster| {
+                for (frame, load) in path.iter().enumerate() {
+                    let unload = Aabb::new(
+                        load.min.x - 40.0,
+                        load.min.y - 40.0,
+                        load.max.x + 40.0,
+                        load.max.y + 40.0,
+                    );
+                    cluster.update(load, &unload, frame as u64);
+                    black_box(cluster.spectres_in(*load).count());
+                }
+                cluster
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_spectres_in,
     bench_spectres_in_with_size,
-    bench_spectres_in_position
+    bench_spectres_in_position,
+    bench_with_anchor,
+    bench_to_spectre_cluster,
+    bench_update_pan
 );
 criterion_main!(benches);
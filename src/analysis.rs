@@ -0,0 +1,11 @@
+mod planar_mesh;
+mod statistics;
+mod substitution;
+mod vertex_atlas;
+
+pub use planar_mesh::{
+    Face, FaceId, HalfEdge, HalfEdgeId, PlanarMesh, PlanarMeshBuilder, VertexId,
+};
+pub use statistics::{LevelCounts, TileStatistics};
+pub use substitution::{Substitution, SupertileType};
+pub use vertex_atlas::{VertexAtlas, VertexAtlasEntry, VertexConfiguration, VertexCorner};
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    tiles::{Spectre, SpectreIter, TileAddress},
+    utils::{HexVec, Region},
+};
+
+pub type VertexId = usize;
+pub type HalfEdgeId = usize;
+pub type FaceId = usize;
+
+/// 有向辺
+///
+/// 左側にfaceがあり、境界の外側ではfaceがNoneになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfEdge {
+    pub origin: VertexId,
+    pub twin: HalfEdgeId,
+    pub next: HalfEdgeId,
+    pub prev: HalfEdgeId,
+    pub face: Option<FaceId>,
+}
+
+/// タイル一枚に対応する面
+#[derive(Clone)]
+pub struct Face {
+    pub address: TileAddress,
+    pub spectre: Spectre,
+    /// 面を反時計回りに囲む有向辺の一つ
+    pub edge: HalfEdgeId,
+}
+
+/// タイルを面とする平面グラフ（ハーフエッジ構造）
+///
+/// 頂点はHexVecで厳密に同一視する
+pub struct PlanarMesh {
+    vertices: Vec<HexVec>,
+    /// 各頂点から出る有向辺の一つ
+    vertex_edges: Vec<HalfEdgeId>,
+    half_edges: Vec<HalfEdge>,
+    faces: Vec<Face>,
+}
+
+impl PlanarMesh {
+    /// spectres_inで列挙されたタイルからメッシュを作る
+    pub fn from_spectres<R: Region>(mut iter: SpectreIter<'_, R>) -> Self {
+        let mut builder = PlanarMeshBuilder::new();
+        while let Some(spectre) = iter.next() {
+            if let Some(address) = iter.address() {
+                builder.add_tile(*spectre, address);
+            }
+        }
+        builder.build()
+    }
+
+    pub fn vertices(&self) -> &[HexVec] {
+        &self.vertices
+    }
+
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    pub fn vertex(&self, id: VertexId) -> HexVec {
+        self.vertices[id]
+    }
+
+    pub fn half_edge(&self, id: HalfEdgeId) -> &HalfEdge {
+        &self.half_edges[id]
+    }
+
+    pub fn face(&self, id: FaceId) -> &Face {
+        &self.faces[id]
+    }
+
+    /// 座標が一致する頂点
+    pub fn find_vertex(&self, point: HexVec) -> Option<VertexId> {
+        self.vertices.iter().position(|&v| v == point)
+    }
+
+    /// 有向辺の終点
+    pub fn destination(&self, edge: HalfEdgeId) -> VertexId {
+        self.half_edges[self.half_edges[edge].twin].origin
+    }
+
+    /// 頂点から出る有向辺（反時計回り）
+    pub fn edges_around_vertex(&self, vertex: VertexId) -> Vec<HalfEdgeId> {
+        let start = self.vertex_edges[vertex];
+        let mut edges = vec![start];
+        let mut edge = self.half_edges[self.half_edges[start].prev].twin;
+        while edge != start {
+            edges.push(edge);
+            edge = self.half_edges[self.half_edges[edge].prev].twin;
+        }
+        edges
+    }
+
+    /// 頂点を囲む面（反時計回り）。境界上の頂点では外側を除く
+    pub fn faces_around_vertex(&self, vertex: VertexId) -> Vec<FaceId> {
+        self.edges_around_vertex(vertex)
+            .into_iter()
+            .filter_map(|edge| self.half_edges[edge].face)
+            .collect()
+    }
+
+    /// 面を反時計回りに囲む頂点
+    pub fn face_vertices(&self, face: FaceId) -> Vec<VertexId> {
+        self.cycle(self.faces[face].edge)
+            .into_iter()
+            .map(|edge| self.half_edges[edge].origin)
+            .collect()
+    }
+
+    /// メッシュ全体の境界。外周は時計回り、穴は反時計回りに並ぶ
+    pub fn boundary_loops(&self) -> Vec<Vec<VertexId>> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = vec![];
+        for (id, edge) in self.half_edges.iter().enumerate() {
+            if edge.face.is_some() || visited[id] {
+                continue;
+            }
+            let cycle = self.cycle(id);
+            for &edge in &cycle {
+                visited[edge] = true;
+            }
+            loops.push(
+                cycle
+                    .into_iter()
+                    .map(|edge| self.half_edges[edge].origin)
+                    .collect(),
+            );
+        }
+        loops
+    }
+
+    /// 面の集合の境界。面を左に見ながら辿るので、外周は反時計回りになる
+    pub fn boundary_of(&self, faces: impl IntoIterator<Item = FaceId>) -> Vec<Vec<VertexId>> {
+        let faces: HashSet<FaceId> = faces.into_iter().collect();
+        let inside = |edge: HalfEdgeId| {
+            self.half_edges[edge]
+                .face
+                .is_some_and(|face| faces.contains(&face))
+        };
+        let on_boundary = |edge: HalfEdgeId| inside(edge) && !inside(self.half_edges[edge].twin);
+
+        let mut visited = HashSet::new();
+        let mut loops = vec![];
+        for start in (0..self.half_edges.len()).filter(|&edge| on_boundary(edge)) {
+            if visited.contains(&start) {
+                continue;
+            }
+            let mut boundary = vec![];
+            let mut edge = start;
+            loop {
+                visited.insert(edge);
+                boundary.push(self.half_edges[edge].origin);
+                // 終点の周りを時計回りに回って、次に集合の外と接する辺を探す
+                let mut next = self.half_edges[edge].next;
+                while !on_boundary(next) {
+                    next = self.half_edges[self.half_edges[next].twin].next;
+                }
+                edge = next;
+                if edge == start {
+                    break;
+                }
+            }
+            loops.push(boundary);
+        }
+        loops
+    }
+
+    /// nextを辿って一周する有向辺
+    fn cycle(&self, start: HalfEdgeId) -> Vec<HalfEdgeId> {
+        let mut edges = vec![start];
+        let mut edge = self.half_edges[start].next;
+        while edge != start {
+            edges.push(edge);
+            edge = self.half_edges[edge].next;
+        }
+        edges
+    }
+}
+
+/// タイルを一枚ずつ追加してPlanarMeshを作る
+///
+/// タイルは辺同士で接している（頂点が他のタイルの辺の途中にない）ものとする
+#[derive(Default)]
+pub struct PlanarMeshBuilder {
+    vertices: Vec<HexVec>,
+    vertex_ids: HashMap<HexVec, VertexId>,
+    /// 各タイルの頂点（反時計回り）
+    tiles: Vec<(Spectre, TileAddress, Vec<VertexId>)>,
+}
+
+impl PlanarMeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_tile(&mut self, spectre: Spectre, address: TileAddress) -> &mut Self {
+        let mut points = spectre.vertices();
+        // 鏡映されたタイルは頂点が時計回りに並ぶ
+        if spectre.is_reflected() {
+            points.reverse();
+        }
+        let vertices = points
+            .into_iter()
+            .map(|point| {
+                *self.vertex_ids.entry(point).or_insert_with(|| {
+                    self.vertices.push(point);
+                    self.vertices.len() - 1
+                })
+            })
+            .collect();
+        self.tiles.push((spectre, address, vertices));
+        self
+    }
+
+    pub fn build(self) -> PlanarMesh {
+        let mut half_edges: Vec<HalfEdge> = vec![];
+        let mut faces = vec![];
+        let mut edge_ids: HashMap<(VertexId, VertexId), HalfEdgeId> = HashMap::new();
+
+        // 面を囲む有向辺
+        for (face, (spectre, address, vertices)) in self.tiles.into_iter().enumerate() {
+            let first = half_edges.len();
+            let n = vertices.len();
+            for i in 0..n {
+                let (from, to) = (vertices[i], vertices[(i + 1) % n]);
+                edge_ids.insert((from, to), first + i);
+                half_edges.push(HalfEdge {
+                    origin: from,
+                    twin: usize::MAX,
+                    next: first + (i + 1) % n,
+                    prev: first + (i + n - 1) % n,
+                    face: Some(face),
+                });
+            }
+            faces.push(Face {
+                address,
+                spectre,
+                edge: first,
+            });
+        }
+
+        // 対になる有向辺。相手がなければ境界の外側の有向辺を作る
+        let face_edge_count = half_edges.len();
+        for id in 0..face_edge_count {
+            let from = half_edges[id].origin;
+            let to = half_edges[half_edges[id].next].origin;
+            if let Some(&twin) = edge_ids.get(&(to, from)) {
+                half_edges[id].twin = twin;
+            } else {
+                let twin = half_edges.len();
+                half_edges.push(HalfEdge {
+                    origin: to,
+                    twin: id,
+                    next: usize::MAX,
+                    prev: usize::MAX,
+                    face: None,
+                });
+                half_edges[id].twin = twin;
+            }
+        }
+
+        // 各頂点から出る有向辺を反時計回りに並べる
+        let mut outgoing: Vec<Vec<HalfEdgeId>> = vec![vec![]; self.vertices.len()];
+        for (id, edge) in half_edges.iter().enumerate() {
+            outgoing[edge.origin].push(id);
+        }
+        let direction = |edge: &HalfEdge, half_edges: &[HalfEdge]| {
+            let d = self.vertices[half_edges[edge.twin].origin].to_vec2()
+                - self.vertices[edge.origin].to_vec2();
+            d.y.atan2(d.x)
+        };
+        for edges in outgoing.iter_mut() {
+            edges.sort_by(|&a, &b| {
+                direction(&half_edges[a], &half_edges)
+                    .total_cmp(&direction(&half_edges[b], &half_edges))
+            });
+        }
+
+        // 境界の外側の有向辺を繋ぐ
+        // 終点で、逆向きの辺から時計回りに次の辺がnextになる
+        for id in face_edge_count..half_edges.len() {
+            let twin = half_edges[id].twin;
+            let around = &outgoing[half_edges[twin].origin];
+            let position = around.iter().position(|&edge| edge == twin).unwrap();
+            let next = around[(position + around.len() - 1) % around.len()];
+            half_edges[id].next = next;
+            half_edges[next].prev = id;
+        }
+
+        let vertex_edges = outgoing.iter().map(|edges| edges[0]).collect();
+        PlanarMesh {
+            vertices: self.vertices,
+            vertex_edges,
+            half_edges,
+            faces,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Aabb, Angle},
+    };
+
+    fn mesh(level: usize) -> PlanarMesh {
+        let cluster =
+            SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, level);
+        PlanarMesh::from_spectres(cluster.spectres_in(cluster.bbox()))
+    }
+
+    #[test]
+    fn test_half_edge_links() {
+        let mesh = mesh(2);
+        for (id, edge) in mesh.half_edges().iter().enumerate() {
+            assert_eq!(mesh.half_edge(edge.twin).twin, id);
+            assert_eq!(mesh.half_edge(edge.next).prev, id);
+            assert_eq!(mesh.half_edge(edge.next).origin, mesh.destination(id));
+        }
+        // 円板と同相なのでオイラー標数は1で、境界は一つ
+        let (v, e, f) = (
+            mesh.vertices().len() as i64,
+            mesh.half_edges().len() as i64 / 2,
+            mesh.faces().len() as i64,
+        );
+        assert_eq!(v - e + f, 1);
+        assert_eq!(mesh.boundary_loops().len(), 1);
+    }
+
+    #[test]
+    fn test_faces_around_vertex() {
+        let mesh = mesh(3);
+        for vertex in 0..mesh.vertices().len() {
+            let faces = mesh.faces_around_vertex(vertex);
+            assert!(!faces.is_empty());
+            for face in faces {
+                assert!(mesh.face_vertices(face).contains(&vertex));
+            }
+        }
+        // 境界上にない頂点では、周りの有向辺が全て面に接する
+        let boundary: HashSet<VertexId> = mesh.boundary_loops().into_iter().flatten().collect();
+        for vertex in (0..mesh.vertices().len()).filter(|v| !boundary.contains(v)) {
+            // Spectreには180度の頂点があるので、二本の辺しか出ない内部の頂点もある
+            let edges = mesh.edges_around_vertex(vertex);
+            assert!(edges.len() >= 2);
+            assert!(edges
+                .iter()
+                .all(|&edge| mesh.half_edge(edge).face.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_boundary_of() {
+        let mesh = mesh(3);
+        // 全ての面の境界はメッシュの外周を逆向きに辿ったもの
+        let all = mesh.boundary_of(0..mesh.faces().len());
+        let mut outer = mesh.boundary_loops().pop().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].len(), outer.len());
+        outer.sort();
+        let mut all = all[0].clone();
+        all.sort();
+        assert_eq!(all, outer);
+
+        // 一枚のタイルの境界はその頂点
+        let single = mesh.boundary_of([5]);
+        assert_eq!(single, vec![mesh.face_vertices(5)]);
+
+        // 範囲内の面の境界
+        let bbox = Aabb::new(-5.0, -5.0, 5.0, 5.0);
+        let faces: Vec<FaceId> = (0..mesh.faces().len())
+            .filter(|&face| bbox.contains(mesh.vertex(mesh.face_vertices(face)[0]).to_vec2()))
+            .collect();
+        assert!(!mesh.boundary_of(faces).is_empty());
+    }
+
+    #[test]
+    fn test_addresses() {
+        let mesh = mesh(2);
+        let addresses: HashSet<&TileAddress> =
+            mesh.faces().iter().map(|face| &face.address).collect();
+        assert_eq!(addresses.len(), mesh.faces().len());
+        assert!(mesh
+            .faces()
+            .iter()
+            .all(|face| face.address.slots().len() == 2));
+    }
+}
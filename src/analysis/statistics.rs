@@ -0,0 +1,219 @@
+use glam::Vec2;
+
+use crate::{
+    tiles::{self, SpectreCluster},
+    utils::{Aabb, Angle},
+};
+
+/// 生成されたパッチに含まれるタイルの統計
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileStatistics {
+    /// Angleごとのタイル数（インデックスは`Angle::value()`）
+    pub rotation_counts: [usize; 12],
+    /// Mysticを構成するタイルの数
+    pub mystic_tiles: usize,
+    /// 集計した範囲
+    pub bbox: Aabb,
+}
+
+impl TileStatistics {
+    /// bboxに中心が含まれるタイルを集計する
+    ///
+    /// bboxと交差するだけのタイルを数えると密度が過大になるため、
+    /// タイルのbboxの中心がbboxに含まれるものだけを数える。
+    /// clusterはbboxの範囲でロード済みである必要がある。
+    pub fn collect(cluster: &SpectreCluster, bbox: &Aabb) -> Self {
+        let mut rotation_counts = [0; 12];
+        let mut mystic_tiles = 0;
+        let mut iter = cluster.spectres_in(*bbox);
+        while let Some(spectre) = iter.next() {
+            let tile_bbox = spectre.bbox();
+            if !bbox.contains((tile_bbox.min + tile_bbox.max) * 0.5) {
+                continue;
+            }
+            rotation_counts[spectre.rotation().value() as usize] += 1;
+            if iter.in_mystic() {
+                mystic_tiles += 1;
+            }
+        }
+        Self {
+            rotation_counts,
+            mystic_tiles,
+            bbox: *bbox,
+        }
+    }
+
+    /// centerを中心に一辺の半分がhalf_sizesの正方形を順に広げながら集計する
+    ///
+    /// 範囲を広げたときに各値がどう収束するかを見るためのもの
+    pub fn convergence(
+        cluster: &SpectreCluster,
+        center: Vec2,
+        half_sizes: &[f32],
+    ) -> Vec<TileStatistics> {
+        half_sizes
+            .iter()
+            .map(|&half_size| {
+                let half_size = Vec2::splat(half_size);
+                let bbox = Aabb::from_min_max(center - half_size, center + half_size);
+                Self::collect(cluster, &bbox)
+            })
+            .collect()
+    }
+
+    /// タイルの総数
+    pub fn total(&self) -> usize {
+        self.rotation_counts.iter().sum()
+    }
+
+    /// 指定されたAngleのタイルの割合
+    pub fn rotation_frequency(&self, angle: Angle) -> f64 {
+        ratio(
+            self.rotation_counts[angle.value() as usize] as f64,
+            self.total() as f64,
+        )
+    }
+
+    /// Mysticに属するタイルの割合
+    pub fn mystic_fraction(&self) -> f64 {
+        ratio(self.mystic_tiles as f64, self.total() as f64)
+    }
+
+    /// 単位面積あたりのタイル数
+    pub fn density(&self) -> f64 {
+        let size = self.bbox.max - self.bbox.min;
+        let area = size.x as f64 * size.y as f64;
+        if self.bbox.is_empty() {
+            0.0
+        } else {
+            self.total() as f64 / area
+        }
+    }
+}
+
+/// あるlevelのSpectreClusterに含まれるタイル数の厳密値
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelCounts {
+    /// タイルの総数
+    pub tiles: u64,
+    /// Mysticの数（各Mysticは2枚のタイルからなる）
+    pub mystics: u64,
+}
+
+impl LevelCounts {
+    /// タイルを列挙せずにlevelごとの数を求める。u64に収まらなければNone
+    pub fn at_level(level: usize) -> Option<Self> {
+        let (_, mystics) = tiles::cluster_counts(level, false)?;
+        Some(Self {
+            tiles: tiles::tile_count(level, false)?,
+            mystics,
+        })
+    }
+
+    /// Mysticに属するタイルの割合
+    pub fn mystic_fraction(&self) -> f64 {
+        // usizeを経由すると32bitの環境で切り詰められるので、u64から直接変換する
+        ratio(2.0 * self.mystics as f64, self.tiles as f64)
+    }
+}
+
+fn ratio(count: f64, total: f64) -> f64 {
+    if total == 0.0 {
+        0.0
+    } else {
+        count / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{Substitution, SupertileType},
+        tiles::Anchor,
+        utils::HexVec,
+    };
+
+    #[test]
+    fn test_level_counts_match_enumeration() {
+        for level in 1..=3 {
+            let cluster =
+                SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, level);
+            let counts = LevelCounts::at_level(level).unwrap();
+
+            let mut tiles = 0;
+            let mut mystic_tiles = 0;
+            let mut iter = cluster.spectres_in(cluster.bbox());
+            while iter.next().is_some() {
+                tiles += 1;
+                if iter.in_mystic() {
+                    mystic_tiles += 1;
+                }
+            }
+
+            assert_eq!(
+                counts.tiles, tiles,
+                "tile count mismatch at level {}",
+                level
+            );
+            assert_eq!(
+                2 * counts.mystics,
+                mystic_tiles,
+                "mystic count mismatch at level {}",
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn test_level_counts() {
+        assert_eq!(
+            LevelCounts::at_level(0),
+            Some(LevelCounts {
+                tiles: 1,
+                mystics: 0
+            })
+        );
+        assert_eq!(
+            LevelCounts::at_level(1),
+            Some(LevelCounts {
+                tiles: 9,
+                mystics: 1
+            })
+        );
+        assert_eq!(
+            LevelCounts::at_level(2),
+            Some(LevelCounts {
+                tiles: 71,
+                mystics: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_mystic_fraction_at_high_level() {
+        // u32に収まらない数でも、Mysticの割合は置換行列の固有ベクトルに収束する
+        let counts = LevelCounts::at_level(21).unwrap();
+        assert!(counts.tiles > u32::MAX as u64);
+        let frequencies = Substitution::new().tile_frequencies();
+        let expected =
+            frequencies[SupertileType::H.index()] + frequencies[SupertileType::MysticH.index()];
+        assert!((counts.mystic_fraction() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_collect() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 3);
+        let stats = TileStatistics::collect(&cluster, &cluster.bbox());
+        assert_eq!(
+            stats.total() as u64,
+            LevelCounts::at_level(3).unwrap().tiles
+        );
+        assert!(stats.density() > 0.0);
+
+        let frequencies: f64 = (0..12)
+            .map(|i| stats.rotation_frequency(Angle::new(i)))
+            .sum();
+        assert!((frequencies - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::tiles;
+
+/// 置換規則におけるタイル（またはCluster）の種類
+///
+/// 親のどの位置に置かれているかで区別する。
+/// hの位置は常にMysticLikeで、SpectreClusterのhとMysticClusterのhを別の種類として扱う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SupertileType {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    /// SpectreClusterのhに置かれたMysticLike
+    H,
+    /// MysticClusterのhに置かれたMysticLike
+    MysticH,
+}
+
+impl SupertileType {
+    /// 種類の数
+    pub const COUNT: usize = 9;
+
+    /// 全ての種類
+    pub const ALL: [SupertileType; Self::COUNT] = [
+        SupertileType::A,
+        SupertileType::B,
+        SupertileType::C,
+        SupertileType::D,
+        SupertileType::E,
+        SupertileType::F,
+        SupertileType::G,
+        SupertileType::H,
+        SupertileType::MysticH,
+    ];
+
+    /// 行列におけるインデックス
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// MysticLikeかどうか
+    pub fn is_mystic(self) -> bool {
+        matches!(self, SupertileType::H | SupertileType::MysticH)
+    }
+
+    /// TileAddressのslot（0〜7、a〜h）に置かれた種類
+    ///
+    /// in_mystic_clusterは親がMysticClusterかどうか。MysticClusterのslotもeを飛ばした番号で数える
+    pub fn from_slot(slot: u8, in_mystic_cluster: bool) -> Self {
+        match (slot, in_mystic_cluster) {
+            (7, true) => SupertileType::MysticH,
+            _ => SPECTRE_CLUSTER_CHILDREN[slot as usize],
+        }
+    }
+
+    /// SpectreClusterの根からslotsを辿ったときの、各段の種類
+    pub fn along(slots: &[u8]) -> Vec<SupertileType> {
+        let mut types: Vec<SupertileType> = Vec::with_capacity(slots.len());
+        for &slot in slots {
+            let in_mystic_cluster = types.last().is_some_and(|t| t.is_mystic());
+            types.push(Self::from_slot(slot, in_mystic_cluster));
+        }
+        types
+    }
+
+    /// level 0でのタイル数（Mysticは2枚のSpectreからなる）
+    pub fn tiles_at_level_zero(self) -> u64 {
+        if self.is_mystic() {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// SpectreCluster::newの子の並び
+const SPECTRE_CLUSTER_CHILDREN: [SupertileType; 8] = [
+    SupertileType::A,
+    SupertileType::B,
+    SupertileType::C,
+    SupertileType::D,
+    SupertileType::E,
+    SupertileType::F,
+    SupertileType::G,
+    SupertileType::H,
+];
+
+/// MysticCluster::newの子の並び（eを持たない）
+const MYSTIC_CLUSTER_CHILDREN: [SupertileType; 7] = [
+    SupertileType::A,
+    SupertileType::B,
+    SupertileType::C,
+    SupertileType::D,
+    SupertileType::F,
+    SupertileType::G,
+    SupertileType::MysticH,
+];
+
+/// 9×9の置換行列
+///
+/// `matrix[i][j]`は種類iのClusterに含まれる種類jの子の数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    matrix: [[u64; SupertileType::COUNT]; SupertileType::COUNT],
+}
+
+impl Default for Substitution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Substitution {
+    /// SpectreClusterとMysticClusterの子の構成から置換行列を作る
+    pub fn new() -> Self {
+        let mut matrix = [[0; SupertileType::COUNT]; SupertileType::COUNT];
+        for parent in SupertileType::ALL {
+            let children: &[SupertileType] = if parent.is_mystic() {
+                &MYSTIC_CLUSTER_CHILDREN
+            } else {
+                &SPECTRE_CLUSTER_CHILDREN
+            };
+            for child in children {
+                matrix[parent.index()][child.index()] += 1;
+            }
+        }
+        Self { matrix }
+    }
+
+    pub fn matrix(&self) -> &[[u64; SupertileType::COUNT]; SupertileType::COUNT] {
+        &self.matrix
+    }
+
+    /// level nの種類rootのClusterに含まれるlevel 0の子を種類ごとに数える。u64に収まらなければNone
+    ///
+    /// level 0の子の種類はlevel 1の親での位置で決まるので、level 1のClusterの数から求める
+    pub fn counts(&self, root: SupertileType, level: usize) -> Option<[u64; SupertileType::COUNT]> {
+        let mut counts = [0; SupertileType::COUNT];
+        if level == 0 {
+            counts[root.index()] = 1;
+            return Some(counts);
+        }
+        let (spectre, mystic) = tiles::cluster_counts(level - 1, root.is_mystic())?;
+        for (children, parents) in [
+            (&SPECTRE_CLUSTER_CHILDREN[..], spectre),
+            (&MYSTIC_CLUSTER_CHILDREN[..], mystic),
+        ] {
+            for child in children {
+                counts[child.index()] = counts[child.index()].checked_add(parents)?;
+            }
+        }
+        Some(counts)
+    }
+
+    /// level nの種類rootのClusterに含まれるタイル数。u64に収まらなければNone
+    pub fn tile_count(&self, root: SupertileType, level: usize) -> Option<u64> {
+        tiles::tile_count(level, root.is_mystic())
+    }
+
+    /// Perron–Frobenius固有値（1 levelあたりの個数の増加率）
+    ///
+    /// 理論値は4+√15
+    pub fn inflation_factor(&self) -> f64 {
+        self.perron_frobenius().0
+    }
+
+    /// Perron–Frobenius固有値に対応する左固有ベクトル
+    ///
+    /// 十分大きなClusterに含まれる各種類の割合を表す（和が1になるよう正規化）
+    pub fn frequencies(&self) -> [f64; SupertileType::COUNT] {
+        self.perron_frobenius().1
+    }
+
+    /// 各種類に属するタイルの割合（Mysticを2枚と数える）
+    pub fn tile_frequencies(&self) -> [f64; SupertileType::COUNT] {
+        let mut frequencies = self.frequencies();
+        for (f, t) in frequencies.iter_mut().zip(SupertileType::ALL) {
+            *f *= t.tiles_at_level_zero() as f64;
+        }
+        let sum: f64 = frequencies.iter().sum();
+        frequencies.map(|f| f / sum)
+    }
+
+    /// べき乗法で固有値と左固有ベクトルを求める
+    fn perron_frobenius(&self) -> (f64, [f64; SupertileType::COUNT]) {
+        const ITERATIONS: usize = 64;
+        let mut vector = [1.0 / SupertileType::COUNT as f64; SupertileType::COUNT];
+        let mut eigenvalue = 0.0;
+        for _ in 0..ITERATIONS {
+            let mut next = [0.0; SupertileType::COUNT];
+            for (i, &v) in vector.iter().enumerate() {
+                for (j, &m) in self.matrix[i].iter().enumerate() {
+                    next[j] += v * m as f64;
+                }
+            }
+            // vectorの和は1なので、和がそのまま固有値の近似になる
+            eigenvalue = next.iter().sum();
+            vector = next.map(|v| v / eigenvalue);
+        }
+        (eigenvalue, vector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_matrix_rows() {
+        let substitution = Substitution::new();
+        for t in SupertileType::ALL {
+            let row_sum: u64 = substitution.matrix()[t.index()].iter().sum();
+            assert_eq!(row_sum, if t.is_mystic() { 7 } else { 8 });
+        }
+    }
+
+    #[test]
+    fn test_counts_match_tile_count() {
+        // 種類ごとの数をタイル数に直すと、levelから求めたタイル数と一致する
+        let substitution = Substitution::new();
+        for root in [SupertileType::A, SupertileType::H] {
+            for level in 0..22 {
+                let counts = substitution.counts(root, level).unwrap();
+                let tiles: u64 = counts
+                    .iter()
+                    .zip(SupertileType::ALL)
+                    .map(|(&count, t)| count * t.tiles_at_level_zero())
+                    .sum();
+                assert_eq!(Some(tiles), substitution.tile_count(root, level));
+            }
+        }
+    }
+
+    #[test]
+    fn test_counts_overflow() {
+        // level 22のタイル数はu64に収まらない
+        let substitution = Substitution::new();
+        assert!(substitution.tile_count(SupertileType::A, 21).is_some());
+        assert_eq!(substitution.tile_count(SupertileType::A, 22), None);
+        assert_eq!(substitution.counts(SupertileType::A, 100), None);
+    }
+
+    #[test]
+    fn test_children_match_clusters() {
+        // 実際のClusterのタイルを位置から種類に分けた数が、子の並びから作った置換行列と一致する
+        let level = 3;
+        let cluster =
+            SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, level);
+        let mut counts = [0; SupertileType::COUNT];
+        let mut iter = cluster.spectres_in(cluster.bbox());
+        while iter.next().is_some() {
+            let address = iter.address().unwrap();
+            // Mysticの上半分は下半分と同じ子として数えない
+            if address.mystic_part() == Some(1) {
+                continue;
+            }
+            let types = SupertileType::along(address.slots());
+            counts[types.last().unwrap().index()] += 1;
+        }
+        assert_eq!(
+            Some(counts),
+            Substitution::new().counts(SupertileType::A, level)
+        );
+    }
+
+    #[test]
+    fn test_from_slot() {
+        assert_eq!(SupertileType::from_slot(4, false), SupertileType::E);
+        assert_eq!(SupertileType::from_slot(7, false), SupertileType::H);
+        assert_eq!(SupertileType::from_slot(7, true), SupertileType::MysticH);
+        assert_eq!(
+            SupertileType::along(&[7, 7, 5]),
+            vec![SupertileType::H, SupertileType::MysticH, SupertileType::F]
+        );
+    }
+
+    #[test]
+    fn test_inflation_factor() {
+        let substitution = Substitution::new();
+        let expected = 4.0 + 15.0_f64.sqrt();
+        assert!((substitution.inflation_factor() - expected).abs() < 1e-9);
+
+        // 個数の比も同じ値に収束する
+        let ratio = substitution.tile_count(SupertileType::A, 10).unwrap() as f64
+            / substitution.tile_count(SupertileType::A, 9).unwrap() as f64;
+        assert!((ratio - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_frequencies() {
+        let substitution = Substitution::new();
+        let frequencies = substitution.frequencies();
+        assert!((frequencies.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // 固有ベクトルであることを確認
+        let eigenvalue = substitution.inflation_factor();
+        for j in 0..SupertileType::COUNT {
+            let product: f64 = (0..SupertileType::COUNT)
+                .map(|i| frequencies[i] * substitution.matrix()[i][j] as f64)
+                .sum();
+            assert!((product - eigenvalue * frequencies[j]).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use super::{PlanarMesh, VertexId};
+use crate::{
+    tiles::Spectre,
+    utils::{HexVec, Orientation},
+};
+
+/// 頂点の周りに集まるタイルの角の一つ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VertexCorner {
+    /// タイルの頂点のインデックス（Anchor1が0）
+    pub corner: u8,
+    /// 最初の角のタイルから見た向き
+    pub orientation: Orientation,
+}
+
+/// 頂点の周りのタイルの並び（頂点近傍）
+///
+/// 角を反時計回りに並べたもの。平行移動と回転で重なる近傍が同じ値になるよう、
+/// 向きは最初の角のタイルからの相対値にし、巡回の始点は辞書順で最小になるものを選ぶ。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VertexConfiguration {
+    corners: Vec<VertexCorner>,
+}
+
+impl VertexConfiguration {
+    /// 反時計回りに並んだ(タイルの頂点のインデックス, タイルの向き)から作る
+    pub fn new(corners: &[(u8, Orientation)]) -> Self {
+        let n = corners.len();
+        let corners = (0..n)
+            .map(|start| {
+                let base = corners[start].1.inverse();
+                (0..n)
+                    .map(|i| {
+                        let (corner, orientation) = corners[(start + i) % n];
+                        VertexCorner {
+                            corner,
+                            orientation: base.compose(orientation),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .min()
+            .unwrap_or_default();
+        Self { corners }
+    }
+
+    pub fn corners(&self) -> &[VertexCorner] {
+        &self.corners
+    }
+
+    /// 角の内角の合計（30度単位）。タイルで埋まった頂点なら12になる
+    pub fn angle_sum(&self) -> u32 {
+        self.corners
+            .iter()
+            .map(|c| Spectre::interior_angle(c.corner as usize).value() as u32)
+            .sum()
+    }
+}
+
+impl std::fmt::Display for VertexConfiguration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, c) in self.corners.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            let reflected = if c.orientation.reflected { "'" } else { "" };
+            write!(
+                f,
+                "{}@{}{}",
+                c.corner,
+                c.orientation.rotation.value(),
+                reflected
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 一種類の頂点近傍の集計
+#[derive(Debug, Clone)]
+pub struct VertexAtlasEntry {
+    pub configuration: VertexConfiguration,
+    /// 出現した頂点の数
+    pub count: usize,
+    /// 出現した位置の例
+    pub examples: Vec<HexVec>,
+}
+
+/// パッチに現れる頂点近傍の一覧
+///
+/// 境界上の頂点は近傍が欠けているので数えない
+#[derive(Debug, Clone)]
+pub struct VertexAtlas {
+    /// 出現数の多い順
+    entries: Vec<VertexAtlasEntry>,
+    /// 集計した頂点の数
+    total: usize,
+}
+
+impl VertexAtlas {
+    /// meshの内部の頂点を集計する。位置の例は一種類につきmax_examples個まで残す
+    pub fn collect(mesh: &PlanarMesh, max_examples: usize) -> Self {
+        let mut entries: HashMap<VertexConfiguration, VertexAtlasEntry> = HashMap::new();
+        let mut total = 0;
+        for vertex in 0..mesh.vertices().len() {
+            let Some(configuration) = configuration_at(mesh, vertex) else {
+                continue;
+            };
+            total += 1;
+            let entry = entries
+                .entry(configuration.clone())
+                .or_insert_with(|| VertexAtlasEntry {
+                    configuration,
+                    count: 0,
+                    examples: vec![],
+                });
+            entry.count += 1;
+            if entry.examples.len() < max_examples {
+                entry.examples.push(mesh.vertex(vertex));
+            }
+        }
+        let mut entries: Vec<_> = entries.into_values().collect();
+        entries.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.configuration.cmp(&b.configuration))
+        });
+        Self { entries, total }
+    }
+
+    pub fn entries(&self) -> &[VertexAtlasEntry] {
+        &self.entries
+    }
+
+    /// 集計した頂点の数
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// 頂点近傍の出現頻度
+    pub fn frequency(&self, configuration: &VertexConfiguration) -> f64 {
+        let count = self
+            .entries
+            .iter()
+            .find(|entry| &entry.configuration == configuration)
+            .map_or(0, |entry| entry.count);
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 / self.total as f64
+        }
+    }
+}
+
+impl std::fmt::Display for VertexAtlas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            write!(
+                f,
+                "{:>8} {:>7.4}  {}",
+                entry.count,
+                entry.count as f64 / self.total as f64,
+                entry.configuration
+            )?;
+            if let Some(example) = entry.examples.first() {
+                let p = example.to_vec2();
+                write!(f, "  ({:.3}, {:.3})", p.x, p.y)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// 頂点の近傍。境界上の頂点ではNone
+fn configuration_at(mesh: &PlanarMesh, vertex: VertexId) -> Option<VertexConfiguration> {
+    let point = mesh.vertex(vertex);
+    let mut corners = vec![];
+    for edge in mesh.edges_around_vertex(vertex) {
+        let spectre = &mesh.face(mesh.half_edge(edge).face?).spectre;
+        let corner = spectre.vertices().iter().position(|&p| p == point)?;
+        corners.push((corner as u8, spectre.orientation()));
+    }
+    Some(VertexConfiguration::new(&corners))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::Angle,
+    };
+
+    fn atlas(rotation: Angle) -> VertexAtlas {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, rotation, 3);
+        let mesh = PlanarMesh::from_spectres(cluster.spectres_in(cluster.bbox()));
+        VertexAtlas::collect(&mesh, 3)
+    }
+
+    #[test]
+    fn test_vertex_atlas() {
+        let atlas = atlas(Angle::ZERO);
+        assert!(atlas.total() > 0);
+        assert_eq!(
+            atlas.entries().iter().map(|e| e.count).sum::<usize>(),
+            atlas.total()
+        );
+        for entry in atlas.entries() {
+            // 内部の頂点は周りがタイルで埋まっている
+            assert_eq!(entry.configuration.angle_sum(), 12);
+            assert!(!entry.examples.is_empty() && entry.examples.len() <= 3);
+        }
+        let frequencies: f64 = atlas
+            .entries()
+            .iter()
+            .map(|e| atlas.frequency(&e.configuration))
+            .sum();
+        assert!((frequencies - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_configuration_count() {
+        // Spectreのタイリングの頂点近傍は33種類。レベル6（約160万頂点）まで集計しても増えない。
+        // 鏡像のタイルはなく、一つの頂点には2〜4枚のタイルが集まる
+        let configurations = |atlas: &VertexAtlas| {
+            atlas
+                .entries()
+                .iter()
+                .map(|e| e.configuration.clone())
+                .collect::<Vec<_>>()
+        };
+        let mut small = configurations(&atlas(Angle::ZERO));
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor3, HexVec::ZERO, Angle::new(1), 4);
+        let mesh = PlanarMesh::from_spectres(cluster.spectres_in(cluster.bbox()));
+        let mut large = configurations(&VertexAtlas::collect(&mesh, 1));
+        small.sort();
+        large.sort();
+        assert_eq!(small.len(), 33);
+        assert_eq!(small, large);
+        for configuration in &small {
+            assert!((2..=4).contains(&configuration.corners().len()));
+            assert!(configuration
+                .corners()
+                .iter()
+                .all(|c| !c.orientation.reflected));
+        }
+
+        // 同じ角を持つ3枚のタイルが120度ずつ回って集まる近傍
+        let three_fold = |corner: u8| {
+            VertexConfiguration::new(
+                &[0, 4, 8].map(|r| (corner, Orientation::rotation(Angle::new(r)))),
+            )
+        };
+        assert!(small.contains(&three_fold(4)));
+        assert!(small.contains(&three_fold(6)));
+        assert_eq!(small.iter().filter(|c| c.corners().len() == 4).count(), 2);
+    }
+
+    #[test]
+    fn test_rotation_invariant() {
+        // パッチ全体を回転しても同じ近傍として数えられる
+        let counts = |atlas: &VertexAtlas| {
+            atlas
+                .entries()
+                .iter()
+                .map(|e| (e.configuration.clone(), e.count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counts(&atlas(Angle::ZERO)), counts(&atlas(Angle::new(5))));
+    }
+
+    #[test]
+    fn test_cyclic_start() {
+        let a = Orientation::rotation(Angle::new(2));
+        let b = Orientation::rotation(Angle::new(7));
+        assert_eq!(
+            VertexConfiguration::new(&[(3, a), (8, b)]),
+            VertexConfiguration::new(&[(8, b), (3, a)])
+        );
+        assert_ne!(
+            VertexConfiguration::new(&[(3, a), (8, b)]),
+            VertexConfiguration::new(&[(3, b), (8, a)])
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::{fs, io, path::Path};
+
+use glam::Vec2;
+use tiny_skia::{Color, Pixmap, Transform};
+
+use crate::{
+    raster::{draw_tiles, group_color, RasterStyle},
+    tiles::{Anchor, Skeleton},
+    utils::Aabb,
+};
+
+/// Supertileが子に分かれる様子を見せるアニメーション
+///
+/// 1周期で、親のClusterを写した画面が、子の一つ（slot）を同じ位置・大きさで写した画面へ移る。
+/// 画面はアンカー1からアンカー3へのベクトルを揃える相似変換を補間して動くので、
+/// 親と子の対応するアンカーの周りを回転しながら拡大する。子は次の周期の親になり、
+/// level 0の子に着いたら根に戻って繰り返す
+pub struct SubstitutionAnimation {
+    /// 根から辿った各levelのCluster。最後はlevel 0
+    keyframes: Vec<Keyframe>,
+    slot: u8,
+}
+
+/// Clusterと、それを画面に写すための基準
+#[derive(Clone, Copy)]
+struct Keyframe {
+    skeleton: Skeleton,
+    /// アンカー1の位置
+    origin: Vec2,
+    /// アンカー1からアンカー3へのベクトル（複素数として扱う）
+    axis: Vec2,
+    /// origin、axisを基準にした座標での画面の中心
+    center: Vec2,
+    /// axisの長さを1とした画面の幅
+    width: f32,
+}
+
+impl Keyframe {
+    /// Clusterの周りに空ける余白の割合
+    const MARGIN: f32 = 1.1;
+
+    fn new(skeleton: Skeleton) -> Self {
+        let origin = skeleton.coordinate(Anchor::Anchor1).to_vec2();
+        let axis = skeleton.coordinate(Anchor::Anchor3).to_vec2() - origin;
+        let bbox = skeleton.bbox(false);
+        let local = |p: Vec2| divide(p - origin, axis);
+        Self {
+            skeleton,
+            origin,
+            axis,
+            center: local((bbox.min + bbox.max) * 0.5),
+            width: (bbox.max - bbox.min).max_element() / axis.length() * Self::MARGIN,
+        }
+    }
+}
+
+/// ある時刻に画面に写す範囲
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationView {
+    /// 画面の中心に写すワールド座標
+    pub center: Vec2,
+    /// 画面の回転（ラジアン）
+    pub rotation: f32,
+    /// 画面の幅に写すワールド座標の長さ
+    pub width: f32,
+    /// 何周期目か。親は根からslotを周期の数だけ辿ったCluster
+    pub cycle: usize,
+}
+
+impl SubstitutionAnimation {
+    /// rootから子のslot（0〜6、a〜g）を辿っていくアニメーション
+    ///
+    /// 周期が一つもなくなるので、rootはlevel 1以上でなければならない
+    pub fn new(root: Skeleton, slot: u8) -> Self {
+        assert!(slot < 7, "the mystic child (h) cannot be followed");
+        assert!(root.level() > 0, "a level 0 root has no child to follow");
+        let mut keyframes = vec![Keyframe::new(root)];
+        let mut skeleton = root;
+        while skeleton.level() > 0 {
+            skeleton = skeleton.split_into_skeletons()[slot as usize];
+            keyframes.push(Keyframe::new(skeleton));
+        }
+        Self { keyframes, slot }
+    }
+
+    /// 根に戻るまでの周期の数
+    pub fn cycles(&self) -> usize {
+        self.keyframes.len() - 1
+    }
+
+    /// cycle周期目の親の、根からの道順
+    pub fn path(&self, cycle: usize) -> Vec<u8> {
+        vec![self.slot; cycle]
+    }
+
+    /// 時刻time（周期単位）に写す範囲。各周期の始めと終わりでは動きが緩やかになる
+    pub fn view(&self, time: f32) -> AnimationView {
+        let time = time.rem_euclid(self.cycles() as f32);
+        let cycle = (time as usize).min(self.cycles() - 1);
+        let t = time - cycle as f32;
+        let t = t * t * (3.0 - 2.0 * t);
+        let from = &self.keyframes[cycle];
+        let to = &self.keyframes[cycle + 1];
+
+        // fromの基準をtoの基準へ写す相似変換 z ↦ m(z - fixed) + fixed をt乗する
+        let m = divide(to.axis, from.axis);
+        let fixed = divide(to.origin - m.rotate(from.origin), Vec2::X - m);
+        let m_t = Vec2::from_angle(m.to_angle() * t) * m.length().powf(t);
+        let origin = fixed + m_t.rotate(from.origin - fixed);
+        let axis = m_t.rotate(from.axis);
+
+        let center = from.center.lerp(to.center, t);
+        let width = from.width * (to.width / from.width).powf(t);
+        AnimationView {
+            center: origin + axis.rotate(center),
+            rotation: -axis.to_angle(),
+            width: axis.length() * width,
+            cycle,
+        }
+    }
+
+    /// 時刻timeの画面をCPUで描く。親のClusterのタイルを、子の位置（a〜h）で色分けする
+    pub fn render_frame(&self, time: f32, width: u32, height: u32, style: &RasterStyle) -> Pixmap {
+        let view = self.view(time);
+        let parent = self.keyframes[view.cycle].skeleton;
+        let scale = width as f32 / view.width;
+        // 画面の対角線が収まる範囲のタイルを描く
+        let radius = (width as f32).hypot(height as f32) * 0.5 / scale;
+        let region = Aabb::from_min_max(view.center - radius, view.center + radius);
+
+        let (sin, cos) = view.rotation.sin_cos();
+        let (sx, kx, ky, sy) = (scale * cos, -scale * sin, -scale * sin, -scale * cos);
+        let transform = Transform::from_row(
+            sx,
+            ky,
+            kx,
+            sy,
+            width as f32 * 0.5 - (sx * view.center.x + kx * view.center.y),
+            height as f32 * 0.5 - (ky * view.center.x + sy * view.center.y),
+        );
+
+        let mut pixmap = Pixmap::new(width, height).expect("image size must be non-zero");
+        let [r, g, b, a] = style.background;
+        pixmap.fill(Color::from_rgba8(r, g, b, a));
+        let cluster = parent.to_spectre_cluster(&region);
+        let mut tiles = vec![];
+        let mut iter = cluster.spectres_in(region);
+        while let Some(tile) = iter.next() {
+            let Some(address) = iter.address() else {
+                continue;
+            };
+            let group = address.slots()[0];
+            tiles.push((tile, group_color(tile, group)));
+        }
+        draw_tiles(&mut pixmap, tiles, transform, &region, style);
+        pixmap
+    }
+
+    /// 全周期をframes_per_cycle枚ずつdirにframe_00000.pngから連番で書き出す
+    pub fn write_frames(
+        &self,
+        dir: &Path,
+        frames_per_cycle: usize,
+        width: u32,
+        height: u32,
+        style: &RasterStyle,
+    ) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for frame in 0..self.cycles() * frames_per_cycle {
+            let time = frame as f32 / frames_per_cycle as f32;
+            self.render_frame(time, width, height, style)
+                .save_png(dir.join(format!("frame_{:05}.png", frame)))
+                .map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
+
+/// 複素数としての商
+fn divide(a: Vec2, b: Vec2) -> Vec2 {
+    Vec2::new(b.x, -b.y).rotate(a) / b.length_squared()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::utils::{Angle, HexVec};
+
+    fn animation() -> SubstitutionAnimation {
+        SubstitutionAnimation::new(
+            Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 3),
+            0,
+        )
+    }
+
+    /// 角度の差を(-π, π]に揃える
+    fn angle_difference(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(2.0 * PI);
+        if d > PI {
+            d - 2.0 * PI
+        } else {
+            d
+        }
+    }
+
+    fn close(a: &AnimationView, b: &AnimationView) -> bool {
+        (a.center - b.center).length() < 1e-2 * a.width
+            && angle_difference(a.rotation, b.rotation).abs() < 1e-3
+            && (a.width / b.width - 1.0).abs() < 1e-3
+    }
+
+    #[test]
+    fn test_cycles_connect() {
+        let animation = animation();
+        assert_eq!(animation.cycles(), 3);
+        for cycle in 1..3 {
+            let time = cycle as f32;
+            let before = animation.view(time - 1e-4);
+            let after = animation.view(time);
+            assert_eq!((before.cycle, after.cycle), (cycle - 1, cycle));
+            assert!(close(&before, &after), "{:?} {:?}", before, after);
+        }
+        // 最後は根に戻る
+        assert!(close(&animation.view(3.0), &animation.view(0.0)));
+        assert_eq!(animation.path(2), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_view_fits_cluster() {
+        let animation = animation();
+        let view = animation.view(0.0);
+        let bbox = animation.keyframes[0].skeleton.bbox(false);
+        assert!(bbox.contains(view.center));
+        assert!(view.width >= (bbox.max - bbox.min).max_element());
+        // 周期の終わりには子が同じように写る
+        let child = animation.keyframes[1].skeleton.bbox(false);
+        assert!(child.contains(animation.view(0.9999).center));
+        assert!(animation.view(0.9999).width < view.width);
+    }
+
+    #[test]
+    fn test_render_frame() {
+        let animation = animation();
+        let style = RasterStyle {
+            background: [0, 0, 0, 0],
+            stroke: None,
+        };
+        let first = animation.render_frame(0.0, 64, 64, &style);
+        let middle = animation.render_frame(0.5, 64, 64, &style);
+        // 画面の多くはタイルで塗られ、時刻が進むと画面が変わる
+        let covered = first.pixels().iter().filter(|p| p.alpha() > 0).count();
+        assert!(covered > first.pixels().len() / 4);
+        assert_ne!(first.data(), middle.data());
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use spectre::{
+    animation::SubstitutionAnimation,
+    raster::RasterStyle,
+    tiles::{Anchor, Skeleton},
+    utils::{Angle, HexVec},
+};
+
+/// Supertileが子に分かれていくアニメーションを連番PNGに書き出す
+///
+/// ```sh
+/// cargo run --release --bin substitution_frames -- --level 4 --frames 120 --out frames
+/// ```
+fn main() {
+    let mut level: usize = 4;
+    let mut frames: usize = 120;
+    let mut size: u32 = 720;
+    let mut out = PathBuf::from("frames");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        let parsed = match arg.as_str() {
+            "--level" => value.and_then(|v| v.parse().ok()).map(|v| level = v),
+            "--frames" => value.and_then(|v| v.parse().ok()).map(|v| frames = v),
+            "--size" => value.and_then(|v| v.parse().ok()).map(|v| size = v),
+            "--out" => value.map(|v| out = v.into()),
+            _ => None,
+        };
+        if parsed.is_none() || level == 0 || frames == 0 || size == 0 {
+            eprintln!(
+                "usage: substitution_frames [--level LEVEL] [--frames PER_CYCLE] [--size PIXELS] [--out DIR]"
+            );
+            std::process::exit(2);
+        }
+    }
+    let root = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, level);
+    let animation = SubstitutionAnimation::new(root, 0);
+    if let Err(e) = animation.write_frames(&out, frames, size, size, &RasterStyle::default()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,26 @@
+/// 地図タイルをlocalhostで配信する
+///
+/// ```sh
+/// cargo run --release --features server --bin tile_server -- --port 8080
+/// ```
+fn main() {
+    let mut port: u16 = 8080;
+    let mut cache: usize = 1024;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        let parsed = match arg.as_str() {
+            "--port" => value.and_then(|v| v.parse().ok()).map(|v| port = v),
+            "--cache" => value.and_then(|v| v.parse().ok()).map(|v| cache = v),
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("usage: tile_server [--port PORT] [--cache TILES]");
+            std::process::exit(2);
+        }
+    }
+    if let Err(e) = spectre::tile_server::serve(port, cache) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
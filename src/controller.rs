@@ -1,13 +1,11 @@
-use glam::Vec2;
-use lyon_tessellation::{
-    geom::Point, geometry_builder::simple_builder, path::Path, FillOptions, FillTessellator,
-    VertexBuffers,
-};
+use glam::{Mat4, Vec2, Vec4};
 use mikage::InstanceVertex;
 
 use crate::{
-    tiles::{Anchor, Skeleton, Spectre, SpectreCluster, SpectreIter},
-    utils::{Aabb, Angle, HexVec},
+    analysis::SupertileType,
+    mesh::tessellate,
+    tiles::{Anchor, MemoryUsage, Skeleton, Spectre, SpectreCluster, SpectreIter, TileAddress},
+    utils::{Aabb, Angle, ConvexPolygon, HexVec, Region},
 };
 
 #[repr(C)]
@@ -15,68 +13,173 @@ use crate::{
 pub struct SpectreInstance {
     pub position: [f32; 3],
     pub angle: f32,
+    /// 鏡映されたタイルなら1.0、そうでなければ0.0
+    pub reflected: f32,
+    /// 色分けするグループの番号。負なら色分けしない
+    pub group: f32,
+    /// 選択したタイルとの近さ。3なら選択したタイル、2なら同じ親、1なら同じ祖父母に含まれる
+    pub highlight: f32,
 }
 
 impl InstanceVertex for SpectreInstance {
     fn vertex_attributes() -> Vec<mikage::wgpu::VertexAttribute> {
-        vec![mikage::wgpu::VertexAttribute {
-            format: mikage::wgpu::VertexFormat::Float32x4,
-            offset: 0,
-            shader_location: 2,
-        }]
+        vec![
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 2,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32,
+                offset: 16,
+                shader_location: 3,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32,
+                offset: 20,
+                shader_location: 4,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32,
+                offset: 24,
+                shader_location: 5,
+            },
+        ]
+    }
+}
+
+/// タイルのシェーダーに渡す画面の変換
+///
+/// 画面を動かしてもインスタンスを作り直さずに済むよう、インスタンスとは別にユニフォームで渡す
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ViewUniform {
+    /// ワールド座標からクリップ座標への変換
+    pub view_proj: [[f32; 4]; 4],
+}
+
+impl ViewUniform {
+    pub fn new(view: &ViewTransform, screen: &Aabb) -> Self {
+        Self {
+            view_proj: view.view_proj(screen).to_cols_array_2d(),
+        }
+    }
+}
+
+/// ワールド座標から画面（カメラ）の座標への相似変換
+///
+/// 原点を中心にrotationだけ回転し、scale倍してからoffsetだけ平行移動する
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewTransform {
+    /// 回転（ラジアン）
+    pub rotation: f32,
+    pub scale: f32,
+    pub offset: Vec2,
+}
+
+impl ViewTransform {
+    /// ワールド座標のcenterを画面のscreen_centerに写し、ワールド座標の長さwidthを画面のscreen_widthにする変換
+    pub fn looking_at(
+        center: Vec2,
+        rotation: f32,
+        width: f32,
+        screen_center: Vec2,
+        screen_width: f32,
+    ) -> Self {
+        let scale = screen_width / width;
+        Self {
+            rotation,
+            scale,
+            offset: screen_center - Vec2::from_angle(rotation).rotate(center) * scale,
+        }
+    }
+
+    pub fn apply(&self, world: Vec2) -> Vec2 {
+        Vec2::from_angle(self.rotation).rotate(world) * self.scale + self.offset
+    }
+
+    pub fn inverse_apply(&self, screen: Vec2) -> Vec2 {
+        Vec2::from_angle(-self.rotation).rotate((screen - self.offset) / self.scale)
+    }
+
+    /// ワールド座標をクリップ座標に写す行列。screenはカメラの表示範囲で、クリップ座標の-1〜1に写る
+    pub fn view_proj(&self, screen: &Aabb) -> Mat4 {
+        let origin = self.apply(Vec2::ZERO);
+        let x = self.apply(Vec2::X) - origin;
+        let y = self.apply(Vec2::Y) - origin;
+        let view = Mat4::from_cols(
+            x.extend(0.0).extend(0.0),
+            y.extend(0.0).extend(0.0),
+            Vec4::Z,
+            origin.extend(0.0).extend(1.0),
+        );
+        let (min, max) = (screen.min, screen.max);
+        Mat4::orthographic_rh(min.x, max.x, min.y, max.y, -1.0, 1.0) * view
+    }
+
+    /// 画面の範囲に写るワールド座標の範囲
+    pub fn viewport(&self, screen: &Aabb) -> ConvexPolygon {
+        let corners = [
+            screen.min,
+            Vec2::new(screen.max.x, screen.min.y),
+            screen.max,
+            Vec2::new(screen.min.x, screen.max.y),
+        ];
+        ConvexPolygon::new(corners.iter().map(|&p| self.inverse_apply(p)).collect())
     }
 }
 
 /// Spectreタイルのメッシュを生成する
 pub fn create_spectre_mesh() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
-    let mut path_builder = Path::builder();
     let points = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO).vertices();
     let points_vec2: Vec<Vec2> = points.iter().map(|p| p.to_vec2()).collect();
-    path_builder.begin(Point::new(points_vec2[0].x, points_vec2[0].y));
-    for point in points_vec2.iter().skip(1) {
-        path_builder.line_to(Point::new(point.x, point.y));
-    }
-    path_builder.close();
-    let path = path_builder.build();
-
-    let mut buffers: VertexBuffers<Point<f32>, u16> = VertexBuffers::new();
-    {
-        let mut vertex_builder = simple_builder(&mut buffers).with_inverted_winding(); // 反時計回りにする
-        let mut tessellator = FillTessellator::new();
-        let result =
-            tessellator.tessellate_path(&path, &FillOptions::default(), &mut vertex_builder);
-        assert!(result.is_ok());
-    }
+    let (vertices, indices) = tessellate(&points_vec2);
 
-    let positions: Vec<[f32; 3]> = buffers.vertices.iter().map(|p| [p.x, p.y, 0.0]).collect();
-    let normals: Vec<[f32; 3]> = buffers.vertices.iter().map(|_| [0.0, 0.0, 1.0]).collect();
-    let indices: Vec<u32> = buffers.indices.iter().map(|&i| i as u32).collect();
+    let positions: Vec<[f32; 3]> = vertices.iter().map(|p| [p.x, p.y, 0.0]).collect();
+    let normals: Vec<[f32; 3]> = vertices.iter().map(|_| [0.0, 0.0, 1.0]).collect();
 
     (positions, normals, indices)
 }
 
 #[inline]
-fn to_instance(spectre: &Spectre) -> SpectreInstance {
+fn to_instance(spectre: &Spectre, group: Option<u8>, highlight: u8) -> SpectreInstance {
     let anchor_pos = spectre.coordinate(Anchor::Anchor1).to_vec2();
     SpectreInstance {
         position: [anchor_pos.x, anchor_pos.y, 0.0],
         angle: spectre.rotation().to_radians(),
+        reflected: if spectre.is_reflected() { 1.0 } else { 0.0 },
+        group: group.map_or(-1.0, f32::from),
+        highlight: f32::from(highlight),
     }
 }
 
 pub struct TilesController {
-    spectres: Box<SpectreCluster>,
+    spectres: SpectreCluster,
+    /// 読み込んだタイルに使ってよいメモリの上限（バイト）。Noneなら制限しない
+    memory_budget: Option<usize>,
+    /// updateの呼び出し回数。Clusterが最後に表示された時期の記録に使う
+    frame: u64,
+    /// 直前のupdateでロードした範囲のbbox。これと交差するClusterはアンロードしない
+    ///
+    /// 有界でない範囲をロードしたときはNoneで、どのClusterもアンロードしない
+    load: Option<Aabb>,
 }
 
 impl TilesController {
     /// クラスターの最大レベル。これ以上拡張しようとすると座標がi32の範囲を超えるため。
     const MAX_CLUSTER_LEVEL: usize = 18;
+    /// メモリ使用量の上限の既定値
+    const DEFAULT_MEMORY_BUDGET: usize = 128 * 1024 * 1024;
 
     pub fn new() -> Self {
-        let skeleton = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 5, None)
+        let spectres = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 5)
             .to_spectre_cluster(&Aabb::NULL);
-        let spectres = Box::new(skeleton);
-        Self { spectres }
+        Self {
+            spectres,
+            memory_budget: Some(Self::DEFAULT_MEMORY_BUDGET),
+            frame: 0,
+            load: Some(Aabb::NULL),
+        }
     }
 
     pub fn expand(&mut self) {
@@ -86,67 +189,311 @@ impl TilesController {
         }
 
         // 現在のSpectreClusterをAまたはFとして上位のSpectreClusterを生成する
-        let mut spectres = Box::new(
-            Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 1, None)
-                .to_spectre_cluster(&Aabb::NULL),
-        );
-        std::mem::swap(&mut self.spectres, &mut spectres);
+        let placeholder = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 1)
+            .to_spectre_cluster(&Aabb::NULL);
+        let spectres = std::mem::replace(&mut self.spectres, placeholder);
         if spectres.level().is_multiple_of(2) {
             tracing::info!("Expand from A");
-            self.spectres = Box::new(SpectreCluster::with_child_a(*spectres));
+            self.spectres = SpectreCluster::with_child_a(spectres);
         } else {
             tracing::info!("Expand from F");
-            self.spectres = Box::new(SpectreCluster::with_child_f(*spectres));
+            self.spectres = SpectreCluster::with_child_f(spectres);
         }
     }
 
-    pub fn update(&mut self, bbox: &Aabb) {
-        self.spectres.update(bbox);
+    /// 表示範囲からmargins.loadの範囲にあるClusterをロードし、
+    /// margins.unloadより離れたClusterをアンロードする
+    pub fn update<R: Region>(&mut self, region: &R, margins: UpdateMargins) {
+        self.frame += 1;
+        let load = region.expanded(margins.load);
+        let unload = region.expanded(margins.unload);
+        self.spectres.update(&load, &unload, self.frame);
+        self.load = load.bbox();
+        self.enforce_memory_budget();
     }
 
-    pub fn spectres_in(&self, bbox: &Aabb) -> SpectreIter<'_> {
-        self.spectres.spectres_in(*bbox)
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        self.memory_budget = budget;
+        self.enforce_memory_budget();
+    }
+
+    /// 読み込まれているタイルの数とメモリ量
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new(0, size_of::<SpectreCluster>()) + self.spectres.memory_usage()
+    }
+
+    /// メモリ使用量が上限を超えていれば、最も長く表示されていないClusterから順にSkeletonに戻す
+    ///
+    /// 直前のupdateでロードした範囲と交差するClusterは戻さないため、上限を下回らないこともある
+    fn enforce_memory_budget(&mut self) {
+        let (Some(budget), Some(load)) = (self.memory_budget, self.load) else {
+            return;
+        };
+        let before = self.memory_usage();
+        if before.bytes <= budget {
+            return;
+        }
+
+        let mut stale = vec![];
+        self.spectres.collect_stale(self.frame, &load, &mut stale);
+        stale.sort_unstable_by_key(|&(last_visible, _)| last_visible);
+        let mut excess = before.bytes - budget;
+        let mut threshold = None;
+        for (last_visible, bytes) in stale {
+            threshold = Some(last_visible);
+            if bytes >= excess {
+                break;
+            }
+            excess -= bytes;
+        }
+        let Some(threshold) = threshold else {
+            tracing::debug!("Memory budget exceeded but nothing to evict: {:?}", before);
+            return;
+        };
+
+        self.spectres.evict(threshold, &load);
+        let after = self.memory_usage();
+        tracing::info!(
+            "Evicted clusters: tiles {} -> {}, bytes {} -> {}",
+            before.tiles,
+            after.tiles,
+            before.bytes,
+            after.bytes
+        );
+    }
+
+    pub fn spectres_in<R: Region + Clone>(&self, region: &R) -> SpectreIter<'_, R> {
+        self.spectres.spectres_in(region.clone())
+    }
+
+    /// regionと交差するタイルの数を列挙せずに数える
+    pub fn count_in<R: Region>(&self, region: &R) -> u64 {
+        self.spectres.count_in(region)
     }
 
     pub fn cluster_bbox(&self) -> Aabb {
         self.spectres.bbox()
     }
+
+    pub fn level(&self) -> usize {
+        self.spectres.level()
+    }
+
+    /// 根のClusterの骨格
+    pub fn skeleton(&self) -> Skeleton {
+        self.spectres.to_skeleton()
+    }
+
+    /// 根がlevelだったときの根のClusterの、今の根からの道順
+    ///
+    /// expandでは偶数levelの根はAとして、奇数levelの根はFとして包まれる
+    pub fn expansion_path(&self, level: usize) -> Vec<u8> {
+        (level..self.level())
+            .rev()
+            .map(|level| if level.is_multiple_of(2) { 0 } else { 5 })
+            .collect()
+    }
+
+    /// 根がlevelだったときの根からのaddressを、今の根からのaddressにする
+    pub fn rerooted(&self, level: usize, address: &TileAddress) -> TileAddress {
+        let mut slots = self.expansion_path(level);
+        slots.extend_from_slice(address.slots());
+        TileAddress::new(slots, address.mystic_part())
+    }
+
+    /// pointを含むタイルを調べる。読み込まれていない範囲のタイルは見つからない
+    pub fn pick(&self, point: Vec2) -> Option<TileInspection> {
+        // 大きさのないAabbは何とも交差しないので、pointの周りの小さな範囲で探す
+        let region = Aabb::from_min_max(point - Vec2::splat(1e-3), point + Vec2::splat(1e-3));
+        let mut iter = self.spectres_in(&region);
+        while let Some(spectre) = iter.next() {
+            if spectre.contains(point) {
+                let address = iter.address()?;
+                return Some(TileInspection {
+                    vertices: spectre.vertices(),
+                    rotation: spectre.rotation(),
+                    reflected: spectre.is_reflected(),
+                    types: SupertileType::along(address.slots()),
+                    address,
+                    root_level: self.level(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// 選択したタイルの情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileInspection {
+    pub vertices: Vec<HexVec>,
+    pub rotation: Angle,
+    pub reflected: bool,
+    /// 選択したときの根のClusterからのaddress
+    pub address: TileAddress,
+    /// addressの各段の種類。最後はタイル（Mysticの半分ならMysticLike）自身
+    pub types: Vec<SupertileType>,
+    /// 選択したときの根のlevel
+    pub root_level: usize,
+}
+
+impl TileInspection {
+    /// タイルを含むlevel 1のClusterの種類
+    pub fn parent(&self) -> Option<SupertileType> {
+        self.types.iter().rev().nth(1).copied()
+    }
+
+    /// タイルを含むlevel 2のClusterの種類
+    pub fn grandparent(&self) -> Option<SupertileType> {
+        self.types.iter().rev().nth(2).copied()
+    }
+
+    /// Mysticの半分かどうか
+    pub fn in_mystic(&self) -> bool {
+        self.address.mystic_part().is_some()
+    }
+
+    /// 頂点を除いた情報。画面のパネルにはこれを書く
+    pub fn summary(&self) -> String {
+        let name = |t: Option<SupertileType>| t.map_or("-".to_string(), |t| format!("{:?}", t));
+        format!(
+            "address: {}\nangle: {} ({}°){}\nparent: {}, grandparent: {}\nhalf of a mystic: {}",
+            self.address,
+            self.rotation.value(),
+            self.rotation.value() as u32 * 30,
+            if self.reflected { ", reflected" } else { "" },
+            name(self.parent()),
+            name(self.grandparent()),
+            self.in_mystic()
+        )
+    }
+}
+
+impl std::fmt::Display for TileInspection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        write!(f, "vertices:")?;
+        for vertex in &self.vertices {
+            write!(f, " {}", vertex)?;
+        }
+        Ok(())
+    }
+}
+
+/// addressのタイルが選択したタイルにどれだけ近いか（SpectreInstance::highlight）
+fn highlight_level(address: &TileAddress, picked: &TileAddress) -> u8 {
+    if address == picked {
+        return 3;
+    }
+    let common = address
+        .slots()
+        .iter()
+        .zip(picked.slots())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Mysticのもう半分は同じ親に含まれる
+    let depth = picked.slots().len();
+    match depth - common {
+        0 | 1 => 2,
+        2 => 1,
+        _ => 0,
+    }
+}
+
+/// タイルをロード・アンロードする範囲の、表示範囲からのマージン
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UpdateMargins {
+    /// 表示範囲からこの距離以内に入ったClusterをロードする
+    pub load: f32,
+    /// 表示範囲からこの距離より離れたClusterをアンロードする。loadより大きくする
+    pub unload: f32,
+}
+
+impl UpdateMargins {
+    /// 表示範囲の大きさに対する比率で決める
+    pub fn relative_to(bbox: &Aabb, load: f32, unload: f32) -> Self {
+        let size = (bbox.max - bbox.min).max_element();
+        Self {
+            load: size * load,
+            unload: size * unload,
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct LastViewState {
-    /// カメラの表示範囲
-    pub bbox: Option<Aabb>,
+    /// 前のフレームで表示したもの
+    pub viewport: Option<ViewKey>,
     /// 前のフレームでタイルを拡大したかどうか
     pub expanded: bool,
 }
 
+/// タイルの表示を作り直すかどうかを決める、表示の状態
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewKey {
+    /// カメラの表示範囲（ワールド座標系）
+    pub viewport: ConvexPolygon,
+    /// 色分けするClusterの道順
+    pub grouping: Option<Vec<u8>>,
+    /// 選択したタイルの、今の根からのaddress
+    pub highlight: Option<TileAddress>,
+}
+
 /// カメラのビューに基づいてタイルの表示を更新する。
-/// bboxに変更がない場合はNoneを返す。
+/// viewportはワールド座標系での表示範囲で、画面が回転している場合は回転した長方形になる。
+/// groupingを与えると、その道順のClusterに含まれるタイルを子の位置（0〜7）で色分けする。
+/// highlightを与えると、そのaddressのタイルと、その親と祖父母に含まれるタイルを強調する。
+/// 表示範囲に変更がない場合はNoneを返す。
 pub fn update_tiles(
     controller: &mut TilesController,
     last_view: &mut LastViewState,
-    bbox: &Aabb,
+    viewport: &ConvexPolygon,
+    grouping: Option<&[u8]>,
+    highlight: Option<&TileAddress>,
 ) -> Option<Vec<SpectreInstance>> {
-    // 前フレームと同じbboxの場合は早期リターン
-    if let Some(last_bbox) = last_view.bbox
-        && last_bbox == *bbox
-        && !last_view.expanded
-    {
+    // 前フレームと同じ表示範囲の場合は早期リターン
+    let key = ViewKey {
+        viewport: viewport.clone(),
+        grouping: grouping.map(<[u8]>::to_vec),
+        highlight: highlight.cloned(),
+    };
+    if last_view.viewport.as_ref() == Some(&key) && !last_view.expanded {
         return None;
     }
-    last_view.bbox = Some(*bbox);
+    last_view.viewport = Some(key);
 
-    // bboxに含まれるタイルを取得してインスタンスデータを生成
-    controller.update(bbox);
-    let spectres = controller.spectres_in(bbox);
-    let instance_data: Vec<SpectreInstance> = spectres.map(to_instance).collect();
+    // 表示範囲に含まれるタイルを取得してインスタンスデータを生成
+    // 境界付近を往復したときに同じClusterを作り直さないよう、アンロードは表示範囲の半分だけ離れてから
+    let margins = UpdateMargins::relative_to(&viewport.bbox(), 0.0, 0.5);
+    controller.update(viewport, margins);
+    let mut spectres = controller.spectres_in(viewport);
+    let mut instance_data: Vec<SpectreInstance> = vec![];
+    while let Some(spectre) = spectres.next() {
+        let address = (grouping.is_some() || highlight.is_some())
+            .then(|| spectres.address())
+            .flatten();
+        let group = grouping.zip(address.as_ref()).and_then(|(path, address)| {
+            let slots = address.slots();
+            slots
+                .starts_with(path)
+                .then(|| slots.get(path.len()).copied())?
+        });
+        let level = highlight
+            .zip(address.as_ref())
+            .map_or(0, |(picked, address)| highlight_level(address, picked));
+        instance_data.push(to_instance(spectre, group, level));
+    }
 
     // expand判定
     last_view.expanded = false;
     let cluster_bbox = controller.cluster_bbox();
+    let bbox = viewport.bbox();
 
-    // A: クラスタのbboxがビューポートを余裕を持って包含していなければexpand
+    // A: クラスタのbboxが表示範囲のbboxを余裕を持って包含していなければexpand
     // パン時に欠けが見えないよう、ビューポートの50%分のマージンを確保
     let margin = (bbox.max - bbox.min) * 0.5;
     let viewport_outside = (bbox.min.x - margin.x) < cluster_bbox.min.x
@@ -174,3 +521,156 @@ pub fn update_tiles(
 
     Some(instance_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::HalfPlane;
+
+    /// 読み込んだタイルの一つの重心
+    fn loaded_point(controller: &mut TilesController) -> Vec2 {
+        let bbox = controller.cluster_bbox();
+        let center = (bbox.min + bbox.max) * 0.5;
+        let center = Aabb::from_min_max(center - Vec2::ONE, center + Vec2::ONE);
+        controller.update(
+            &center,
+            UpdateMargins {
+                load: 10.0,
+                unload: 20.0,
+            },
+        );
+        let vertices = controller
+            .spectres_in(&center.expanded(10.0))
+            .next()
+            .unwrap()
+            .vertices();
+        vertices.iter().map(|v| v.to_vec2()).sum::<Vec2>() / vertices.len() as f32
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut controller = TilesController::new();
+        let point = loaded_point(&mut controller);
+        let picked = controller.pick(point).unwrap();
+        assert_eq!(picked.vertices.len(), 14);
+        assert_eq!(picked.address.slots().len(), controller.level());
+        assert_eq!(picked.types.len(), picked.address.slots().len());
+        assert_eq!(picked.in_mystic(), picked.types.last().unwrap().is_mystic());
+        // 画面のパネルに書けない文字を含まない
+        assert!(picked
+            .to_string()
+            .lines()
+            .all(|line| line.chars().all(crate::font::has_glyph)));
+        assert!(picked.to_string().starts_with(&picked.summary()));
+
+        // 見つけたタイルがpointを含む
+        let region = Aabb::from_min_max(point - Vec2::ONE, point + Vec2::ONE);
+        let mut iter = controller.spectres_in(&region);
+        while let Some(spectre) = iter.next() {
+            if iter.address().as_ref() == Some(&picked.address) {
+                assert!(spectre.contains(point));
+            }
+        }
+    }
+
+    #[test]
+    fn test_view_proj() {
+        let view = ViewTransform::looking_at(Vec2::new(30.0, -20.0), 0.5, 10.0, Vec2::ZERO, 4.0);
+        let screen = Aabb::new(-3.0, -2.0, 5.0, 6.0);
+        let view_proj = view.view_proj(&screen);
+        // カメラの表示範囲の中心と角が、クリップ座標の中心と角に写る
+        for (camera, clip) in [
+            (Vec2::new(1.0, 2.0), Vec2::ZERO),
+            (screen.max, Vec2::ONE),
+            (screen.min, -Vec2::ONE),
+        ] {
+            let world = view.inverse_apply(camera);
+            let projected = view_proj * world.extend(0.0).extend(1.0);
+            assert!((projected.truncate().truncate() - clip).length() < 1e-4);
+            assert!((0.0..=1.0).contains(&projected.z));
+        }
+    }
+
+    #[test]
+    fn test_highlight_level() {
+        let picked = TileAddress::new(vec![0, 3, 7], Some(1));
+        assert_eq!(highlight_level(&picked, &picked), 3);
+        assert_eq!(
+            highlight_level(&TileAddress::new(vec![0, 3, 7], Some(0)), &picked),
+            2
+        );
+        assert_eq!(
+            highlight_level(&TileAddress::new(vec![0, 3, 2], None), &picked),
+            2
+        );
+        assert_eq!(
+            highlight_level(&TileAddress::new(vec![0, 4, 2], None), &picked),
+            1
+        );
+        assert_eq!(
+            highlight_level(&TileAddress::new(vec![1, 3, 7], Some(1)), &picked),
+            0
+        );
+    }
+
+    #[test]
+    fn test_memory_budget_keeps_visible_tiles() {
+        let mut controller = TilesController::new();
+        // unloadを広くして、離れた範囲のClusterもupdateでは戻さないようにする
+        let margins = UpdateMargins {
+            load: 0.0,
+            unload: 1.0e5,
+        };
+        let bbox = controller.cluster_bbox();
+        let center = (bbox.min + bbox.max) * 0.5;
+        let away = Aabb::from_min_max(center - 20.0, center + 20.0);
+        let view = Aabb::new(-20.0, -20.0, 20.0, 20.0);
+        controller.update(&away, margins);
+        controller.update(&view, margins);
+        let visible = controller.spectres_in(&view).count();
+        assert!(visible > 0);
+
+        // 上限を下回るまで戻しても、表示範囲のタイルは残る
+        let before = controller.memory_usage();
+        controller.set_memory_budget(Some(before.bytes / 2));
+        assert!(controller.memory_usage().bytes < before.bytes);
+        assert_eq!(controller.spectres_in(&view).count(), visible);
+        controller.update(&view, margins);
+        assert_eq!(controller.spectres_in(&view).count(), visible);
+    }
+
+    #[test]
+    fn test_memory_budget_with_half_plane() {
+        // 有界でない範囲をロードしたら、上限を超えても何も戻さない
+        let mut controller = TilesController::new();
+        let margins = UpdateMargins {
+            load: 0.0,
+            unload: 10.0,
+        };
+        controller.update(&HalfPlane::new(Vec2::ZERO, Vec2::X), margins);
+        let before = controller.memory_usage();
+        assert!(before.tiles > 0);
+        controller.set_memory_budget(Some(before.bytes / 2));
+        assert_eq!(controller.memory_usage(), before);
+    }
+
+    #[test]
+    fn test_rerooted() {
+        let mut controller = TilesController::new();
+        let level = controller.level();
+        let point = loaded_point(&mut controller);
+        let picked = controller.pick(point).unwrap();
+        controller.expand();
+        controller.expand();
+        let region = Aabb::from_min_max(point - Vec2::ONE, point + Vec2::ONE);
+        controller.update(
+            &region,
+            UpdateMargins {
+                load: 10.0,
+                unload: 20.0,
+            },
+        );
+        let after = controller.pick(point).unwrap();
+        assert_eq!(controller.rerooted(level, &picked.address), after.address);
+    }
+}
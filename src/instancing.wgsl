@@ -1,13 +1,21 @@
-#import mikage::scene_types
 #import mikage::math
 #import mikage::color_utils
 
-@group(0) @binding(0) var<uniform> scene: SceneUniform;
+// 画面の変換とカメラの投影を合わせた、ワールド座標からクリップ座標への変換
+struct ViewUniform {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> view_uniform: ViewUniform;
 
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) i_pos_angle: vec4<f32>,
+    @location(3) i_reflected: f32,
+    @location(4) i_group: f32,
+    // 選択したタイルとの近さ（3: 選択したタイル、2: 同じ親、1: 同じ祖父母、0: それ以外）
+    @location(5) i_highlight: f32,
 };
 
 struct VertexOutput {
@@ -18,26 +26,44 @@ struct VertexOutput {
 @vertex
 fn vertex(v: Vertex) -> VertexOutput {
     let angle = v.i_pos_angle.w;
+    let reflected = v.i_reflected > 0.5;
+
+    // 鏡映の適用（x軸について折り返してから回転する）
+    var local = v.position.xy;
+    if reflected {
+        local.y = -local.y;
+    }
 
     // 回転の適用
-    let rotated = rotate2d(v.position.xy, angle);
+    let rotated = rotate2d(local, angle);
 
     // 平行移動の適用
-    let world_pos = vec4<f32>(
+    let world_pos = vec2<f32>(
         rotated.x + v.i_pos_angle.x,
         rotated.y + v.i_pos_angle.y,
-        0.0,
-        1.0,
     );
 
     var out: VertexOutput;
-    out.clip_position = scene.view_proj * world_pos;
+    out.clip_position = view_uniform.view_proj * vec4<f32>(world_pos, 0.0, 1.0);
 
     // HSV coloring（hueはラジアン [0,TAU)、bevy/mikage共通）
-    let hue = 3.84 + sin(angle) * 0.333;
+    // 鏡映されたタイルは暖色系で描く
+    let base_hue = select(3.84, 0.52, reflected);
+    var hue = base_hue + sin(angle) * 0.333;
+    // 色分けするグループは8色の色相に振り分ける
+    if v.i_group >= 0.0 {
+        hue = v.i_group * 0.785 + sin(angle) * 0.1;
+    }
     let saturation = sin(1.666 * v.i_pos_angle.x) * 0.166 + 0.666;
     let value = sin(v.i_pos_angle.y) * 0.166 + 0.833;
-    out.color = vec4<f32>(hsv2rgb(hue, saturation, value), 1.0);
+    var color = hsv2rgb(hue, saturation, value);
+    // 選択したタイルは黄色に、その親と祖父母は白に寄せる
+    if v.i_highlight > 2.5 {
+        color = mix(color, vec3<f32>(1.0, 0.85, 0.1), 0.8);
+    } else if v.i_highlight > 0.5 {
+        color = mix(color, vec3<f32>(1.0, 1.0, 1.0), v.i_highlight * 0.2);
+    }
+    out.color = vec4<f32>(color, 1.0);
 
     return out;
 }
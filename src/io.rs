@@ -0,0 +1,18 @@
+mod dxf;
+mod figure;
+mod hpgl;
+mod json;
+mod polygon;
+mod postscript;
+mod svg;
+mod tikz;
+mod tool_path;
+
+pub use dxf::write_dxf;
+pub use hpgl::write_hpgl;
+pub use json::{read_json, write_json};
+pub use polygon::{tiles_from_polygons, ImportError, ImportOptions};
+pub use postscript::write_eps;
+pub use svg::{read_svg, write_svg};
+pub use tikz::write_tikz;
+pub use tool_path::{tool_paths, travel_length, LengthUnit, PlotOptions};
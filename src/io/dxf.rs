@@ -0,0 +1,352 @@
+use std::fmt::Write;
+
+use super::{tool_path::tool_paths, LengthUnit, PlotOptions};
+use crate::tiles::Spectre;
+
+/// タイルの辺をDXF(R2000)のLWPOLYLINEとして書き出す
+///
+/// 共有する辺は一度だけ書き、切断の順に並べる。単位は$INSUNITSに記録する。
+/// R2000で必要な、ハンドルを持つ表・ブロック・辞書も最小限だけ書く
+pub fn write_dxf(tiles: &[Spectre], options: &PlotOptions) -> String {
+    let units = match options.unit {
+        LengthUnit::Inch => 1,
+        LengthUnit::Millimeter => 4,
+        LengthUnit::Centimeter => 5,
+    };
+    let mut body = Dxf::default();
+    body.section("CLASSES");
+    body.end_section();
+
+    body.section("TABLES");
+    body.table("VPORT", &[], |_| {});
+    body.table("LTYPE", &["ByBlock", "ByLayer", "Continuous"], |dxf| {
+        dxf.group(3, &"");
+        dxf.group(72, &65);
+        dxf.group(73, &0);
+        dxf.group(40, &0.0);
+    });
+    body.table("LAYER", &["0", LAYER], |dxf| {
+        dxf.group(62, &7);
+        dxf.group(6, &"Continuous");
+    });
+    body.table("STYLE", &["Standard"], |dxf| {
+        dxf.group(40, &0.0);
+        dxf.group(41, &1.0);
+        dxf.group(50, &0.0);
+        dxf.group(71, &0);
+        dxf.group(42, &2.5);
+        dxf.group(3, &"txt");
+        dxf.group(4, &"");
+    });
+    body.table("VIEW", &[], |_| {});
+    body.table("UCS", &[], |_| {});
+    body.table("APPID", &["ACAD"], |_| {});
+    body.table("DIMSTYLE", &["Standard"], |_| {});
+    let block_records = ["*Model_Space", "*Paper_Space"];
+    let records = body.table("BLOCK_RECORD", &block_records, |_| {});
+    body.end_section();
+
+    body.section("BLOCKS");
+    for (name, &record) in block_records.into_iter().zip(&records) {
+        body.entity("BLOCK", record);
+        if name == "*Paper_Space" {
+            body.group(67, &1);
+        }
+        body.group(8, &"0");
+        body.group(100, &"AcDbBlockBegin");
+        body.group(2, &name);
+        body.group(70, &0);
+        for code in [10, 20, 30] {
+            body.group(code, &0.0);
+        }
+        body.group(3, &name);
+        body.group(1, &"");
+        body.entity("ENDBLK", record);
+        body.group(8, &"0");
+        body.group(100, &"AcDbBlockEnd");
+    }
+    body.end_section();
+
+    body.section("ENTITIES");
+    let model_space = records[0];
+    for path in tool_paths(tiles, options) {
+        let closed = path.len() > 2 && path.first() == path.last();
+        let vertices = if closed {
+            &path[..path.len() - 1]
+        } else {
+            &path[..]
+        };
+        body.entity("LWPOLYLINE", model_space);
+        body.group(8, &LAYER);
+        body.group(100, &"AcDbPolyline");
+        body.group(90, &vertices.len());
+        body.group(70, &(closed as u8));
+        for [x, y] in vertices {
+            body.group(10, &format_args!("{:.6}", x));
+            body.group(20, &format_args!("{:.6}", y));
+        }
+    }
+    body.end_section();
+
+    // 根の辞書と、空のグループの辞書
+    body.section("OBJECTS");
+    let root = body.handle();
+    let groups = body.handle();
+    body.group(0, &"DICTIONARY");
+    body.group(5, &Handle(root));
+    body.group(330, &Handle(0));
+    body.group(100, &"AcDbDictionary");
+    body.group(281, &1);
+    body.group(3, &"ACAD_GROUP");
+    body.group(350, &Handle(groups));
+    body.group(0, &"DICTIONARY");
+    body.group(5, &Handle(groups));
+    body.group(330, &Handle(root));
+    body.group(100, &"AcDbDictionary");
+    body.group(281, &1);
+    body.end_section();
+    body.group(0, &"EOF");
+
+    // $HANDSEEDは全てのハンドルを割り当ててから決まるので、HEADERは最後に書いて前に置く
+    let mut header = Dxf::default();
+    header.section("HEADER");
+    // LWPOLYLINEはR2000から
+    header.group(9, &"$ACADVER");
+    header.group(1, &"AC1015");
+    header.group(9, &"$HANDSEED");
+    header.group(5, &Handle(body.next_handle));
+    header.group(9, &"$INSUNITS");
+    header.group(70, &units);
+    header.end_section();
+    header.text + &body.text
+}
+
+/// タイルの辺を書く画層
+const LAYER: &str = "SPECTRE";
+
+/// 16進数で書くハンドル。0は所有者がないことを表す
+struct Handle(u32);
+
+impl std::fmt::Display for Handle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:X}", self.0)
+    }
+}
+
+/// グループコードと値の組を書き、ハンドルを1から順に割り当てる
+struct Dxf {
+    text: String,
+    next_handle: u32,
+}
+
+impl Default for Dxf {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            next_handle: 1,
+        }
+    }
+}
+
+impl Dxf {
+    fn group(&mut self, code: u32, value: &dyn std::fmt::Display) {
+        writeln!(self.text, "{}\n{}", code, value).unwrap();
+    }
+
+    fn handle(&mut self) -> u32 {
+        self.next_handle += 1;
+        self.next_handle - 1
+    }
+
+    fn section(&mut self, name: &str) {
+        self.group(0, &"SECTION");
+        self.group(2, &name);
+    }
+
+    fn end_section(&mut self) {
+        self.group(0, &"ENDSEC");
+    }
+
+    /// 表を書き、各項目のハンドルを返す
+    ///
+    /// 項目は名前だけを持ち、それ以外のグループはfieldsで書く
+    fn table(
+        &mut self,
+        name: &str,
+        entries: &[&str],
+        mut fields: impl FnMut(&mut Self),
+    ) -> Vec<u32> {
+        let table = self.handle();
+        self.group(0, &"TABLE");
+        self.group(2, &name);
+        self.group(5, &Handle(table));
+        self.group(330, &Handle(0));
+        self.group(100, &"AcDbSymbolTable");
+        self.group(70, &entries.len());
+        if name == "DIMSTYLE" {
+            self.group(100, &"AcDbDimStyleTable");
+            self.group(71, &0);
+        }
+        let subclass = match name {
+            "VPORT" => "AcDbViewportTableRecord",
+            "LTYPE" => "AcDbLinetypeTableRecord",
+            "LAYER" => "AcDbLayerTableRecord",
+            "STYLE" => "AcDbTextStyleTableRecord",
+            "VIEW" => "AcDbViewTableRecord",
+            "UCS" => "AcDbUCSTableRecord",
+            "APPID" => "AcDbRegAppTableRecord",
+            "DIMSTYLE" => "AcDbDimStyleTableRecord",
+            _ => "AcDbBlockTableRecord",
+        };
+        let mut handles = vec![];
+        for &entry in entries {
+            let handle = self.handle();
+            self.group(0, &name);
+            // DIMSTYLEだけはハンドルを105に書く
+            self.group(if name == "DIMSTYLE" { 105 } else { 5 }, &Handle(handle));
+            self.group(330, &Handle(table));
+            self.group(100, &"AcDbSymbolTableRecord");
+            self.group(100, &subclass);
+            self.group(2, &entry);
+            self.group(70, &0);
+            fields(self);
+            handles.push(handle);
+        }
+        self.group(0, &"ENDTAB");
+        handles
+    }
+
+    /// ハンドルと所有者を持つ図形を書き始める。続けて画層を書く
+    fn entity(&mut self, kind: &str, owner: u32) {
+        let handle = self.handle();
+        self.group(0, &kind);
+        self.group(5, &Handle(handle));
+        self.group(330, &Handle(owner));
+        self.group(100, &"AcDbEntity");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        tiles::Anchor,
+        utils::{Angle, HexVec},
+    };
+
+    fn single_tile() -> String {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        write_dxf(
+            &[tile],
+            &PlotOptions {
+                edge_length: 1.0,
+                unit: LengthUnit::Inch,
+            },
+        )
+    }
+
+    /// グループコードと値の組
+    fn groups(dxf: &str) -> Vec<(u32, &str)> {
+        let lines: Vec<&str> = dxf.lines().collect();
+        lines
+            .chunks(2)
+            .map(|pair| (pair[0].parse().unwrap(), pair[1]))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_tile() {
+        let dxf = single_tile();
+        let groups = groups(&dxf);
+        let start = groups
+            .iter()
+            .position(|&group| group == (0, "LWPOLYLINE"))
+            .unwrap();
+        let value = |code: u32| {
+            groups[start..]
+                .iter()
+                .find(|&&(c, _)| c == code)
+                .map(|&(_, value)| value)
+        };
+        assert_eq!(dxf.lines().filter(|&l| l == "LWPOLYLINE").count(), 1);
+        // 一周して閉じた14頂点の折れ線
+        assert_eq!(value(90), Some("14"));
+        assert_eq!(value(70), Some("1"));
+        assert_eq!(value(8), Some(LAYER));
+        assert!(dxf.contains("$INSUNITS\n70\n1\n"));
+        assert!(dxf.ends_with("0\nEOF\n"));
+    }
+
+    #[test]
+    fn test_r2000_structure() {
+        let dxf = single_tile();
+        let groups = groups(&dxf);
+        let sections: Vec<&str> = groups
+            .windows(2)
+            .filter(|pair| pair[0] == (0, "SECTION"))
+            .map(|pair| pair[1].1)
+            .collect();
+        assert_eq!(
+            sections,
+            ["HEADER", "CLASSES", "TABLES", "BLOCKS", "ENTITIES", "OBJECTS"]
+        );
+        let tables: Vec<&str> = groups
+            .windows(2)
+            .filter(|pair| pair[0] == (0, "TABLE"))
+            .map(|pair| pair[1].1)
+            .collect();
+        assert_eq!(
+            tables,
+            [
+                "VPORT",
+                "LTYPE",
+                "LAYER",
+                "STYLE",
+                "VIEW",
+                "UCS",
+                "APPID",
+                "DIMSTYLE",
+                "BLOCK_RECORD"
+            ]
+        );
+
+        // ハンドルは重複せず、所有者と辞書の参照は書いたハンドルを指し、$HANDSEEDはどれよりも大きい
+        let parse = |value: &str| u32::from_str_radix(value, 16).unwrap();
+        // HEADERの$HANDSEEDもグループコード5で書かれるので除く
+        let header = groups.iter().position(|&g| g == (0, "ENDSEC")).unwrap();
+        let handles: Vec<u32> = groups[header..]
+            .iter()
+            .filter(|&&(code, _)| code == 5 || code == 105)
+            .map(|&(_, value)| parse(value))
+            .collect();
+        let unique: HashSet<u32> = handles.iter().copied().collect();
+        assert_eq!(unique.len(), handles.len());
+        for &(code, value) in &groups {
+            if code == 330 || code == 350 {
+                let handle = parse(value);
+                assert!(handle == 0 || unique.contains(&handle), "{}", value);
+            }
+        }
+        let seed = groups
+            .windows(2)
+            .find(|pair| pair[0] == (9, "$HANDSEED"))
+            .map(|pair| parse(pair[1].1))
+            .unwrap();
+        assert!(handles.iter().all(|&handle| handle < seed));
+
+        // 図形はモデル空間のブロックに属する
+        let model_space = groups
+            .windows(3)
+            .find(|w| w[0] == (0, "BLOCK_RECORD"))
+            .map(|w| w[1].1)
+            .unwrap();
+        let owner = groups
+            .windows(3)
+            .find(|w| w[0] == (0, "LWPOLYLINE"))
+            .map(|w| w[2].1)
+            .unwrap();
+        assert_eq!(owner, model_space);
+    }
+}
@@ -0,0 +1,80 @@
+use super::polygon::to_f64;
+use crate::{
+    tiles::{Anchor, Spectre},
+    utils::{Angle, HexVec},
+};
+
+/// 基準のタイル（Anchor1が原点、回転0）の頂点
+pub(super) fn reference_outline() -> Vec<[f64; 2]> {
+    Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO)
+        .vertices()
+        .into_iter()
+        .map(to_f64)
+        .collect()
+}
+
+/// 基準のタイルを各タイルへ写す変換
+///
+/// x軸について鏡映し（reflectedのときのみ）、30°のturns倍だけ回転し、positionへ平行移動する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Placement {
+    pub position: [f64; 2],
+    pub turns: u8,
+    pub reflected: bool,
+}
+
+impl Placement {
+    pub fn of(tile: &Spectre) -> Self {
+        Self {
+            position: to_f64(tile.coordinate(Anchor::Anchor1)),
+            turns: tile.rotation().value(),
+            reflected: tile.is_reflected(),
+        }
+    }
+
+    /// 回転の角度（度）
+    pub fn degrees(&self) -> u32 {
+        self.turns as u32 * 30
+    }
+}
+
+/// 小数点以下4桁までで、末尾の0を省いた数
+pub(super) fn number(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::SpectreCluster;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(1.0), "1");
+        assert_eq!(number(-0.00001), "0");
+        assert_eq!(number(0.8660254), "0.866");
+        assert_eq!(number(-12.5), "-12.5");
+    }
+
+    #[test]
+    fn test_placement() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor2, HexVec::ZERO, Angle::new(3), 2);
+        let outline = reference_outline();
+        for tile in cluster.spectres_in(cluster.bbox()) {
+            let placement = Placement::of(tile);
+            let (sin, cos) = (placement.degrees() as f64).to_radians().sin_cos();
+            for (p, q) in outline.iter().zip(tile.vertices()) {
+                let y = if placement.reflected { -p[1] } else { p[1] };
+                let x = p[0] * cos - y * sin + placement.position[0];
+                let y = p[0] * sin + y * cos + placement.position[1];
+                let [qx, qy] = to_f64(q);
+                assert!((x - qx).abs() < 1e-9 && (y - qy).abs() < 1e-9);
+            }
+        }
+    }
+}
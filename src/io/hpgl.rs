@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use super::{tool_path::tool_paths, PlotOptions};
+use crate::tiles::Spectre;
+
+/// HPGLの1ミリメートルあたりのプロッター単位
+const UNITS_PER_MM: f64 = 40.0;
+
+/// タイルの辺をペンプロッター用のHPGLとして書き出す
+///
+/// 共有する辺は一度だけ描き、ペンを上げて移動する距離が短くなる順に並べる
+pub fn write_hpgl(tiles: &[Spectre], options: &PlotOptions) -> String {
+    let scale = options.unit.millimeters() * UNITS_PER_MM;
+    let mut hpgl = String::from("IN;SP1;");
+    for path in tool_paths(tiles, options) {
+        let mut points = path.iter().map(|[x, y]| {
+            format!(
+                "{},{}",
+                (x * scale).round() as i64,
+                (y * scale).round() as i64
+            )
+        });
+        let start = points.next().unwrap();
+        let rest: Vec<String> = points.collect();
+        write!(hpgl, "PU{};PD{};", start, rest.join(",")).unwrap();
+    }
+    hpgl.push_str("PU;SP0;\n");
+    hpgl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::LengthUnit,
+        tiles::Anchor,
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_units() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let options = PlotOptions {
+            edge_length: 1.0,
+            unit: LengthUnit::Centimeter,
+        };
+        let hpgl = write_hpgl(&[tile], &options);
+        assert!(hpgl.starts_with("IN;SP1;PU"));
+        assert!(hpgl.ends_with("PU;SP0;\n"));
+        // ペンを下ろすのは一度だけで、一周して戻る
+        assert_eq!(hpgl.matches("PD").count(), 1);
+        let pen_down = hpgl.split("PD").nth(1).unwrap();
+        let coordinates: Vec<i64> = pen_down
+            .split(';')
+            .next()
+            .unwrap()
+            .split(',')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert_eq!(coordinates.len(), 14 * 2);
+        // 1cmの辺は400プロッター単位
+        let start: Vec<i64> = hpgl["IN;SP1;PU".len()..]
+            .split(';')
+            .next()
+            .unwrap()
+            .split(',')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert_eq!(&coordinates[26..], &start[..]);
+        let (dx, dy) = (coordinates[0] - start[0], coordinates[1] - start[1]);
+        assert!((((dx * dx + dy * dy) as f64).sqrt() - 400.0).abs() < 1.0);
+    }
+}
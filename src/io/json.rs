@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    polygon::{tiles_from_polygons, to_f64},
+    ImportError, ImportOptions,
+};
+use crate::tiles::Spectre;
+
+/// パッチのJSON表現
+///
+/// ```json
+/// { "tiles": [ { "vertices": [[0.0, 0.0], [1.0, 0.0], ...] }, ... ] }
+/// ```
+#[derive(Serialize, Deserialize)]
+struct PatchJson {
+    tiles: Vec<TileJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TileJson {
+    /// 頂点の座標（y軸は上向き）
+    vertices: Vec<[f64; 2]>,
+}
+
+/// JSONからタイルを読み込む
+pub fn read_json(text: &str, options: &ImportOptions) -> Result<Vec<Spectre>, ImportError> {
+    let patch: PatchJson =
+        serde_json::from_str(text).map_err(|e| ImportError::Syntax(e.to_string()))?;
+    let polygons: Vec<Vec<[f64; 2]>> = patch.tiles.into_iter().map(|t| t.vertices).collect();
+    tiles_from_polygons(&polygons, options)
+}
+
+/// タイルをJSONに書き出す
+pub fn write_json(tiles: &[Spectre]) -> String {
+    let patch = PatchJson {
+        tiles: tiles
+            .iter()
+            .map(|tile| TileJson {
+                vertices: tile.vertices().into_iter().map(to_f64).collect(),
+            })
+            .collect(),
+    };
+    serde_json::to_string(&patch).expect("patch is always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster, SupertileRecognition},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor3, HexVec::ZERO, Angle::new(7), 2);
+        let tiles: Vec<Spectre> = cluster.spectres_in(cluster.bbox()).copied().collect();
+        let imported = read_json(&write_json(&tiles), &ImportOptions::default()).unwrap();
+        assert_eq!(imported.len(), tiles.len());
+        for (imported, tile) in imported.iter().zip(&tiles) {
+            assert_eq!(imported.vertices(), tile.vertices());
+        }
+        // 読み込んだパッチはそのまま検証できる
+        assert!(SupertileRecognition::recognize(imported).is_ok());
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        assert!(matches!(
+            read_json(r#"{"tiles": [{"vertices": 1}]}"#, &ImportOptions::default()),
+            Err(ImportError::Syntax(_))
+        ));
+        assert!(read_json(r#"{"tiles": []}"#, &ImportOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    tiles::{Anchor, Spectre},
+    utils::{Angle, HexValue, HexVec, Orientation},
+};
+
+/// 多角形をタイルに合わせるときの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportOptions {
+    /// 入力の座標でのタイルの辺の長さ。Noneなら最初の多角形の周の長さから推定する
+    pub edge_length: Option<f64>,
+    /// 辺の長さを1としたときの、頂点の位置の許容誤差
+    pub tolerance: f64,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            edge_length: None,
+            tolerance: 1e-3,
+        }
+    }
+}
+
+/// 読み込めなかった理由。polygonは入力に現れた順の多角形のインデックス
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    /// ファイルの構文が正しくない
+    Syntax(String),
+    /// 曲線など、多角形として読めない形
+    Unsupported { polygon: usize, reason: String },
+    /// 頂点の数がSpectreと合わない（一直線上の頂点は数えない）
+    VertexCount { polygon: usize, count: usize },
+    /// 辺の長さや角度がSpectre(Tile(1,1))と合わない
+    NotSpectre { polygon: usize },
+    /// 頂点が正六角形の格子に乗らないか、隣のタイルの頂点とずれている
+    OffLattice { polygon: usize, vertex: usize },
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Syntax(message) => write!(f, "syntax error: {}", message),
+            ImportError::Unsupported { polygon, reason } => {
+                write!(f, "polygon {} is not supported: {}", polygon, reason)
+            }
+            ImportError::VertexCount { polygon, count } => write!(
+                f,
+                "polygon {} has {} corners, but a Spectre has 13",
+                polygon, count
+            ),
+            ImportError::NotSpectre { polygon } => write!(
+                f,
+                "polygon {} does not match any placement of Tile(1,1)",
+                polygon
+            ),
+            ImportError::OffLattice { polygon, vertex } => write!(
+                f,
+                "vertex {} of polygon {} is not on the tiling lattice",
+                vertex, polygon
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// 多角形の列をSpectreの列に変換する
+///
+/// 各多角形を回転・鏡映したSpectreと照合し、頂点を共有するタイルから順に位置をHexVecに合わせる。
+/// 頂点は時計回りでも反時計回りでもよい。戻り値は入力と同じ順に並ぶ。
+/// 全体の平行移動は問わず、頂点を共有しない多角形は位置の決まった最も近い頂点からの差で格子に合わせる。
+pub fn tiles_from_polygons(
+    polygons: &[Vec<[f64; 2]>],
+    options: &ImportOptions,
+) -> Result<Vec<Spectre>, ImportError> {
+    let Some(first) = polygons.first() else {
+        return Ok(vec![]);
+    };
+    let edge_length = options
+        .edge_length
+        .unwrap_or_else(|| perimeter(first) / SPECTRE_PERIMETER);
+    if edge_length.is_nan() || edge_length <= 0.0 {
+        return Err(ImportError::NotSpectre { polygon: 0 });
+    }
+    let tolerance = options.tolerance;
+
+    let templates = templates();
+    let mut matches = polygons
+        .iter()
+        .enumerate()
+        .map(|(i, polygon)| {
+            let scaled: Vec<[f64; 2]> = polygon
+                .iter()
+                .map(|p| [p[0] / edge_length, p[1] / edge_length])
+                .collect();
+            match_polygon(i, &scaled, &templates, tolerance)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 全体の平行移動は格子に乗らなくてもよいので、最初の多角形を格子点に丸めたときの差だけ全体を動かす。
+    // 丸められなければ、最初の多角形のアンカー1を原点に置く
+    let first = &matches[0];
+    let anchor = to_f64(first.template.vertices()[first.corners[0]]);
+    let offset = [
+        first.points[0][0] - anchor[0],
+        first.points[0][1] - anchor[1],
+    ];
+    let base = snap(offset, tolerance).map_or([0.0, 0.0], to_f64);
+    let origin = [offset[0] - base[0], offset[1] - base[1]];
+    for m in &mut matches {
+        for p in &mut m.points {
+            p[0] -= origin[0];
+            p[1] -= origin[1];
+        }
+    }
+
+    // 頂点を共有する多角形
+    let mut corners: PointGrid<usize> = PointGrid::default();
+    for (i, m) in matches.iter().enumerate() {
+        for &p in &m.points {
+            corners.insert(p, i);
+        }
+    }
+
+    let mut tiles: Vec<Option<Spectre>> = vec![None; matches.len()];
+    let mut snapped: PointGrid<HexVec> = PointGrid::default();
+    let mut queue = VecDeque::new();
+    for start in 0..matches.len() {
+        if tiles[start].is_some() {
+            continue;
+        }
+        queue.push_back(start);
+        while let Some(i) = queue.pop_front() {
+            if tiles[i].is_some() {
+                continue;
+            }
+            let tile = place(i, &matches[i], &snapped, tolerance)?;
+            for vertex in tile.vertices() {
+                snapped.insert(to_f64(vertex), vertex);
+            }
+            for &p in &matches[i].points {
+                for &(_, neighbor) in corners.near(p, tolerance) {
+                    if tiles[neighbor].is_none() {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            tiles[i] = Some(tile);
+        }
+    }
+    Ok(tiles.into_iter().flatten().collect())
+}
+
+/// 辺の長さを1としたSpectreの周の長さ
+const SPECTRE_PERIMETER: f64 = 14.0;
+
+/// 向きと頂点の対応が決まった多角形
+struct PolygonMatch {
+    /// 原点にアンカー1を置いた、同じ向きのSpectre
+    template: Spectre,
+    /// 一直線上の頂点を除いた頂点
+    points: Vec<[f64; 2]>,
+    /// pointsの各頂点に対応するtemplateの頂点のインデックス
+    corners: Vec<usize>,
+    /// pointsの各頂点の入力でのインデックス
+    original: Vec<usize>,
+}
+
+/// 多角形と重なるSpectreの向きを探す
+fn match_polygon(
+    polygon: usize,
+    points: &[[f64; 2]],
+    templates: &[(Spectre, Vec<usize>)],
+    tolerance: f64,
+) -> Result<PolygonMatch, ImportError> {
+    let (mut points, mut original) = simplify(points, tolerance);
+    let n = points.len();
+    if n != templates[0].1.len() {
+        return Err(ImportError::VertexCount { polygon, count: n });
+    }
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+        original.reverse();
+    }
+
+    for (template, corners) in templates {
+        let template = *template;
+        let vertices: Vec<[f64; 2]> = template.vertices().into_iter().map(to_f64).collect();
+        let base = vertices[corners[0]];
+        for shift in 0..n {
+            let origin = points[shift];
+            let fits = (0..n).all(|i| {
+                let p = points[(shift + i) % n];
+                let q = vertices[corners[i]];
+                distance(
+                    [p[0] - origin[0], p[1] - origin[1]],
+                    [q[0] - base[0], q[1] - base[1]],
+                ) <= tolerance
+            });
+            if fits {
+                let corners = (0..n).map(|i| corners[(i + n - shift) % n]).collect();
+                return Ok(PolygonMatch {
+                    template,
+                    points,
+                    corners,
+                    original,
+                });
+            }
+        }
+    }
+    Err(ImportError::NotSpectre { polygon })
+}
+
+/// 全ての向きの、原点にアンカー1を置いたSpectreと、反時計回りに並べた角の頂点のインデックス
+fn templates() -> Vec<(Spectre, Vec<usize>)> {
+    let base = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+    let corners: Vec<usize> = (0..base.vertices().len())
+        .filter(|&i| Spectre::interior_angle(i) != Angle::new(6))
+        .collect();
+    [false, true]
+        .into_iter()
+        .flat_map(|reflected| (0..12).map(move |r| Orientation::new(Angle::new(r), reflected)))
+        .map(|orientation| {
+            let mut corners = corners.clone();
+            // 鏡映したタイルの頂点は時計回りに並ぶ
+            if orientation.reflected {
+                corners.reverse();
+            }
+            (base.transformed(HexVec::ZERO, orientation), corners)
+        })
+        .collect()
+}
+
+/// 頂点を共有しない多角形を格子に合わせるときの、位置の決まった頂点からの距離の上限
+///
+/// 離れるほど許容誤差に入る格子点が増え、格子に乗らない多角形も丸められてしまう
+const MAX_GAP: f64 = 24.0;
+
+/// 多角形の位置を決める
+///
+/// 位置の決まったタイルと共有する頂点があればそこに合わせ、なければ最も近い位置の決まった頂点からの差を
+/// 格子点に丸める。MAX_GAPより離れていれば位置を決められない
+fn place(
+    polygon: usize,
+    m: &PolygonMatch,
+    snapped: &PointGrid<HexVec>,
+    tolerance: f64,
+) -> Result<Spectre, ImportError> {
+    let vertices = m.template.vertices();
+    let shared = m.points.iter().zip(&m.corners).find_map(|(&p, &corner)| {
+        snapped
+            .near(p, tolerance)
+            .next()
+            .map(|&(_, vertex)| vertex - vertices[corner])
+    });
+    let offset = match shared {
+        Some(offset) => offset,
+        None => {
+            let off_lattice = ImportError::OffLattice {
+                polygon,
+                vertex: m.original[0],
+            };
+            let anchor = vertices[m.corners[0]];
+            let p = m.points[0];
+            match snapped.nearest(p) {
+                // 最初のタイルは全体の平行移動を決めるだけなので、どの格子点に丸めてもよい
+                None => {
+                    let anchor = to_f64(anchor);
+                    snap([p[0] - anchor[0], p[1] - anchor[1]], tolerance).ok_or(off_lattice)?
+                }
+                // 格子点は密に並ぶので、位置の決まった頂点からの差を小さいうちに丸める
+                Some(&(q, vertex)) => {
+                    let d = [p[0] - q[0], p[1] - q[1]];
+                    if distance(d, [0.0, 0.0]) > MAX_GAP {
+                        return Err(off_lattice);
+                    }
+                    vertex + snap(d, tolerance).ok_or(off_lattice)? - anchor
+                }
+            }
+        }
+    };
+
+    let tile = m.template.translated(offset);
+    let vertices = tile.vertices();
+    for (i, (&p, &corner)) in m.points.iter().zip(&m.corners).enumerate() {
+        if distance(p, to_f64(vertices[corner])) > tolerance {
+            return Err(ImportError::OffLattice {
+                polygon,
+                vertex: m.original[i],
+            });
+        }
+    }
+    Ok(tile)
+}
+
+/// 点に最も近い格子点
+///
+/// x = (a + b√3)/2, y = (c + d√3)/2 のうち、単位辺をたどって届く a≡d, b≡c (mod 2) のものから探す
+fn snap(p: [f64; 2], tolerance: f64) -> Option<HexVec> {
+    let sqrt3 = 3.0_f64.sqrt();
+    // 係数の偶奇ごとに最も近い値を探す
+    let nearest = |value: f64| {
+        let range = (2.0 * value.abs() / sqrt3).ceil() as i32 + 4;
+        let mut best = [[(f64::INFINITY, HexValue::ZERO); 2]; 2];
+        for irrational in -range..=range {
+            let target = 2.0 * value - irrational as f64 * sqrt3;
+            for parity in 0..2 {
+                let rational = 2 * ((target - parity as f64) / 2.0).round() as i32 + parity;
+                let error = (rational as f64 - target).abs() / 2.0;
+                let slot = &mut best[parity as usize][irrational.rem_euclid(2) as usize];
+                if error < slot.0 {
+                    *slot = (error, HexValue::new(rational, irrational));
+                }
+            }
+        }
+        best
+    };
+    let xs = nearest(p[0]);
+    let ys = nearest(p[1]);
+    let mut best: Option<(f64, HexVec)> = None;
+    for a in 0..2 {
+        for b in 0..2 {
+            let (ex, x) = xs[a][b];
+            let (ey, y) = ys[b][a];
+            let error = ex.max(ey);
+            if error <= tolerance && best.is_none_or(|(e, _)| error < e) {
+                best = Some((error, HexVec::new(x, y)));
+            }
+        }
+    }
+    best.map(|(_, v)| v)
+}
+
+/// 閉じるための最後の頂点と、一直線上の頂点を除く。入力でのインデックスも返す
+fn simplify(points: &[[f64; 2]], tolerance: f64) -> (Vec<[f64; 2]>, Vec<usize>) {
+    let mut indexed: Vec<(usize, [f64; 2])> = points.iter().copied().enumerate().collect();
+    indexed.dedup_by(|b, a| distance(a.1, b.1) <= tolerance);
+    if indexed.len() > 1 && distance(indexed[0].1, indexed[indexed.len() - 1].1) <= tolerance {
+        indexed.pop();
+    }
+    loop {
+        let n = indexed.len();
+        let straight = (0..n).find(|&i| {
+            let prev = indexed[(i + n - 1) % n].1;
+            let p = indexed[i].1;
+            let next = indexed[(i + 1) % n].1;
+            let u = [p[0] - prev[0], p[1] - prev[1]];
+            let v = [next[0] - p[0], next[1] - p[1]];
+            let cross = u[0] * v[1] - u[1] * v[0];
+            let dot = u[0] * v[0] + u[1] * v[1];
+            n > 3 && dot > 0.0 && cross.abs() <= tolerance
+        });
+        match straight {
+            Some(i) => {
+                indexed.remove(i);
+            }
+            None => break,
+        }
+    }
+    indexed.into_iter().map(|(i, p)| (p, i)).unzip()
+}
+
+fn signed_area(points: &[[f64; 2]]) -> f64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let p = points[i];
+            let q = points[(i + 1) % n];
+            p[0] * q[1] - q[0] * p[1]
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+fn perimeter(points: &[[f64; 2]]) -> f64 {
+    let n = points.len();
+    let closed = n > 1 && points[0] == points[n - 1];
+    let m = if closed { n - 1 } else { n };
+    (0..m)
+        .map(|i| distance(points[i], points[(i + 1) % m]))
+        .sum()
+}
+
+fn distance(p: [f64; 2], q: [f64; 2]) -> f64 {
+    (p[0] - q[0]).hypot(p[1] - q[1])
+}
+
+pub(super) fn to_f64(p: HexVec) -> [f64; 2] {
+    [p.x.to_f64(), p.y.to_f64()]
+}
+
+/// 近くの点を引けるようにした点の集合
+struct PointGrid<T> {
+    cells: HashMap<(i64, i64), Vec<GridPoint<T>>>,
+}
+
+type GridPoint<T> = ([f64; 2], T);
+
+impl<T> Default for PointGrid<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PointGrid<T> {
+    /// 区画の大きさ。許容誤差より十分大きい
+    const CELL_SIZE: f64 = 0.5;
+
+    fn cell(p: [f64; 2]) -> (i64, i64) {
+        (
+            (p[0] / Self::CELL_SIZE).floor() as i64,
+            (p[1] / Self::CELL_SIZE).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, p: [f64; 2], value: T) {
+        self.cells
+            .entry(Self::cell(p))
+            .or_default()
+            .push((p, value));
+    }
+
+    /// pに最も近い点。全ての点を調べる
+    fn nearest(&self, p: [f64; 2]) -> Option<&GridPoint<T>> {
+        self.cells
+            .values()
+            .flatten()
+            .min_by(|(a, _), (b, _)| distance(p, *a).total_cmp(&distance(p, *b)))
+    }
+
+    /// pからtolerance以内にある点
+    fn near(&self, p: [f64; 2], tolerance: f64) -> impl Iterator<Item = &GridPoint<T>> {
+        let (cx, cy) = Self::cell(p);
+        (cx - 1..=cx + 1)
+            .flat_map(move |x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |(q, _)| distance(p, *q) <= tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::SpectreCluster;
+
+    fn polygons(tiles: &[Spectre]) -> Vec<Vec<[f64; 2]>> {
+        tiles
+            .iter()
+            .map(|tile| tile.vertices().into_iter().map(to_f64).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_tiles_from_polygons() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::new(3), 2);
+        let tiles: Vec<Spectre> = cluster.spectres_in(cluster.bbox()).copied().collect();
+        let mut polygons = polygons(&tiles);
+        // 拡大し、一部は時計回りにして一直線上の頂点を除く
+        for (i, polygon) in polygons.iter_mut().enumerate() {
+            for p in polygon.iter_mut() {
+                p[0] *= 10.0;
+                p[1] *= 10.0;
+            }
+            if i % 3 == 0 {
+                polygon.remove(1);
+                polygon.reverse();
+            }
+        }
+        let imported = tiles_from_polygons(&polygons, &ImportOptions::default()).unwrap();
+        assert_eq!(imported.len(), tiles.len());
+        for (imported, tile) in imported.iter().zip(&tiles) {
+            assert_eq!(imported.vertices(), tile.vertices());
+            assert_eq!(imported.orientation(), tile.orientation());
+        }
+    }
+
+    #[test]
+    fn test_reflected_polygon() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::new(5))
+            .transformed(HexVec::ZERO, Orientation::new(Angle::new(2), true));
+        let imported = tiles_from_polygons(&polygons(&[tile]), &ImportOptions::default()).unwrap();
+        assert!(imported[0].is_reflected());
+        assert_eq!(imported[0].vertices(), tile.vertices());
+    }
+
+    #[test]
+    fn test_reject_invalid_polygons() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let options = ImportOptions {
+            edge_length: Some(1.0),
+            ..Default::default()
+        };
+
+        let mut polygon = polygons(&[tile]).remove(0);
+        polygon.remove(5);
+        assert_eq!(
+            tiles_from_polygons(&[polygon], &options).err(),
+            Some(ImportError::VertexCount {
+                polygon: 0,
+                count: 12
+            })
+        );
+
+        // 頂点の数は同じでも形が違う
+        let mut polygon = polygons(&[tile]).remove(0);
+        polygon[5][0] += 0.3;
+        assert_eq!(
+            tiles_from_polygons(&[polygon], &options).err(),
+            Some(ImportError::NotSpectre { polygon: 0 })
+        );
+
+        // 隣のタイルと頂点がずれている
+        let neighbor = tile.connected_spectre(Anchor::Anchor1, Anchor::Anchor4);
+        let mut shifted = polygons(&[tile, neighbor]);
+        for p in shifted[1].iter_mut() {
+            p[0] += 0.0005;
+        }
+        assert!(tiles_from_polygons(&shifted, &options).is_ok());
+        for p in shifted[1].iter_mut() {
+            p[0] += 0.01;
+        }
+        assert!(matches!(
+            tiles_from_polygons(&shifted, &options),
+            Err(ImportError::OffLattice { polygon: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_translated_patch() {
+        // 格子に乗らない平行移動をしても読み込める
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let neighbor = tile.connected_spectre(Anchor::Anchor1, Anchor::Anchor4);
+        let far = tile.translated(HexVec::new(HexValue::new(40, 0), HexValue::ZERO));
+        let mut shifted = polygons(&[tile, neighbor, far]);
+        for p in shifted.iter_mut().flatten() {
+            p[0] += 0.37;
+            p[1] += 0.11;
+        }
+        let options = ImportOptions {
+            edge_length: Some(1.0),
+            ..Default::default()
+        };
+        let imported = tiles_from_polygons(&shifted, &options).unwrap();
+        assert_eq!(imported[0].vertices(), tile.vertices());
+        assert_eq!(imported[1].vertices(), neighbor.vertices());
+        assert_eq!(imported[2].vertices(), far.vertices());
+    }
+
+    #[test]
+    fn test_reject_far_off_lattice_polygon() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let options = ImportOptions {
+            edge_length: Some(1.0),
+            ..Default::default()
+        };
+        // 遠くでは格子点が密に並ぶので、格子からずらした多角形もどこかの格子点に丸められてしまう
+        let mut far = polygons(&[tile]).remove(0);
+        for p in far.iter_mut() {
+            p[0] += 1000.37;
+            p[1] += 500.11;
+        }
+        let [x, y] = far[0];
+        assert!(snap([x, y], options.tolerance).is_some());
+        let polygons = [polygons(&[tile]).remove(0), far];
+        assert!(matches!(
+            tiles_from_polygons(&polygons, &options),
+            Err(ImportError::OffLattice { polygon: 1, .. })
+        ));
+
+        // 近くのタイルからの差で丸めれば、格子からずれていることが分かる
+        let mut near = polygons[0].clone();
+        for p in near.iter_mut() {
+            p[0] += 6.37;
+            p[1] += 0.11;
+        }
+        assert!(matches!(
+            tiles_from_polygons(&[polygons[0].clone(), near], &options),
+            Err(ImportError::OffLattice { polygon: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_snap() {
+        let p = HexVec::new(HexValue::new(7, -3), HexValue::new(-5, 3));
+        let [x, y] = to_f64(p);
+        assert_eq!(snap([x + 1e-5, y - 1e-5], 1e-3), Some(p));
+    }
+}
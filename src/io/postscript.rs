@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use super::{
+    figure::{number, reference_outline, Placement},
+    PlotOptions,
+};
+use crate::tiles::Spectre;
+
+/// 1ミリメートルあたりのポイント数
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+/// 図の周りの余白（ポイント）
+const MARGIN: f64 = 2.0;
+
+/// タイルをEPSとして書き出す
+///
+/// タイルの形は手続き`S`として一度だけ定義し、各タイルは`x y turns F`（鏡映されたタイルは`R`）で、
+/// 平行移動と30°単位の回転だけを指定して描く
+pub fn write_eps(tiles: &[Spectre], options: &PlotOptions) -> String {
+    let scale = options.edge_length * options.unit.millimeters() * POINTS_PER_MM;
+    let bbox = tiles
+        .iter()
+        .map(|tile| tile.bbox())
+        .reduce(|a, b| a.union(&b));
+    let (min, max) = bbox.map_or(([0.0, 0.0], [0.0, 0.0]), |bbox| {
+        (
+            [bbox.min.x as f64, bbox.min.y as f64],
+            [bbox.max.x as f64, bbox.max.y as f64],
+        )
+    });
+    let width = ((max[0] - min[0]) * scale + MARGIN * 2.0).ceil();
+    let height = ((max[1] - min[1]) * scale + MARGIN * 2.0).ceil();
+
+    let mut eps = String::new();
+    eps.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+    writeln!(eps, "%%BoundingBox: 0 0 {} {}", width, height).unwrap();
+    eps.push_str("%%Creator: Infinite Spectres\n%%EndComments\n");
+
+    let outline = reference_outline();
+    let mut path = String::from("/S { newpath");
+    for (i, [x, y]) in outline.iter().enumerate() {
+        let operator = if i == 0 { "moveto" } else { "lineto" };
+        write!(path, " {} {} {}", number(*x), number(*y), operator).unwrap();
+    }
+    path.push_str(" closepath } bind def\n");
+    eps.push_str(&path);
+    // gray D: 塗ってから輪郭を描く
+    eps.push_str("/D { S gsave setgray fill grestore stroke } bind def\n");
+    eps.push_str("/F { gsave 3 1 roll translate 30 mul rotate 0.85 D grestore } bind def\n");
+    eps.push_str(
+        "/R { gsave 3 1 roll translate 30 mul rotate 1 -1 scale 0.6 D grestore } bind def\n",
+    );
+    writeln!(
+        eps,
+        "gsave {} {} translate {} dup scale {} {} translate 0.03 setlinewidth 1 setlinejoin",
+        MARGIN,
+        MARGIN,
+        number(scale),
+        number(-min[0]),
+        number(-min[1])
+    )
+    .unwrap();
+    for tile in tiles {
+        let placement = Placement::of(tile);
+        let [x, y] = placement.position;
+        let procedure = if placement.reflected { "R" } else { "F" };
+        writeln!(
+            eps,
+            "{} {} {} {}",
+            number(x),
+            number(y),
+            placement.turns,
+            procedure
+        )
+        .unwrap();
+    }
+    eps.push_str("grestore\nshowpage\n%%EOF\n");
+    eps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::LengthUnit,
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_instances() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let tiles: Vec<Spectre> = cluster.spectres_in(cluster.bbox()).copied().collect();
+        let options = PlotOptions {
+            edge_length: 0.1,
+            unit: LengthUnit::Inch,
+        };
+        let eps = write_eps(&tiles, &options);
+        let instances = eps
+            .lines()
+            .filter(|line| line.ends_with(" F") || line.ends_with(" R"))
+            .count();
+        assert_eq!(instances, tiles.len());
+        assert_eq!(eps.matches("/S {").count(), 1);
+
+        // 辺の長さは7.2ポイント
+        let bbox = cluster.bbox();
+        let width = ((bbox.max.x - bbox.min.x) as f64 * 7.2 + 4.0).ceil();
+        assert!(eps.contains(&format!("%%BoundingBox: 0 0 {} ", width)));
+        assert!(eps.ends_with("%%EOF\n"));
+    }
+}
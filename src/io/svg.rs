@@ -0,0 +1,451 @@
+use std::fmt::Write;
+
+use super::{
+    polygon::{tiles_from_polygons, to_f64},
+    ImportError, ImportOptions,
+};
+use crate::tiles::Spectre;
+
+/// SVGの<path>と<polygon>からタイルを読み込む
+///
+/// グループや要素のtransformは適用する。SVGのy軸は下向きなので、読み込むときに反転する。
+/// 曲線を含むパスは読めない。
+pub fn read_svg(text: &str, options: &ImportOptions) -> Result<Vec<Spectre>, ImportError> {
+    let mut polygons = vec![];
+    // 開いている<g>ごとの変換
+    let mut transforms = vec![Affine::IDENTITY];
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        // コメントや宣言は読み飛ばす
+        let skip = [("<!--", "-->"), ("<?", "?>"), ("<!", ">")]
+            .into_iter()
+            .find(|(open, _)| rest.starts_with(open));
+        if let Some((_, close)) = skip {
+            let end = rest
+                .find(close)
+                .ok_or_else(|| ImportError::Syntax("unterminated markup".to_string()))?;
+            rest = &rest[end + close.len()..];
+            continue;
+        }
+        let end = rest
+            .find('>')
+            .ok_or_else(|| ImportError::Syntax("unterminated tag".to_string()))?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            if name.trim() == "g" {
+                if transforms.len() == 1 {
+                    return Err(ImportError::Syntax("unbalanced </g>".to_string()));
+                }
+                transforms.pop();
+            }
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        let attributes = parse_attributes(&tag[name_end..])?;
+        let attribute = |key: &str| attributes.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+        let parent = *transforms.last().unwrap();
+        let transform = match attribute("transform") {
+            Some(value) => parent.then(parse_transform(value)?),
+            None => parent,
+        };
+        match name {
+            "g" if !self_closing => transforms.push(transform),
+            "path" => {
+                let d = attribute("d").unwrap_or_default();
+                for points in parse_path(d, polygons.len())? {
+                    polygons.push(points.into_iter().map(|p| transform.apply(p)).collect());
+                }
+            }
+            "polygon" => {
+                let numbers = parse_numbers(attribute("points").unwrap_or_default())?;
+                if numbers.len() % 2 != 0 {
+                    return Err(ImportError::Syntax(
+                        "odd number of coordinates in points".to_string(),
+                    ));
+                }
+                polygons.push(
+                    numbers
+                        .chunks(2)
+                        .map(|p| transform.apply([p[0], p[1]]))
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let polygons: Vec<Vec<[f64; 2]>> = polygons
+        .into_iter()
+        .map(|points: Vec<[f64; 2]>| points.into_iter().map(|[x, y]| [x, -y]).collect())
+        .collect();
+    tiles_from_polygons(&polygons, options)
+}
+
+/// タイルをSVGに書き出す
+///
+/// 辺の長さを1とした座標で、塗りの色はfillで決める
+pub fn write_svg(tiles: &[Spectre], fill: impl Fn(&Spectre) -> String) -> String {
+    let bbox = tiles.iter().fold(crate::utils::Aabb::NULL, |bbox, tile| {
+        bbox.union(&tile.bbox())
+    });
+    let margin = 0.5;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        bbox.min.x - margin,
+        -bbox.max.y - margin,
+        bbox.max.x - bbox.min.x + 2.0 * margin,
+        bbox.max.y - bbox.min.y + 2.0 * margin
+    );
+    for tile in tiles {
+        let mut d = String::new();
+        for (i, vertex) in tile.vertices().into_iter().enumerate() {
+            let [x, y] = to_f64(vertex);
+            let command = if i == 0 { 'M' } else { 'L' };
+            let _ = write!(d, "{}{:.6} {:.6} ", command, x, -y);
+        }
+        d.push('Z');
+        let _ = writeln!(
+            svg,
+            r#"  <path d="{}" fill="{}" stroke="black" stroke-width="0.05"/>"#,
+            d,
+            fill(tile)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// 2次元のアフィン変換 [a c e; b d f]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Affine([f64; 6]);
+
+impl Affine {
+    const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn apply(self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let [a, b, c, d, e, f] = self.0;
+        [a * x + c * y + e, b * x + d * y + f]
+    }
+
+    /// otherを適用してからselfを適用する変換
+    fn then(self, other: Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Self([
+            a * a2 + c * b2,
+            b * a2 + d * b2,
+            a * c2 + c * d2,
+            b * c2 + d * d2,
+            a * e2 + c * f2 + e,
+            b * e2 + d * f2 + f,
+        ])
+    }
+}
+
+/// transform属性を読む
+fn parse_transform(value: &str) -> Result<Affine, ImportError> {
+    let mut transform = Affine::IDENTITY;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('(')
+            .ok_or_else(|| ImportError::Syntax(format!("invalid transform: {}", value)))?;
+        let close = rest
+            .find(')')
+            .ok_or_else(|| ImportError::Syntax(format!("invalid transform: {}", value)))?;
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let args = parse_numbers(&rest[open + 1..close])?;
+        let next = match (name, &args[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine([a, b, c, d, e, f]),
+            ("translate", &[x]) => Affine([1.0, 0.0, 0.0, 1.0, x, 0.0]),
+            ("translate", &[x, y]) => Affine([1.0, 0.0, 0.0, 1.0, x, y]),
+            ("scale", &[s]) => Affine([s, 0.0, 0.0, s, 0.0, 0.0]),
+            ("scale", &[x, y]) => Affine([x, 0.0, 0.0, y, 0.0, 0.0]),
+            ("rotate", &[angle]) => rotation(angle),
+            ("rotate", &[angle, cx, cy]) => Affine([1.0, 0.0, 0.0, 1.0, cx, cy])
+                .then(rotation(angle))
+                .then(Affine([1.0, 0.0, 0.0, 1.0, -cx, -cy])),
+            _ => {
+                return Err(ImportError::Syntax(format!(
+                    "unsupported transform: {}",
+                    &rest[..=close]
+                )));
+            }
+        };
+        transform = transform.then(next);
+        rest = rest[close + 1..].trim_start();
+    }
+    Ok(transform)
+}
+
+fn rotation(degrees: f64) -> Affine {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Affine([cos, sin, -sin, cos, 0.0, 0.0])
+}
+
+/// タグの属性を(名前, 値)の列にする
+fn parse_attributes(mut rest: &str) -> Result<Vec<(&str, &str)>, ImportError> {
+    let mut attributes = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(attributes);
+        }
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| ImportError::Syntax(format!("invalid attribute: {}", rest)))?;
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|&c| c == '"' || c == '\'')
+            .ok_or_else(|| ImportError::Syntax(format!("unquoted attribute: {}", key)))?;
+        let end = value[1..]
+            .find(quote)
+            .ok_or_else(|| ImportError::Syntax(format!("unterminated attribute: {}", key)))?;
+        attributes.push((key, &value[1..end + 1]));
+        rest = &value[end + 2..];
+    }
+}
+
+/// パスのd属性を、サブパスごとの多角形にする。firstは最初のサブパスの多角形のインデックス
+fn parse_path(d: &str, first: usize) -> Result<Vec<Vec<[f64; 2]>>, ImportError> {
+    let mut polygons: Vec<Vec<[f64; 2]>> = vec![];
+    let mut current: Vec<[f64; 2]> = vec![];
+    let mut position = [0.0, 0.0];
+    let mut command = None;
+    let mut tokens = PathTokens { rest: d };
+    while let Some(token) = tokens.next() {
+        let token = token?;
+        let (c, number) = match token {
+            PathToken::Command(c) => {
+                command = Some(c);
+                if c == 'Z' || c == 'z' {
+                    if let Some(&start) = current.first() {
+                        position = start;
+                    }
+                    if !current.is_empty() {
+                        polygons.push(std::mem::take(&mut current));
+                    }
+                    continue;
+                }
+                match tokens.next() {
+                    Some(Ok(PathToken::Number(number))) => (c, number),
+                    _ => {
+                        return Err(ImportError::Syntax(format!(
+                            "missing coordinates after {}",
+                            c
+                        )));
+                    }
+                }
+            }
+            PathToken::Number(number) => match command {
+                Some(c) if c != 'Z' && c != 'z' => (c, number),
+                _ => {
+                    return Err(ImportError::Syntax(
+                        "coordinates without command".to_string(),
+                    ))
+                }
+            },
+        };
+        let relative = c.is_ascii_lowercase();
+        let base = if relative { position } else { [0.0, 0.0] };
+        let mut second = || match tokens.next() {
+            Some(Ok(PathToken::Number(number))) => Ok(number),
+            _ => Err(ImportError::Syntax(format!(
+                "missing coordinate after {}",
+                c
+            ))),
+        };
+        position = match c.to_ascii_uppercase() {
+            'M' => {
+                if !current.is_empty() {
+                    polygons.push(std::mem::take(&mut current));
+                }
+                // 続く座標はLとして扱う
+                command = Some(if relative { 'l' } else { 'L' });
+                [base[0] + number, base[1] + second()?]
+            }
+            'L' => [base[0] + number, base[1] + second()?],
+            'H' => [base[0] + number, position[1]],
+            'V' => [position[0], base[1] + number],
+            _ => {
+                return Err(ImportError::Unsupported {
+                    polygon: first + polygons.len(),
+                    reason: format!("path command {} is not a straight line", c),
+                });
+            }
+        };
+        current.push(position);
+    }
+    if !current.is_empty() {
+        polygons.push(current);
+    }
+    Ok(polygons)
+}
+
+enum PathToken {
+    Command(char),
+    Number(f64),
+}
+
+/// d属性の字句
+struct PathTokens<'a> {
+    rest: &'a str,
+}
+
+impl Iterator for PathTokens<'_> {
+    type Item = Result<PathToken, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rest = self
+            .rest
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let c = self.rest.chars().next()?;
+        if c.is_ascii_alphabetic() && c != 'e' && c != 'E' {
+            self.rest = &self.rest[1..];
+            return Some(Ok(PathToken::Command(c)));
+        }
+        let (number, rest) = split_number(self.rest);
+        self.rest = rest;
+        Some(
+            number
+                .parse()
+                .map(PathToken::Number)
+                .map_err(|_| ImportError::Syntax(format!("invalid number near {:?}", c))),
+        )
+    }
+}
+
+/// 先頭の数値の文字列と残り
+fn split_number(s: &str) -> (&str, &str) {
+    let bytes = s.as_bytes();
+    let mut end = 0;
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let mut seen_dot = false;
+    while end < bytes.len() && (bytes[end].is_ascii_digit() || (bytes[end] == b'.' && !seen_dot)) {
+        seen_dot |= bytes[end] == b'.';
+        end += 1;
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent = end + 1;
+        if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+            exponent += 1;
+        }
+        if exponent < bytes.len() && bytes[exponent].is_ascii_digit() {
+            end = exponent;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+        }
+    }
+    // 数値として読めない文字は一文字だけ進めてエラーにする
+    let end = end.max(s.chars().next().map_or(0, char::len_utf8));
+    s.split_at(end)
+}
+
+/// 空白やカンマで区切られた数値の列
+fn parse_numbers(s: &str) -> Result<Vec<f64>, ImportError> {
+    let tokens = PathTokens { rest: s };
+    let mut numbers = vec![];
+    for token in tokens {
+        match token? {
+            PathToken::Number(number) => numbers.push(number),
+            PathToken::Command(c) => {
+                return Err(ImportError::Syntax(format!(
+                    "unexpected {:?} in numbers",
+                    c
+                )));
+            }
+        }
+    }
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::new(2), 2);
+        let tiles: Vec<Spectre> = cluster.spectres_in(cluster.bbox()).copied().collect();
+        let svg = write_svg(&tiles, |_| "#ccc".to_string());
+        let imported = read_svg(&svg, &ImportOptions::default()).unwrap();
+        assert_eq!(imported.len(), tiles.len());
+        for (imported, tile) in imported.iter().zip(&tiles) {
+            assert_eq!(imported.vertices(), tile.vertices());
+        }
+    }
+
+    #[test]
+    fn test_transformed_svg() {
+        // 他のツールが書き出したような、拡大・平行移動された相対座標のパスと<polygon>
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let neighbor = tile.connected_spectre(Anchor::Anchor1, Anchor::Anchor4);
+        let relative = |tile: &Spectre| {
+            let vertices: Vec<[f64; 2]> = tile.vertices().into_iter().map(to_f64).collect();
+            let mut d = format!("m{},{}", vertices[0][0], -vertices[0][1]);
+            for pair in vertices.windows(2) {
+                d += &format!(
+                    " {},{}",
+                    pair[1][0] - pair[0][0],
+                    -(pair[1][1] - pair[0][1])
+                );
+            }
+            d + "z"
+        };
+        let points: Vec<String> = neighbor
+            .vertices()
+            .into_iter()
+            .map(to_f64)
+            .map(|[x, y]| format!("{},{}", x, -y))
+            .collect();
+        let svg = format!(
+            r#"<?xml version="1.0"?>
+<!-- exported -->
+<svg xmlns="http://www.w3.org/2000/svg">
+  <g transform="translate(100 40) scale(20)">
+    <path d="{}" style="fill:red"/>
+    <g><polygon points="{}"/></g>
+  </g>
+</svg>"#,
+            relative(&tile),
+            points.join(" ")
+        );
+        let imported = read_svg(&svg, &ImportOptions::default()).unwrap();
+        assert_eq!(imported.len(), 2);
+        // 位置は格子点に丸められるので、タイル同士の位置関係が保たれる
+        let offset = imported[0].coordinate(Anchor::Anchor1) - tile.coordinate(Anchor::Anchor1);
+        assert_eq!(imported[0].vertices(), tile.translated(offset).vertices());
+        assert_eq!(
+            imported[1].vertices(),
+            neighbor.translated(offset).vertices()
+        );
+    }
+
+    #[test]
+    fn test_reject_curves() {
+        let svg = r#"<svg><path d="M0 0 L1 0 Z"/><path d="M0 0 C1 1 2 2 3 3 Z"/></svg>"#;
+        assert!(matches!(
+            read_svg(svg, &ImportOptions::default()),
+            Err(ImportError::Unsupported { polygon: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::Write;
+
+use super::{
+    figure::{number, reference_outline, Placement},
+    PlotOptions,
+};
+use crate::tiles::Spectre;
+
+/// タイルをTikZのtikzpictureとして書き出す
+///
+/// タイルの形は`\spectre`として一度だけ定義し、各タイルは平行移動、30°単位の回転、
+/// 鏡映だけを指定して描く。見た目は`spectre`と`spectre reflected`のスタイルで変えられる
+pub fn write_tikz(tiles: &[Spectre], options: &PlotOptions) -> String {
+    let mut tikz = String::new();
+    let outline: Vec<String> = reference_outline()
+        .into_iter()
+        .map(|[x, y]| format!("({},{})", number(x), number(y)))
+        .collect();
+    writeln!(tikz, r"\def\spectre{{{} -- cycle}}", outline.join(" -- ")).unwrap();
+    writeln!(
+        tikz,
+        r"\tikzset{{spectre/.style={{draw=black!80,fill=blue!15,line join=round}},spectre reflected/.style={{spectre,fill=blue!40}}}}"
+    )
+    .unwrap();
+    // 辺の長さをcmで
+    let scale = options.edge_length * options.unit.millimeters() / 10.0;
+    writeln!(
+        tikz,
+        r"\begin{{tikzpicture}}[scale={},very thin]",
+        number(scale)
+    )
+    .unwrap();
+    for tile in tiles {
+        let placement = Placement::of(tile);
+        let [x, y] = placement.position;
+        let style = if placement.reflected {
+            "spectre reflected"
+        } else {
+            "spectre"
+        };
+        write!(
+            tikz,
+            r"\path[{},shift={{({},{})}}",
+            style,
+            number(x),
+            number(y)
+        )
+        .unwrap();
+        if placement.turns != 0 {
+            write!(tikz, ",rotate={}", placement.degrees()).unwrap();
+        }
+        if placement.reflected {
+            tikz.push_str(",yscale=-1");
+        }
+        tikz.push_str(r"] \spectre;");
+        tikz.push('\n');
+    }
+    tikz.push_str("\\end{tikzpicture}\n");
+    tikz
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec, Orientation},
+    };
+
+    #[test]
+    fn test_instances() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let mut tiles: Vec<Spectre> = cluster.spectres_in(cluster.bbox()).copied().collect();
+        // 鏡映されたタイルはタイリングには現れないが、読み込んだパッチには含まれうる
+        tiles.push(tiles[0].transformed(HexVec::ZERO, Orientation::new(Angle::new(2), true)));
+        let tikz = write_tikz(&tiles, &PlotOptions::default());
+        assert_eq!(tikz.matches(r"\def\spectre").count(), 1);
+        assert_eq!(tikz.matches(r"\spectre;").count(), tiles.len());
+        assert_eq!(tikz.matches("yscale=-1").count(), 1);
+        assert!(tikz.contains("[scale=1,very thin]"));
+        // 回転は30°の倍数
+        for rotate in tikz.split("rotate=").skip(1) {
+            let degrees: u32 = rotate.split([',', ']']).next().unwrap().parse().unwrap();
+            assert_eq!(degrees % 30, 0);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+
+use super::polygon::to_f64;
+use crate::{tiles::Spectre, utils::HexVec};
+
+/// 書き出す長さの単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    Millimeter,
+    Centimeter,
+    Inch,
+}
+
+impl LengthUnit {
+    /// 1単位のミリメートル数
+    pub fn millimeters(self) -> f64 {
+        match self {
+            LengthUnit::Millimeter => 1.0,
+            LengthUnit::Centimeter => 10.0,
+            LengthUnit::Inch => 25.4,
+        }
+    }
+}
+
+/// 切断・作図用の書き出しの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotOptions {
+    /// タイルの辺一本の長さ（unit単位）
+    pub edge_length: f64,
+    pub unit: LengthUnit,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            edge_length: 10.0,
+            unit: LengthUnit::Millimeter,
+        }
+    }
+}
+
+/// タイルの辺を一筆書きの折れ線に分け、移動の少ない順に並べる
+///
+/// 隣り合うタイルが共有する辺は一度だけ通る。座標はunit単位で、全体のbboxの左下を原点にする。
+/// 始点と終点が一致する折れ線は閉じている
+pub fn tool_paths(tiles: &[Spectre], options: &PlotOptions) -> Vec<Vec<[f64; 2]>> {
+    let edges = unique_edges(tiles);
+    let Some(min) = edges
+        .iter()
+        .map(|&(p, _)| to_f64(p))
+        .reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1])])
+    else {
+        return vec![];
+    };
+    // 原点から切り始める
+    let trails = sort_by_travel(trails(&edges), min);
+    trails
+        .into_iter()
+        .map(|trail| {
+            trail
+                .into_iter()
+                .map(|p| {
+                    let [x, y] = to_f64(p);
+                    [
+                        (x - min[0]) * options.edge_length,
+                        (y - min[1]) * options.edge_length,
+                    ]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// 重複を除いた辺。順序はタイルと頂点の順
+fn unique_edges(tiles: &[Spectre]) -> Vec<(HexVec, HexVec)> {
+    let mut seen = HashSet::new();
+    let mut edges = vec![];
+    for tile in tiles {
+        let vertices = tile.vertices();
+        for (i, &from) in vertices.iter().enumerate() {
+            let to = vertices[(i + 1) % vertices.len()];
+            if !seen.contains(&(to, from)) && seen.insert((from, to)) {
+                edges.push((from, to));
+            }
+        }
+    }
+    edges
+}
+
+/// 辺をすべて一度ずつ通る折れ線に分ける
+///
+/// 次数が奇数の頂点から先に辿り始めると、折れ線の数が最小（奇数次の頂点の数の半分）に近くなる
+fn trails(edges: &[(HexVec, HexVec)]) -> Vec<Vec<HexVec>> {
+    let mut vertices = vec![];
+    let mut incident: HashMap<HexVec, Vec<usize>> = HashMap::new();
+    for (i, &(from, to)) in edges.iter().enumerate() {
+        for vertex in [from, to] {
+            let list = incident.entry(vertex).or_default();
+            if list.is_empty() {
+                vertices.push(vertex);
+            }
+            list.push(i);
+        }
+    }
+    let starts: Vec<HexVec> = vertices
+        .iter()
+        .filter(|v| incident[v].len() % 2 == 1)
+        .chain(vertices.iter())
+        .copied()
+        .collect();
+
+    let mut used = vec![false; edges.len()];
+    let mut result = vec![];
+    for start in starts {
+        let mut trail = vec![start];
+        let mut current = start;
+        while let Some(&edge) = incident[&current].iter().find(|&&e| !used[e]) {
+            used[edge] = true;
+            let (from, to) = edges[edge];
+            current = if from == current { to } else { from };
+            trail.push(current);
+        }
+        if trail.len() > 1 {
+            result.push(trail);
+        }
+    }
+    result
+}
+
+/// startから始め、前の折れ線の終点から最も近い端を持つ折れ線を順に選ぶ。必要なら向きを逆にする
+fn sort_by_travel(mut trails: Vec<Vec<HexVec>>, start: [f64; 2]) -> Vec<Vec<HexVec>> {
+    let mut result = Vec::with_capacity(trails.len());
+    let mut position = start;
+    while !trails.is_empty() {
+        let distance = |p: HexVec| {
+            let [x, y] = to_f64(p);
+            (x - position[0]).hypot(y - position[1])
+        };
+        let (index, reversed, _) = trails
+            .iter()
+            .enumerate()
+            .flat_map(|(i, trail)| {
+                [
+                    (i, false, distance(trail[0])),
+                    (i, true, distance(*trail.last().unwrap())),
+                ]
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .unwrap();
+        let mut trail = trails.swap_remove(index);
+        if reversed {
+            trail.reverse();
+        }
+        position = to_f64(*trail.last().unwrap());
+        result.push(trail);
+    }
+    result
+}
+
+/// 折れ線の間を移動する距離の合計
+pub fn travel_length(paths: &[Vec<[f64; 2]>]) -> f64 {
+    paths
+        .windows(2)
+        .map(|pair| {
+            let from = pair[0].last().unwrap();
+            let to = pair[1][0];
+            (to[0] - from[0]).hypot(to[1] - from[1])
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::Angle,
+    };
+
+    fn cluster_tiles() -> Vec<Spectre> {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        cluster.spectres_in(cluster.bbox()).copied().collect()
+    }
+
+    /// 端点の組を丸めて、向きを区別しない辺にする
+    fn segment(p: [f64; 2], q: [f64; 2]) -> [(i64, i64); 2] {
+        let round = |p: [f64; 2]| ((p[0] * 1e4).round() as i64, (p[1] * 1e4).round() as i64);
+        let (p, q) = (round(p), round(q));
+        if p < q {
+            [p, q]
+        } else {
+            [q, p]
+        }
+    }
+
+    #[test]
+    fn test_each_edge_once() {
+        let tiles = cluster_tiles();
+        let paths = tool_paths(&tiles, &PlotOptions::default());
+        let segments: Vec<_> = paths
+            .iter()
+            .flat_map(|path| path.windows(2).map(|pair| segment(pair[0], pair[1])))
+            .collect();
+        let unique: HashSet<_> = segments.iter().copied().collect();
+        assert_eq!(segments.len(), unique.len());
+        assert_eq!(segments.len(), unique_edges(&tiles).len());
+        // 共有する辺の分だけ少ない
+        assert!(segments.len() < tiles.len() * 14);
+
+        // 原点を左下にし、辺の長さはedge_length
+        let min_x = paths
+            .iter()
+            .flatten()
+            .map(|p| p[0])
+            .fold(f64::MAX, f64::min);
+        let min_y = paths
+            .iter()
+            .flatten()
+            .map(|p| p[1])
+            .fold(f64::MAX, f64::min);
+        assert!(min_x.abs() < 1e-9 && min_y.abs() < 1e-9);
+        let [p, q] = [paths[0][0], paths[0][1]];
+        assert!(((q[0] - p[0]).hypot(q[1] - p[1]) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_travel_sorted() {
+        let tiles = cluster_tiles();
+        let unsorted: Vec<Vec<[f64; 2]>> = trails(&unique_edges(&tiles))
+            .into_iter()
+            .map(|trail| trail.into_iter().map(to_f64).collect())
+            .collect();
+        let sorted = tool_paths(
+            &tiles,
+            &PlotOptions {
+                edge_length: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(sorted.len(), unsorted.len());
+        assert!(travel_length(&sorted) < travel_length(&unsorted));
+    }
+}
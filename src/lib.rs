@@ -1,27 +1,163 @@
 use glam::Vec2;
 use mikage::wgpu;
 use mikage::winit::dpi::PhysicalSize;
+use mikage::winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
+use mikage::winit::keyboard::{Key, NamedKey};
 use mikage::{
     App, Camera2d, FrameContext, GpuContext, InstanceRenderer, InstanceRendererConfig, RunConfig,
-    SceneBinding, ShaderProcessor, UpdateContext,
+    ShaderProcessor, UpdateContext,
 };
 
+pub mod analysis;
+pub mod animation;
 mod controller;
+mod font;
+pub mod io;
+pub mod mesh;
+mod minimap;
+mod navigation;
+pub mod raster;
+pub mod tile_server;
 pub mod tiles;
 pub mod utils;
 
-use controller::{LastViewState, SpectreInstance, TilesController};
+use animation::SubstitutionAnimation;
+use controller::{
+    LastViewState, SpectreInstance, TileInspection, TilesController, ViewTransform, ViewUniform,
+};
+use minimap::{Minimap, OverlayInstance};
+use navigation::{GoToTarget, Navigation};
+use utils::Aabb;
+
+/// アニメーションが1フレームに進める時間（周期単位）
+const ANIMATION_STEP: f32 = 1.0 / 240.0;
+
+/// 押してから離すまでにこれ以上（ピクセル）動いたらクリックではなくドラッグとみなす
+const CLICK_TOLERANCE: f32 = 4.0;
+
+/// キー1回で動かす距離の、カメラの表示範囲に対する割合
+const PAN_STEP: f32 = 0.1;
+/// キー1回で拡大・縮小する倍率
+const ZOOM_STEP: f32 = 1.25;
+
+/// 前のフレームでの、ウィンドウのピクセルとワールド座標の対応
+#[derive(Clone, Copy)]
+struct ScreenMapping {
+    window: Vec2,
+    /// カメラの表示範囲
+    vp_min: Vec2,
+    vp_max: Vec2,
+    view: ViewTransform,
+}
+
+impl ScreenMapping {
+    /// ウィンドウのピクセル（左上が原点、y軸下向き）をワールド座標にする
+    fn to_world(self, pixel: Vec2) -> Vec2 {
+        let t = pixel / self.window.max(Vec2::ONE);
+        let camera = Vec2::new(
+            self.vp_min.x + t.x * (self.vp_max.x - self.vp_min.x),
+            self.vp_max.y - t.y * (self.vp_max.y - self.vp_min.y),
+        );
+        self.view.inverse_apply(camera)
+    }
+
+    fn camera_center(self) -> Vec2 {
+        (self.vp_min + self.vp_max) * 0.5
+    }
+
+    /// カメラの表示範囲の短い方の辺
+    fn camera_width(self) -> f32 {
+        (self.vp_max - self.vp_min).min_element()
+    }
+}
+
+/// タイルのシェーダーに渡す画面の変換のユニフォーム
+///
+/// 画面の変換はインスタンスに含めないので、表示するタイルが変わらなければこれを書き換えるだけで済む
+struct ViewBinding {
+    buffer: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ViewBinding {
+    fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("view_uniform"),
+            size: size_of::<ViewUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("view_uniform_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("view_uniform_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    fn update(&self, queue: &wgpu::Queue, uniform: &ViewUniform) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(uniform));
+    }
+}
+
+/// 再生中のアニメーション
+struct AnimationState {
+    animation: SubstitutionAnimation,
+    /// アニメーションを始めたときの根のlevel。その後expandしても同じClusterを辿る
+    root_level: usize,
+    time: f32,
+}
 
 struct SpectreApp {
     renderer: InstanceRenderer<SpectreInstance>,
-    scene: SceneBinding,
+    /// 画面の上に重ねるミニマップ
+    overlay: InstanceRenderer<OverlayInstance>,
+    minimap: Minimap,
+    /// overlayに最後に渡したインスタンス
+    overlay_instances: Vec<OverlayInstance>,
+    view_binding: ViewBinding,
     controller: TilesController,
     last_view: LastViewState,
+    /// キーボードで動かす画面の変換。カメラはこの変換の後の座標系で動く
+    navigation: Navigation,
+    /// 入力中の移動先。gで入力を始め、Enterで移動する
+    command: Option<String>,
+    animation: Option<AnimationState>,
+    /// クリックした位置をワールド座標にするための、前のフレームの対応
+    screen: Option<ScreenMapping>,
+    /// カーソルの位置（ウィンドウのピクセル）
+    cursor: Vec2,
+    /// 左ボタンを押した位置
+    press: Option<Vec2>,
+    /// クリックして選んだタイル
+    picked: Option<TileInspection>,
 }
 
 impl SpectreApp {
-    fn new(gpu: &GpuContext, _size: PhysicalSize<u32>) -> Self {
-        let scene = SceneBinding::new(&gpu.device);
+    /// animateを与えると、そのlevelのClusterが子に分かれていくアニメーションを再生する
+    fn new(gpu: &GpuContext, _size: PhysicalSize<u32>, animate: Option<usize>) -> Self {
+        let view_binding = ViewBinding::new(&gpu.device);
 
         // シェーダーを解決
         let sp = ShaderProcessor::new();
@@ -40,7 +176,7 @@ impl SpectreApp {
         };
         let renderer = InstanceRenderer::<SpectreInstance>::with_shader(
             gpu,
-            scene.layout(),
+            &view_binding.layout,
             &positions,
             &normals,
             &indices,
@@ -48,11 +184,180 @@ impl SpectreApp {
             config,
         );
 
+        // ミニマップは単位正方形を平行四辺形に写して描く
+        let overlay_src = include_str!("overlay.wgsl");
+        let overlay_resolved = sp.resolve(overlay_src).expect("failed to resolve shader");
+        let (positions, normals, indices) = minimap::create_quad_mesh();
+        // ユニフォームは使わないが、タイルと同じバインドグループのまま描けるようにレイアウトを揃える
+        let overlay = InstanceRenderer::<OverlayInstance>::with_shader(
+            gpu,
+            &view_binding.layout,
+            &positions,
+            &normals,
+            &indices,
+            &overlay_resolved,
+            InstanceRendererConfig {
+                vertex_entry: "vertex",
+                fragment_entry: "fragment",
+                depth: false,
+                storage_binding: false,
+            },
+        );
+
+        let mut controller = TilesController::new();
+        let animation = animate.map(|level| {
+            if level < controller.level() {
+                tracing::warn!(
+                    "--animate {} is below the initial level, animating level {} instead",
+                    level,
+                    controller.level()
+                );
+            }
+            while controller.level() < level {
+                let before = controller.level();
+                controller.expand();
+                // 上限に達するとexpandは何もしない
+                if controller.level() == before {
+                    tracing::warn!(
+                        "--animate {} is too large, animating level {} instead",
+                        level,
+                        before
+                    );
+                    break;
+                }
+            }
+            AnimationState {
+                animation: SubstitutionAnimation::new(controller.skeleton(), 0),
+                root_level: controller.level(),
+                time: 0.0,
+            }
+        });
+
+        let minimap = Minimap::new(controller.skeleton());
+
         Self {
             renderer,
-            scene,
-            controller: TilesController::new(),
+            overlay,
+            minimap,
+            overlay_instances: vec![],
+            view_binding,
+            controller,
             last_view: LastViewState::default(),
+            navigation: Navigation::default(),
+            command: None,
+            animation,
+            screen: None,
+            cursor: Vec2::ZERO,
+            press: None,
+            picked: None,
+        }
+    }
+
+    /// ウィンドウのpixelにあるタイルを選ぶ。情報は画面の左下のパネルに書く
+    fn pick(&mut self, pixel: Vec2) {
+        let Some(screen) = self.screen else {
+            return;
+        };
+        self.picked = self.controller.pick(screen.to_world(pixel));
+        match &self.picked {
+            Some(inspection) => tracing::info!("picked tile\n{}", inspection),
+            None => tracing::info!("no tile picked"),
+        }
+    }
+
+    /// ミニマップの上ならそこへ移動し、そうでなければタイルを選ぶ
+    fn click(&mut self, pixel: Vec2) {
+        let Some(screen) = self.screen else {
+            return;
+        };
+        match self.minimap.to_world(screen.window, pixel) {
+            Some(world) => {
+                self.navigation
+                    .look_at(world, None, screen.camera_center(), screen.camera_width())
+            }
+            None => self.pick(pixel),
+        }
+    }
+
+    /// キー操作。矢印キー・WASDで移動、+と-で拡大・縮小、QとEで30°ずつ回転、Rで元に戻す
+    fn handle_key(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed {
+            return;
+        }
+        if let Some(command) = &mut self.command {
+            match &event.logical_key {
+                Key::Named(NamedKey::Enter) => {
+                    let command = self.command.take().unwrap_or_default();
+                    self.go_to(&command);
+                }
+                Key::Named(NamedKey::Escape) => self.command = None,
+                Key::Named(NamedKey::Backspace) => {
+                    command.pop();
+                }
+                _ => {
+                    if let Some(text) = &event.text {
+                        command.push_str(text);
+                    }
+                }
+            }
+            if let Some(command) = &self.command {
+                tracing::info!("go to: {}", command);
+            }
+            return;
+        }
+
+        let Some(screen) = self.screen else {
+            return;
+        };
+        let center = screen.camera_center();
+        let step = screen.camera_width() * PAN_STEP;
+        let key = match &event.logical_key {
+            Key::Named(NamedKey::ArrowUp) => "w",
+            Key::Named(NamedKey::ArrowLeft) => "a",
+            Key::Named(NamedKey::ArrowDown) => "s",
+            Key::Named(NamedKey::ArrowRight) => "d",
+            Key::Character(c) => c.as_str(),
+            _ => return,
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "w" => self.navigation.pan(Vec2::new(0.0, step)),
+            "a" => self.navigation.pan(Vec2::new(-step, 0.0)),
+            "s" => self.navigation.pan(Vec2::new(0.0, -step)),
+            "d" => self.navigation.pan(Vec2::new(step, 0.0)),
+            "+" | "=" => self.navigation.zoom(ZOOM_STEP, center),
+            "-" => self.navigation.zoom(1.0 / ZOOM_STEP, center),
+            "q" => self.navigation.rotate(1, center),
+            "e" => self.navigation.rotate(-1, center),
+            "r" => self.navigation = Navigation::default(),
+            "g" => {
+                tracing::info!("go to (x,y or address like abch/1, Enter to jump):");
+                self.command = Some(String::new());
+            }
+            _ => {}
+        }
+    }
+
+    /// 座標またはaddressの位置を画面の中心に写す。遠ければ根をexpandする
+    fn go_to(&mut self, command: &str) {
+        let Some(screen) = self.screen else {
+            return;
+        };
+        let result = command.parse::<GoToTarget>().and_then(|target| {
+            navigation::go_to(&mut self.controller, &target, screen.camera_width())
+        });
+        match result {
+            Ok(bbox) => {
+                let size = (bbox.max - bbox.min).max_element();
+                // タイルやClusterへ行くときは、それが画面に収まるように拡大する
+                let width = (size > 0.0).then_some(size * 1.2);
+                self.navigation.look_at(
+                    (bbox.min + bbox.max) * 0.5,
+                    width,
+                    screen.camera_center(),
+                    screen.camera_width(),
+                );
+            }
+            Err(e) => tracing::warn!("{}", e),
         }
     }
 }
@@ -63,22 +368,69 @@ impl App for SpectreApp {
     fn update(&mut self, ctx: &mut UpdateContext<Camera2d>) {
         let window_size = (ctx.window_size.width, ctx.window_size.height);
 
-        // シーンユニフォーム更新
+        // カメラのビューに基づいて画面上の表示範囲を計算
         let aspect = window_size.0 as f32 / window_size.1.max(1) as f32;
-        self.scene
-            .update_from_camera(&ctx.gpu.queue, ctx.camera, aspect);
-
-        // カメラのビューに基づいてbboxを計算
         let (vp_min, vp_max) = ctx.camera.viewport_bounds(aspect);
         let half_size = (vp_max - vp_min) * 0.5 * 1.5; // 1.5倍のマージン
         let center = (vp_min + vp_max) * 0.5;
         const MIN_SIZE: f32 = 15.0;
         let half_size = Vec2::new(half_size.x.max(MIN_SIZE), half_size.y.max(MIN_SIZE));
-        let bbox = crate::utils::Aabb::from_min_max(center - half_size, center + half_size);
+        let bbox = Aabb::from_min_max(center - half_size, center + half_size);
+
+        // アニメーション中は画面をアニメーションに合わせて動かし、親のClusterを子の位置で色分けする
+        let (view, grouping) = match &mut self.animation {
+            Some(state) => {
+                state.time += ANIMATION_STEP;
+                let frame = state.animation.view(state.time);
+                let view = ViewTransform::looking_at(
+                    frame.center,
+                    frame.rotation,
+                    frame.width,
+                    center,
+                    (vp_max - vp_min).min_element(),
+                );
+                (
+                    view,
+                    Some((state.root_level, state.animation.path(frame.cycle))),
+                )
+            }
+            None => (self.navigation.view(), None),
+        };
+
+        // 画面の変換とカメラの投影はインスタンスに含めず、ユニフォームで渡す
+        self.view_binding.update(
+            &ctx.gpu.queue,
+            &ViewUniform::new(&view, &Aabb::from_min_max(vp_min, vp_max)),
+        );
+
+        // 画面の変換を戻してワールド座標系での表示範囲にする
+        let viewport = view.viewport(&bbox);
+        self.screen = Some(ScreenMapping {
+            window: Vec2::new(window_size.0 as f32, window_size.1 as f32),
+            vp_min,
+            vp_max,
+            view,
+        });
 
         // タイル更新（expand が発生した場合は同一フレーム内で再計算、最大3回）
         for _ in 0..3 {
-            match controller::update_tiles(&mut self.controller, &mut self.last_view, &bbox) {
+            // 色分けする道順と選んだタイルのaddressは、expandで変わる今の根から数える
+            let grouping = grouping.as_ref().map(|(level, path)| {
+                let mut slots = self.controller.expansion_path(*level);
+                slots.extend_from_slice(path);
+                slots
+            });
+            let highlight = self
+                .picked
+                .as_ref()
+                .map(|picked| self.controller.rerooted(picked.root_level, &picked.address));
+            match controller::update_tiles(
+                &mut self.controller,
+                &mut self.last_view,
+                &viewport,
+                grouping.as_deref(),
+                highlight.as_ref(),
+            ) {
                 Some(instances) => {
                     self.renderer.update_instances(ctx.gpu, &instances);
                     if !self.last_view.expanded {
@@ -88,6 +440,52 @@ impl App for SpectreApp {
                 None => break,
             }
         }
+
+        // ミニマップ。根が広がったら作り直す
+        if self.minimap.level() != self.controller.level() {
+            self.minimap = Minimap::new(self.controller.skeleton());
+        }
+        let visible = [
+            vp_min,
+            Vec2::new(vp_max.x, vp_min.y),
+            vp_max,
+            Vec2::new(vp_min.x, vp_max.y),
+        ]
+        .map(|p| view.inverse_apply(p));
+        let window = Vec2::new(window_size.0 as f32, window_size.1 as f32);
+        let mut instances = self.minimap.instances(window, visible);
+        if let Some(picked) = &self.picked {
+            instances.extend(minimap::inspection_panel(window, &picked.summary()));
+        }
+        if instances != self.overlay_instances {
+            self.overlay.update_instances(ctx.gpu, &instances);
+            self.overlay_instances = instances;
+        }
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Vec2::new(position.x as f32, position.y as f32);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.press = Some(self.cursor),
+                // ドラッグでカメラを動かしたときは選ばない
+                ElementState::Released => {
+                    if let Some(press) = self.press.take()
+                        && press.distance(self.cursor) < CLICK_TOLERANCE
+                    {
+                        self.click(self.cursor);
+                    }
+                }
+            },
+            WindowEvent::KeyboardInput { event, .. } => self.handle_key(event),
+            _ => {}
+        }
     }
 
     fn encode(&mut self, ctx: &mut FrameContext<Camera2d>) {
@@ -107,12 +505,21 @@ impl App for SpectreApp {
             occlusion_query_set: None,
         });
 
-        pass.set_bind_group(0, self.scene.bind_group(), &[]);
+        pass.set_bind_group(0, &self.view_binding.bind_group, &[]);
         self.renderer.render(&mut pass);
+        self.overlay.render(&mut pass);
     }
 }
 
+/// `--animate LEVEL`で、levelのClusterが子に分かれていくアニメーションを再生する
+///
+/// levelは最初の根のlevelからexpandできる上限までの範囲に切り詰める
 pub fn run() {
+    let animate = std::env::args()
+        .skip_while(|arg| arg != "--animate")
+        .nth(1)
+        .and_then(|level| level.parse().ok());
+
     let mut camera = Camera2d::default();
     camera.zoom = 0.028;
     camera.damping = 0.95;
@@ -124,7 +531,7 @@ pub fn run() {
     let mut config = RunConfig::new("Infinite Spectres").with_camera(camera);
     config.sample_count = 4;
     mikage::run(
-        |gpu: &GpuContext, size: PhysicalSize<u32>| SpectreApp::new(gpu, size),
+        |gpu: &GpuContext, size: PhysicalSize<u32>| SpectreApp::new(gpu, size, animate),
         config,
     );
 }
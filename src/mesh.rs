@@ -0,0 +1,10 @@
+mod extrude;
+mod gltf;
+mod obj;
+mod stl;
+
+pub(crate) use extrude::tessellate;
+pub use extrude::{ExtrudeOptions, HeightMode, TriangleMesh};
+pub use gltf::write_glb;
+pub use obj::write_obj;
+pub use stl::write_stl;
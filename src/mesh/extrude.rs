@@ -0,0 +1,351 @@
+use glam::{Mat2, Vec2, Vec3};
+use lyon_tessellation::{
+    geom::Point, geometry_builder::simple_builder, path::Path, FillOptions, FillTessellator,
+    VertexBuffers,
+};
+
+use crate::{
+    tiles::{Anchor, Spectre, SpectreIter, TileAddress},
+    utils::{Angle, HexVec, Region},
+};
+
+/// 多角形を三角形に分割する。三角形は反時計回り
+pub(crate) fn tessellate(outline: &[Vec2]) -> (Vec<Vec2>, Vec<u32>) {
+    let mut path_builder = Path::builder();
+    path_builder.begin(Point::new(outline[0].x, outline[0].y));
+    for point in outline.iter().skip(1) {
+        path_builder.line_to(Point::new(point.x, point.y));
+    }
+    path_builder.close();
+    let path = path_builder.build();
+
+    let mut buffers: VertexBuffers<Point<f32>, u16> = VertexBuffers::new();
+    {
+        let mut vertex_builder = simple_builder(&mut buffers).with_inverted_winding(); // 反時計回りにする
+        let mut tessellator = FillTessellator::new();
+        let result =
+            tessellator.tessellate_path(&path, &FillOptions::default(), &mut vertex_builder);
+        assert!(result.is_ok());
+    }
+
+    let vertices = buffers
+        .vertices
+        .iter()
+        .map(|p| Vec2::new(p.x, p.y))
+        .collect();
+    let indices = buffers.indices.iter().map(|&i| i as u32).collect();
+    (vertices, indices)
+}
+
+/// タイルごとの高さの決め方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeightMode {
+    /// すべてheight
+    Constant,
+    /// 回転（0〜11）が1増えるごとにstepずつ高くする
+    Rotation { step: f32 },
+    /// levelのSupertileが親の中で占める位置（a〜h）ごとにstepずつ高くする。
+    /// 同じSupertileに含まれるタイルは同じ高さになる。道順の分からないタイルはheight
+    Level { level: usize, step: f32 },
+}
+
+/// 押し出しの設定。長さはすべて書き出す座標の単位
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtrudeOptions {
+    /// タイルの辺一本の長さ
+    pub edge_length: f32,
+    /// 柱の高さの基準
+    pub height: f32,
+    /// 隣り合うタイルの間の隙間
+    pub gap: f32,
+    pub height_mode: HeightMode,
+}
+
+impl Default for ExtrudeOptions {
+    fn default() -> Self {
+        Self {
+            edge_length: 10.0,
+            height: 2.0,
+            gap: 0.0,
+            height_mode: HeightMode::Constant,
+        }
+    }
+}
+
+impl ExtrudeOptions {
+    fn tile_height(&self, tile: &Spectre, address: Option<&TileAddress>) -> f32 {
+        match self.height_mode {
+            HeightMode::Constant => self.height,
+            HeightMode::Rotation { step } => self.height + step * tile.rotation().value() as f32,
+            HeightMode::Level { level, step } => {
+                let slot = address.and_then(|address| {
+                    let slots = address.slots();
+                    slots.len().checked_sub(level + 1).map(|i| slots[i])
+                });
+                self.height + step * slot.unwrap_or(0) as f32
+            }
+        }
+    }
+}
+
+/// 面ごとに頂点を分けた三角形メッシュ。z軸が上で、三角形は外から見て反時計回り
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriangleMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl TriangleMesh {
+    /// spectres_inで列挙されたタイルを柱に押し出す。HeightMode::Levelには道順を使う
+    pub fn extrude<R: Region>(mut iter: SpectreIter<'_, R>, options: &ExtrudeOptions) -> Self {
+        let mut builder = Extruder::new(options);
+        while let Some(spectre) = iter.next() {
+            let height = options.tile_height(spectre, iter.address().as_ref());
+            builder.add_tile(spectre, height);
+        }
+        builder.mesh
+    }
+
+    /// 道順の分からないタイルを柱に押し出す
+    pub fn extrude_tiles<'a>(
+        tiles: impl IntoIterator<Item = &'a Spectre>,
+        options: &ExtrudeOptions,
+    ) -> Self {
+        let mut builder = Extruder::new(options);
+        for tile in tiles {
+            builder.add_tile(tile, options.tile_height(tile, None));
+        }
+        builder.mesh
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// 三角形ごとの3頂点の位置
+    pub fn triangles(&self) -> impl Iterator<Item = [Vec3; 3]> + '_ {
+        self.indices
+            .chunks(3)
+            .map(|t| [t[0], t[1], t[2]].map(|i| Vec3::from(self.positions[i as usize])))
+    }
+
+    fn push_face(&mut self, corners: &[Vec3], triangles: &[u32], normal: Vec3) {
+        let base = self.positions.len() as u32;
+        self.positions.extend(corners.iter().map(|p| p.to_array()));
+        self.normals
+            .extend(std::iter::repeat_n(normal.to_array(), corners.len()));
+        self.indices.extend(triangles.iter().map(|&i| base + i));
+    }
+}
+
+/// 基準のタイルを一度だけ三角形に分割し、各タイルへ写して柱を作る
+struct Extruder {
+    /// 基準のタイル（Anchor1が原点、回転0）の頂点
+    reference: Vec<Vec2>,
+    /// 隙間の分だけ内側にずらした基準のタイルの輪郭（反時計回り）
+    outline: Vec<Vec2>,
+    /// outlineを分割した三角形
+    triangles: (Vec<Vec2>, Vec<u32>),
+    scale: f32,
+    mesh: TriangleMesh,
+}
+
+impl Extruder {
+    fn new(options: &ExtrudeOptions) -> Self {
+        let reference: Vec<Vec2> = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO)
+            .vertices()
+            .into_iter()
+            .map(HexVec::to_vec2)
+            .collect();
+        let mut outline = offset_inward(&reference, options.gap * 0.5 / options.edge_length);
+        if signed_area(&outline) < 0.0 {
+            outline.reverse();
+        }
+        Self {
+            triangles: tessellate(&outline),
+            reference,
+            outline,
+            scale: options.edge_length,
+            mesh: TriangleMesh::default(),
+        }
+    }
+
+    fn add_tile(&mut self, tile: &Spectre, height: f32) {
+        let vertices: Vec<Vec2> = tile.vertices().into_iter().map(HexVec::to_vec2).collect();
+        let (linear, origin) = isometry(&self.reference, &vertices);
+        // 鏡映されたタイルでは写すと向きが逆になる
+        let reflected = linear.determinant() < 0.0;
+        let map = |p: Vec2| (linear * p + origin) * self.scale;
+
+        let (points, indices) = &self.triangles;
+        let mut flipped = indices.clone();
+        for triangle in flipped.chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+        let (up, down) = if reflected {
+            (&flipped, indices)
+        } else {
+            (indices, &flipped)
+        };
+        let top: Vec<Vec3> = points.iter().map(|&p| map(p).extend(height)).collect();
+        let bottom: Vec<Vec3> = points.iter().map(|&p| map(p).extend(0.0)).collect();
+        self.mesh.push_face(&top, up, Vec3::Z);
+        self.mesh.push_face(&bottom, down, -Vec3::Z);
+
+        let mut outline: Vec<Vec2> = self.outline.iter().map(|&p| map(p)).collect();
+        if reflected {
+            outline.reverse();
+        }
+        for (i, &from) in outline.iter().enumerate() {
+            let to = outline[(i + 1) % outline.len()];
+            // 反時計回りの輪郭の右側が外
+            let normal = (to - from).perp().normalize() * -1.0;
+            let corners = [
+                from.extend(0.0),
+                to.extend(0.0),
+                to.extend(height),
+                from.extend(height),
+            ];
+            self.mesh
+                .push_face(&corners, &[0, 1, 2, 0, 2, 3], normal.extend(0.0));
+        }
+    }
+}
+
+/// fromの頂点をtoの頂点へ写す合同変換（線形部分と平行移動）
+fn isometry(from: &[Vec2], to: &[Vec2]) -> (Mat2, Vec2) {
+    // 原点から見て十分に向きの違う2頂点を使う
+    let u = from[1] - from[0];
+    let k = (2..from.len())
+        .max_by(|&a, &b| {
+            let cross = |i: usize| u.perp_dot(from[i] - from[0]).abs();
+            cross(a).total_cmp(&cross(b))
+        })
+        .unwrap();
+    let source = Mat2::from_cols(u, from[k] - from[0]);
+    let target = Mat2::from_cols(to[1] - to[0], to[k] - to[0]);
+    let linear = target * source.inverse();
+    (linear, to[0] - linear * from[0])
+}
+
+/// 多角形の辺をdistanceだけ内側に平行移動した多角形
+fn offset_inward(points: &[Vec2], distance: f32) -> Vec<Vec2> {
+    if distance == 0.0 {
+        return points.to_vec();
+    }
+    // 反時計回りなら左が内側
+    let side = signed_area(points).signum();
+    (0..points.len())
+        .map(|i| {
+            let prev = points[(i + points.len() - 1) % points.len()];
+            let next = points[(i + 1) % points.len()];
+            let n1 = (points[i] - prev).perp().normalize() * side;
+            let n2 = (next - points[i]).perp().normalize() * side;
+            // 両側の辺から同じ距離になる位置
+            points[i] + (n1 + n2) / (1.0 + n1.dot(n2)) * distance
+        })
+        .collect()
+}
+
+/// 面積の2倍。反時計回りなら正
+fn signed_area(points: &[Vec2]) -> f32 {
+    (0..points.len())
+        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tiles::SpectreCluster, utils::Orientation};
+
+    /// 閉じた面の体積
+    fn volume(mesh: &TriangleMesh) -> f32 {
+        mesh.triangles()
+            .map(|[a, b, c]| a.dot(b.cross(c)) / 6.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_volume() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let (points, indices) = tessellate(
+            &tile
+                .vertices()
+                .into_iter()
+                .map(HexVec::to_vec2)
+                .collect::<Vec<_>>(),
+        );
+        let area: f32 = indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| points[t[i] as usize]);
+                (b - a).perp_dot(c - a) / 2.0
+            })
+            .sum();
+
+        for tile in [
+            tile,
+            Spectre::with_anchor(Anchor::Anchor2, HexVec::ZERO, Angle::new(5))
+                .transformed(HexVec::ZERO, Orientation::new(Angle::new(3), true)),
+        ] {
+            let options = ExtrudeOptions {
+                edge_length: 2.0,
+                height: 3.0,
+                ..Default::default()
+            };
+            let mesh = TriangleMesh::extrude_tiles([&tile], &options);
+            // 向きが揃った閉じた面なので、体積が面積×高さになる
+            assert!((volume(&mesh) - area * 4.0 * 3.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_gap() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 1);
+        let solid = TriangleMesh::extrude(cluster.spectres_in(cluster.bbox()), &Default::default());
+        let options = ExtrudeOptions {
+            gap: 0.5,
+            ..Default::default()
+        };
+        let gapped = TriangleMesh::extrude(cluster.spectres_in(cluster.bbox()), &options);
+        assert_eq!(solid.triangle_count(), gapped.triangle_count());
+        assert!(volume(&gapped) < volume(&solid));
+        // 隙間を空けると隣のタイルと頂点を共有しない
+        let tops = |mesh: &TriangleMesh| -> Vec<[i32; 2]> {
+            mesh.positions
+                .iter()
+                .filter(|p| p[2] > 0.0)
+                .map(|p| [(p[0] * 100.0).round() as i32, (p[1] * 100.0).round() as i32])
+                .collect()
+        };
+        let gapped_tops = tops(&gapped);
+        let mut unique = gapped_tops.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        let mut solid_unique = tops(&solid);
+        solid_unique.sort_unstable();
+        solid_unique.dedup();
+        assert!(unique.len() > solid_unique.len());
+    }
+
+    #[test]
+    fn test_height_mode() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let options = ExtrudeOptions {
+            height: 1.0,
+            height_mode: HeightMode::Level {
+                level: 1,
+                step: 1.0,
+            },
+            ..Default::default()
+        };
+        let mesh = TriangleMesh::extrude(cluster.spectres_in(cluster.bbox()), &options);
+        let mut heights: Vec<i32> = mesh.positions.iter().map(|p| p[2] as i32).collect();
+        heights.sort_unstable();
+        heights.dedup();
+        // level 1のClusterの位置a〜hで8通り、と床
+        assert_eq!(heights, vec![0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
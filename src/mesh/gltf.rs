@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+
+use super::TriangleMesh;
+
+/// バイナリglTF（GLB）として書き出す
+///
+/// glTFはy軸が上なので、z軸が上のメッシュを(x, z, -y)に回して書く。
+/// 要素が0個のaccessorは書けないので、空のメッシュはノードのない空のシーンとして書く
+pub fn write_glb(mesh: &TriangleMesh, writer: impl Write) -> io::Result<()> {
+    if mesh.positions.is_empty() || mesh.indices.is_empty() {
+        let json = serde_json::json!({
+            "asset": { "version": "2.0", "generator": "Infinite Spectres" },
+            "scene": 0,
+            "scenes": [{}],
+        });
+        return write_chunks(&json, None, writer);
+    }
+
+    let to_y_up = |[x, y, z]: [f32; 3]| [x, z, -y];
+    let positions: Vec<[f32; 3]> = mesh.positions.iter().map(|&p| to_y_up(p)).collect();
+    let normals: Vec<[f32; 3]> = mesh.normals.iter().map(|&n| to_y_up(n)).collect();
+
+    let mut binary: Vec<u8> = vec![];
+    binary.extend_from_slice(bytemuck::cast_slice(&positions));
+    binary.extend_from_slice(bytemuck::cast_slice(&normals));
+    binary.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+    let vertex_bytes = positions.len() * 12;
+
+    // POSITIONにはminとmaxが必須
+    let (min, max) = positions
+        .iter()
+        .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), p| {
+            (
+                [0, 1, 2].map(|i| min[i].min(p[i])),
+                [0, 1, 2].map(|i| max[i].max(p[i])),
+            )
+        });
+    let json = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "Infinite Spectres" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1 },
+                "indices": 2,
+            }],
+        }],
+        "buffers": [{ "byteLength": binary.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": vertex_bytes, "target": 34962 },
+            { "buffer": 0, "byteOffset": vertex_bytes, "byteLength": vertex_bytes, "target": 34962 },
+            {
+                "buffer": 0,
+                "byteOffset": vertex_bytes * 2,
+                "byteLength": mesh.indices.len() * 4,
+                "target": 34963,
+            },
+        ],
+        // 5126 = FLOAT、5125 = UNSIGNED_INT
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": positions.len(),
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            },
+            { "bufferView": 1, "componentType": 5126, "count": normals.len(), "type": "VEC3" },
+            {
+                "bufferView": 2,
+                "componentType": 5125,
+                "count": mesh.indices.len(),
+                "type": "SCALAR",
+            },
+        ],
+    });
+
+    write_chunks(&json, Some(binary), writer)
+}
+
+/// ヘッダーとJSONチャンク、あればバイナリチャンクを書く
+fn write_chunks(
+    json: &serde_json::Value,
+    binary: Option<Vec<u8>>,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    // チャンクは4バイト境界に揃える。JSONは空白、バイナリは0で埋める
+    let mut json = json.to_string().into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut chunks = vec![(b"JSON", json)];
+    if let Some(mut binary) = binary {
+        binary.resize(binary.len().next_multiple_of(4), 0);
+        chunks.push((b"BIN\0", binary));
+    }
+    let length = 12
+        + chunks
+            .iter()
+            .map(|(_, chunk)| 8 + chunk.len())
+            .sum::<usize>();
+
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+    for (kind, chunk) in chunks {
+        writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(kind)?;
+        writer.write_all(&chunk)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mesh::ExtrudeOptions,
+        tiles::{Anchor, Spectre},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_layout() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let mesh = TriangleMesh::extrude_tiles([&tile], &ExtrudeOptions::default());
+        let mut glb = vec![];
+        write_glb(&mesh, &mut glb).unwrap();
+
+        let word = |offset: usize| u32::from_le_bytes(glb[offset..offset + 4].try_into().unwrap());
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(word(8) as usize, glb.len());
+        let json_length = word(12) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        let json: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        assert_eq!(&glb[24 + json_length..28 + json_length], b"BIN\0");
+        let binary_length = word(20 + json_length) as usize;
+        assert!(binary_length >= json["buffers"][0]["byteLength"].as_u64().unwrap() as usize);
+        assert_eq!(json["accessors"][2]["count"], mesh.indices.len());
+        // 高さはyになる
+        assert_eq!(json["accessors"][0]["max"][1], 2.0);
+    }
+
+    #[test]
+    fn test_empty_mesh() {
+        let mesh = TriangleMesh::extrude_tiles([], &ExtrudeOptions::default());
+        assert!(mesh.indices.is_empty());
+        let mut glb = vec![];
+        write_glb(&mesh, &mut glb).unwrap();
+
+        // バイナリチャンクもaccessorも持たない
+        let json_length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(glb.len(), 20 + json_length);
+        let json: serde_json::Value = serde_json::from_slice(&glb[20..]).unwrap();
+        assert!(json.get("accessors").is_none());
+        assert!(json.get("meshes").is_none());
+        assert_eq!(json["scenes"][0], serde_json::json!({}));
+    }
+}
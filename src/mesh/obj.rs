@@ -0,0 +1,43 @@
+use std::io::{self, Write};
+
+use super::TriangleMesh;
+
+/// Wavefront OBJとして書き出す
+pub fn write_obj(mesh: &TriangleMesh, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "# Infinite Spectres")?;
+    for [x, y, z] in &mesh.positions {
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    for [x, y, z] in &mesh.normals {
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+    // 頂点と法線は同じ番号で、1から数える
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mesh::ExtrudeOptions,
+        tiles::{Anchor, Spectre},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_counts() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let mesh = TriangleMesh::extrude_tiles([&tile], &ExtrudeOptions::default());
+        let mut obj = vec![];
+        write_obj(&mesh, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let count = |prefix: &str| obj.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!(count("v "), mesh.positions.len());
+        assert_eq!(count("vn "), mesh.positions.len());
+        assert_eq!(count("f "), mesh.triangle_count());
+    }
+}
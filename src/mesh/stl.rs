@@ -0,0 +1,45 @@
+use std::io::{self, Write};
+
+use super::TriangleMesh;
+
+/// バイナリSTLとして書き出す
+pub fn write_stl(mesh: &TriangleMesh, mut writer: impl Write) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let title = b"Infinite Spectres";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+    for triangle in mesh.triangles() {
+        let [a, b, c] = triangle;
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        for v in [normal, a, b, c] {
+            for component in v.to_array() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        // 属性バイト数
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mesh::ExtrudeOptions,
+        tiles::{Anchor, Spectre},
+        utils::{Angle, HexVec},
+    };
+
+    #[test]
+    fn test_size() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let mesh = TriangleMesh::extrude_tiles([&tile], &ExtrudeOptions::default());
+        let mut stl = vec![];
+        write_stl(&mesh, &mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * mesh.triangle_count());
+        let count = u32::from_le_bytes(stl[80..84].try_into().unwrap());
+        assert_eq!(count as usize, mesh.triangle_count());
+    }
+}
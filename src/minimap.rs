@@ -0,0 +1,452 @@
+use glam::Vec2;
+use mikage::InstanceVertex;
+
+use crate::{
+    font,
+    raster::hsv_to_rgb,
+    tiles::Skeleton,
+    utils::{Aabb, ConvexPolygon},
+};
+
+/// 画面の隅に描く平行四辺形
+///
+/// 単位正方形のメッシュの(u, v)を origin + u * axis_x + v * axis_y に写す。座標はクリップ座標
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverlayInstance {
+    pub origin: [f32; 2],
+    pub axis_x: [f32; 2],
+    pub axis_y: [f32; 2],
+    /// 線形RGB
+    pub color: [f32; 3],
+}
+
+impl InstanceVertex for OverlayInstance {
+    fn vertex_attributes() -> Vec<mikage::wgpu::VertexAttribute> {
+        vec![
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 2,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32x2,
+                offset: 8,
+                shader_location: 3,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32x2,
+                offset: 16,
+                shader_location: 4,
+            },
+            mikage::wgpu::VertexAttribute {
+                format: mikage::wgpu::VertexFormat::Float32x3,
+                offset: 24,
+                shader_location: 5,
+            },
+        ]
+    }
+}
+
+/// OverlayInstanceで使う単位正方形のメッシュ
+pub fn create_quad_mesh() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+    let positions = vec![
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    let normals = vec![[0.0, 0.0, 1.0]; 4];
+    (positions, normals, vec![0, 1, 2, 0, 2, 3])
+}
+
+const BACKGROUND: [f32; 3] = [0.85, 0.85, 0.85];
+const INK: [f32; 3] = [0.15, 0.15, 0.15];
+const VIEWPORT: [f32; 3] = [0.9, 0.1, 0.1];
+
+/// 根のClusterの形と、その中で今見ている範囲を示す地図
+///
+/// 根を何段か分けた子の凸包を格子で塗り、根の子（a〜h）ごとに色を変える。
+/// 地図は常に北が上で、画面の回転は見ている範囲の向きで示す
+pub struct Minimap {
+    level: usize,
+    /// 地図に写すワールド座標の正方形
+    area: Aabb,
+    /// 塗る範囲（ワールド座標）と根の子の位置
+    cells: Vec<(Aabb, u8)>,
+}
+
+impl Minimap {
+    /// 格子の一辺の数
+    const RESOLUTION: usize = 40;
+    /// 根を何段まで分けるか
+    const DEPTH: usize = 3;
+    /// 地図の一辺の、ウィンドウの短い辺に対する割合
+    const SIZE: f32 = 0.25;
+    /// ウィンドウの端からの余白（ピクセル）
+    const MARGIN: f32 = 12.0;
+
+    pub fn new(root: Skeleton) -> Self {
+        // 根をDEPTH段分けた子の凸包を、根の子の位置と一緒に集める
+        let mut parts = vec![(root, false, None)];
+        for _ in 0..Self::DEPTH.min(root.level()) {
+            parts = parts
+                .into_iter()
+                .flat_map(|(skeleton, is_mystic, slot)| {
+                    skeleton
+                        .split_into_skeletons()
+                        .into_iter()
+                        .enumerate()
+                        // MysticClusterはeを持たない
+                        .filter(move |&(i, _)| !(is_mystic && i == 4))
+                        .map(move |(i, child)| (child, i == 7, slot.or(Some(i as u8))))
+                })
+                .collect();
+        }
+        let parts: Vec<(Aabb, ConvexPolygon, u8)> = parts
+            .into_iter()
+            .map(|(skeleton, is_mystic, slot)| {
+                (
+                    skeleton.bbox(is_mystic),
+                    skeleton.bounding_polygon(is_mystic),
+                    slot.unwrap_or(0),
+                )
+            })
+            .collect();
+
+        let bbox = root.bbox(false);
+        let center = (bbox.min + bbox.max) * 0.5;
+        let half = (bbox.max - bbox.min).max_element() * 0.55;
+        let area = Aabb::from_min_max(center - half, center + half);
+
+        // 格子の行ごとに、同じ子が続くセルを一つの長方形にまとめる
+        let cell = 2.0 * half / Self::RESOLUTION as f32;
+        let mut cells = vec![];
+        for row in 0..Self::RESOLUTION {
+            let mut run: Option<(usize, u8)> = None;
+            for column in 0..=Self::RESOLUTION {
+                let slot = (column < Self::RESOLUTION)
+                    .then(|| {
+                        let point =
+                            area.min + Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * cell;
+                        parts
+                            .iter()
+                            .find(|(bbox, polygon, _)| {
+                                bbox.contains(point) && polygon.contains(point)
+                            })
+                            .map(|&(_, _, slot)| slot)
+                    })
+                    .flatten();
+                if let Some((start, run_slot)) = run
+                    && slot != Some(run_slot)
+                {
+                    let min = area.min + Vec2::new(start as f32, row as f32) * cell;
+                    let max = area.min + Vec2::new(column as f32, row as f32 + 1.0) * cell;
+                    cells.push((Aabb::from_min_max(min, max), run_slot));
+                    run = None;
+                }
+                if run.is_none() {
+                    run = slot.map(|slot| (column, slot));
+                }
+            }
+        }
+
+        Self {
+            level: root.level(),
+            area,
+            cells,
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// ウィンドウの中で地図を描く範囲（ピクセル、左上が原点）。右上の隅に置く
+    pub fn frame(window: Vec2) -> Aabb {
+        let side = window.min_element() * Self::SIZE;
+        let min = Vec2::new(window.x - Self::MARGIN - side, Self::MARGIN);
+        Aabb::from_min_max(min, min + side)
+    }
+
+    /// ワールド座標を地図の上のピクセルにする
+    fn to_pixel(&self, frame: &Aabb, world: Vec2) -> Vec2 {
+        let t = (world - self.area.min) / (self.area.max - self.area.min);
+        Vec2::new(
+            frame.min.x + t.x * (frame.max.x - frame.min.x),
+            frame.max.y - t.y * (frame.max.y - frame.min.y),
+        )
+    }
+
+    /// ウィンドウのpixelが地図の上ならワールド座標を返す
+    pub fn to_world(&self, window: Vec2, pixel: Vec2) -> Option<Vec2> {
+        let frame = Self::frame(window);
+        if !frame.contains(pixel) {
+            return None;
+        }
+        let t = (pixel - frame.min) / (frame.max - frame.min);
+        let size = self.area.max - self.area.min;
+        Some(Vec2::new(
+            self.area.min.x + t.x * size.x,
+            self.area.max.y - t.y * size.y,
+        ))
+    }
+
+    /// 地図を描く平行四辺形。visibleは画面の四隅に写っているワールド座標
+    pub fn instances(&self, window: Vec2, visible: [Vec2; 4]) -> Vec<OverlayInstance> {
+        let frame = Self::frame(window);
+        let mut overlay = Overlay::new(window);
+        overlay.rect(frame.min, frame.max, BACKGROUND);
+        for (cell, slot) in &self.cells {
+            let color = hsv_to_rgb(*slot as f32 * 0.785, 0.45, 0.9);
+            let a = self.to_pixel(&frame, cell.min);
+            let b = self.to_pixel(&frame, cell.max);
+            overlay.rect(a.min(b), a.max(b), color);
+        }
+        let frame_corners = [
+            frame.min,
+            Vec2::new(frame.max.x, frame.min.y),
+            frame.max,
+            Vec2::new(frame.min.x, frame.max.y),
+        ];
+        for i in 0..4 {
+            overlay.line(frame_corners[i], frame_corners[(i + 1) % 4], 1.0, INK);
+        }
+
+        // 見ている範囲。地図からはみ出す部分は縁に寄せ、小さすぎて見えないときは点で示す
+        let corners = visible.map(|p| self.to_pixel(&frame, p).clamp(frame.min, frame.max));
+        let size = (corners[2] - corners[0]).length();
+        if size < 6.0 {
+            let center = (corners[0] + corners[2]) * 0.5;
+            overlay.rect(center - 3.0, center + 3.0, VIEWPORT);
+        } else {
+            for i in 0..4 {
+                overlay.line(corners[i], corners[(i + 1) % 4], 2.0, VIEWPORT);
+            }
+        }
+
+        overlay.label(frame.min + 6.0, &format!("L{}", self.level), 14.0, INK);
+        overlay.0
+    }
+}
+
+/// ウィンドウのピクセルで平行四辺形を作り、クリップ座標にする
+struct Overlay(Vec<OverlayInstance>, Vec2);
+
+impl Overlay {
+    fn new(window: Vec2) -> Self {
+        Self(vec![], window.max(Vec2::ONE))
+    }
+
+    fn push(&mut self, origin: Vec2, axis_x: Vec2, axis_y: Vec2, color: [f32; 3]) {
+        let scale = Vec2::new(2.0, -2.0) / self.1;
+        let mut origin = Vec2::new(-1.0, 1.0) + origin * scale;
+        let mut axis_x = axis_x * scale;
+        let axis_y = axis_y * scale;
+        // クリップ座標で反時計回りにする
+        if axis_x.perp_dot(axis_y) < 0.0 {
+            origin += axis_x;
+            axis_x = -axis_x;
+        }
+        self.0.push(OverlayInstance {
+            origin: origin.into(),
+            axis_x: axis_x.into(),
+            axis_y: axis_y.into(),
+            color,
+        });
+    }
+
+    fn rect(&mut self, min: Vec2, max: Vec2, color: [f32; 3]) {
+        let size = max - min;
+        self.push(min, Vec2::new(size.x, 0.0), Vec2::new(0.0, size.y), color);
+    }
+
+    fn line(&mut self, p: Vec2, q: Vec2, width: f32, color: [f32; 3]) {
+        let direction = (q - p).normalize_or_zero();
+        let normal = direction.perp() * width;
+        // 角が欠けないよう両端を太さの半分だけ延ばす
+        let start = p - direction * width * 0.5 - normal * 0.5;
+        self.push(start, q - p + direction * width, normal, color);
+    }
+
+    /// 5×7ドットのフォントで一行の文字を書く。横に続くドットは一つの長方形にまとめる
+    fn label(&mut self, top_left: Vec2, text: &str, height: f32, color: [f32; 3]) {
+        let dot = height / font::HEIGHT as f32;
+        let mut x = top_left.x;
+        for c in text.chars() {
+            for (row, &bits) in font::glyph(c).iter().enumerate() {
+                let lit = |column: usize| bits & (1 << (font::WIDTH - 1 - column)) != 0;
+                let mut column = 0;
+                while column < font::WIDTH {
+                    if !lit(column) {
+                        column += 1;
+                        continue;
+                    }
+                    let start = column;
+                    while column < font::WIDTH && lit(column) {
+                        column += 1;
+                    }
+                    let min = Vec2::new(x + start as f32 * dot, top_left.y + row as f32 * dot);
+                    let size = Vec2::new((column - start) as f32 * dot, dot);
+                    self.rect(min, min + size, color);
+                }
+            }
+            x += (font::WIDTH + 1) as f32 * dot;
+        }
+    }
+}
+
+/// 選んだタイルの情報を画面の左下に書くパネル
+///
+/// textは一行ずつ書き、ウィンドウの幅の半分に収まらない行は空白で折り返す
+pub fn inspection_panel(window: Vec2, text: &str) -> Vec<OverlayInstance> {
+    /// 文字の高さ（ピクセル）。ドットがピクセルに揃うようにする
+    const HEIGHT: f32 = 2.0 * font::HEIGHT as f32;
+    const LINE: f32 = HEIGHT * 1.5;
+    const PADDING: f32 = 8.0;
+    let advance = HEIGHT / font::HEIGHT as f32 * (font::WIDTH + 1) as f32;
+    let columns = ((window.x * 0.5 - Minimap::MARGIN - 2.0 * PADDING) / advance).max(1.0) as usize;
+    let lines: Vec<String> = text.lines().flat_map(|line| wrap(line, columns)).collect();
+
+    let longest = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let size = Vec2::new(
+        longest as f32 * advance + 2.0 * PADDING,
+        lines.len() as f32 * LINE - (LINE - HEIGHT) + 2.0 * PADDING,
+    );
+    let min = Vec2::new(Minimap::MARGIN, window.y - Minimap::MARGIN - size.y);
+    let max = min + size;
+
+    let mut overlay = Overlay::new(window);
+    overlay.rect(min, max, BACKGROUND);
+    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+    for i in 0..4 {
+        overlay.line(corners[i], corners[(i + 1) % 4], 1.0, INK);
+    }
+    for (i, line) in lines.iter().enumerate() {
+        let top_left = min + Vec2::new(PADDING, PADDING + i as f32 * LINE);
+        overlay.label(top_left, line, HEIGHT, INK);
+    }
+    overlay.0
+}
+
+/// lineを空白でcolumns文字以内の行に分ける。columnsより長い語はそのまま一行にする
+fn wrap(line: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in line.split(' ') {
+        let length = current.chars().count();
+        if length > 0 && length + 1 + word.chars().count() > columns {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::Anchor,
+        utils::{Angle, HexVec},
+    };
+
+    fn minimap() -> Minimap {
+        Minimap::new(Skeleton::with_anchor(
+            Anchor::Anchor1,
+            HexVec::ZERO,
+            Angle::ZERO,
+            6,
+        ))
+    }
+
+    #[test]
+    fn test_cells_cover_root() {
+        let minimap = minimap();
+        assert_eq!(minimap.level(), 6);
+        assert!(!minimap.cells.is_empty());
+        // 8つの子がすべて塗られ、格子より多くの長方形にはならない
+        let mut slots: Vec<u8> = minimap.cells.iter().map(|&(_, slot)| slot).collect();
+        slots.sort();
+        slots.dedup();
+        assert_eq!(slots, (0..8).collect::<Vec<_>>());
+        assert!(minimap.cells.len() < Minimap::RESOLUTION * Minimap::RESOLUTION);
+    }
+
+    #[test]
+    fn test_click_round_trip() {
+        let minimap = minimap();
+        let window = Vec2::new(800.0, 600.0);
+        let frame = Minimap::frame(window);
+        assert!(frame.max.x <= window.x && frame.min.y >= 0.0);
+
+        let world = (minimap.area.min + minimap.area.max) * 0.5 + Vec2::new(10.0, -20.0);
+        let pixel = minimap.to_pixel(&frame, world);
+        let back = minimap.to_world(window, pixel).unwrap();
+        assert!((back - world).length() < 1e-2);
+        assert!(minimap.to_world(window, Vec2::new(10.0, 500.0)).is_none());
+    }
+
+    #[test]
+    fn test_instances_in_clip_space() {
+        let minimap = minimap();
+        // 地図からはみ出す大きな範囲
+        let visible = [
+            Vec2::new(-2000.0, -1000.0),
+            Vec2::new(2000.0, -1000.0),
+            Vec2::new(2000.0, 1000.0),
+            Vec2::new(-2000.0, 1000.0),
+        ];
+        let instances = minimap.instances(Vec2::new(800.0, 600.0), visible);
+        assert!(instances.len() > minimap.cells.len() + 8);
+        for instance in &instances {
+            let origin = Vec2::from(instance.origin);
+            let axis_x = Vec2::from(instance.axis_x);
+            let axis_y = Vec2::from(instance.axis_y);
+            // 反時計回りで、地図は画面の右上に収まる
+            assert!(axis_x.perp_dot(axis_y) >= 0.0);
+            for corner in [
+                origin,
+                origin + axis_x,
+                origin + axis_y,
+                origin + axis_x + axis_y,
+            ] {
+                assert!(corner.x > -0.01 && corner.x <= 1.0 + 1e-4);
+                assert!(corner.y > -0.01 && corner.y <= 1.0 + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_inspection_panel() {
+        let window = Vec2::new(800.0, 600.0);
+        let text = format!(
+            "address: ahhb/0\nvertices:{}",
+            " (1/2 + 3√3/2, -2)".repeat(8)
+        );
+        let instances = inspection_panel(window, &text);
+        assert!(!instances.is_empty());
+        // パネルは画面の左下に、ウィンドウの幅の半分に収まる
+        for instance in &instances {
+            let origin = Vec2::from(instance.origin);
+            let corner = origin + Vec2::from(instance.axis_x) + Vec2::from(instance.axis_y);
+            for p in [origin, corner] {
+                assert!(p.x >= -1.0 - 1e-4 && p.x <= 0.01);
+                assert!(p.y >= -1.0 - 1e-4 && p.y <= 1.0);
+            }
+        }
+
+        // 長い行は語の途中で切らずに折り返す
+        let lines = wrap("vertices: (0, 0) (1, 0)", 12);
+        assert_eq!(lines, ["vertices:", "(0, 0) (1,", "0)"]);
+        assert!(lines.iter().all(|line| line.chars().count() <= 12));
+    }
+}
@@ -0,0 +1,318 @@
+use std::str::FromStr;
+
+use glam::Vec2;
+
+use crate::{
+    controller::{TilesController, UpdateMargins, ViewTransform},
+    raster::covers,
+    tiles::TileAddress,
+    utils::{Aabb, Angle},
+};
+
+/// キーボードで動かす画面の変換
+///
+/// マウスで動かすカメラとは別に持ち、ワールド座標をカメラの座標に写す。
+/// focusをカメラの原点に写し、rotationだけ回転してscale倍する
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Navigation {
+    pub focus: Vec2,
+    pub rotation: Angle,
+    pub scale: f32,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Self {
+            focus: Vec2::ZERO,
+            rotation: Angle::ZERO,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Navigation {
+    const MIN_SCALE: f32 = 1.0 / 1024.0;
+    const MAX_SCALE: f32 = 1024.0;
+
+    pub fn view(&self) -> ViewTransform {
+        let rotation = self.rotation.to_radians();
+        ViewTransform {
+            rotation,
+            scale: self.scale,
+            offset: -Vec2::from_angle(rotation).rotate(self.focus) * self.scale,
+        }
+    }
+
+    /// ワールド座標のworldがカメラの座標cameraに写るようにfocusを動かす
+    fn keep(&mut self, camera: Vec2, world: Vec2) {
+        let inverse = Vec2::from_angle(-self.rotation.to_radians());
+        self.focus = world - inverse.rotate(camera) / self.scale;
+    }
+
+    /// カメラの座標でdeltaだけ画面を動かす
+    pub fn pan(&mut self, delta: Vec2) {
+        let inverse = Vec2::from_angle(-self.rotation.to_radians());
+        self.focus += inverse.rotate(delta) / self.scale;
+    }
+
+    /// カメラの座標centerに写っている点を動かさずにfactor倍に拡大する
+    pub fn zoom(&mut self, factor: f32, center: Vec2) {
+        let world = self.view().inverse_apply(center);
+        self.scale = (self.scale * factor).clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+        self.keep(center, world);
+    }
+
+    /// カメラの座標centerに写っている点を中心に、30°のsteps倍だけ回す
+    pub fn rotate(&mut self, steps: i32, center: Vec2) {
+        let world = self.view().inverse_apply(center);
+        self.rotation += Angle::new(steps);
+        self.keep(center, world);
+    }
+
+    /// ワールド座標のworldをカメラの座標centerに写す。widthを与えると、その長さをcamera_widthに合わせる
+    pub fn look_at(&mut self, world: Vec2, width: Option<f32>, center: Vec2, camera_width: f32) {
+        if let Some(width) = width {
+            self.scale = (camera_width / width).clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+        }
+        self.keep(center, world);
+    }
+}
+
+/// 移動先
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoToTarget {
+    /// ワールド座標（`x,y`）
+    Coordinate(Vec2),
+    /// タイルまたはClusterのaddress（`abch/1`のような、a〜hの並びとMysticの半分）
+    ///
+    /// slotがn個のaddressは、最初の根からexpandしていったときのlevel nの根から辿る
+    Address(TileAddress),
+}
+
+impl FromStr for GoToTarget {
+    type Err = GoToError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((x, y)) = s.split_once(',') {
+            let parse = |v: &str| v.trim().parse::<f32>().ok().filter(|v| v.is_finite());
+            return match (parse(x), parse(y)) {
+                (Some(x), Some(y)) => Ok(GoToTarget::Coordinate(Vec2::new(x, y))),
+                _ => Err(GoToError::Syntax(s.to_string())),
+            };
+        }
+        let (slots, mystic_part) = match s.split_once('/') {
+            Some((slots, part)) => match part {
+                "0" => (slots, Some(0)),
+                "1" => (slots, Some(1)),
+                _ => return Err(GoToError::Syntax(s.to_string())),
+            },
+            None => (s, None),
+        };
+        let slots = slots
+            .bytes()
+            .map(|c| match c {
+                b'a'..=b'h' => Some(c - b'a'),
+                _ => None,
+            })
+            .collect::<Option<Vec<u8>>>()
+            .filter(|slots| !slots.is_empty())
+            .ok_or_else(|| GoToError::Syntax(s.to_string()))?;
+        Ok(GoToTarget::Address(TileAddress::new(slots, mystic_part)))
+    }
+}
+
+/// 移動先に行けない理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoToError {
+    /// 座標にもaddressにも読めない
+    Syntax(String),
+    /// MysticClusterのe、Mysticでないタイルの半分のように、存在しない位置を指している
+    NoSuchTile(String),
+    /// 根をこれ以上広げられない
+    TooFar,
+}
+
+impl std::fmt::Display for GoToError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoToError::Syntax(s) => write!(f, "cannot read {:?} as a coordinate or an address", s),
+            GoToError::NoSuchTile(s) => write!(f, "there is no tile at {}", s),
+            GoToError::TooFar => write!(f, "the target is too far from the origin"),
+        }
+    }
+}
+
+impl std::error::Error for GoToError {}
+
+/// targetが根のClusterに収まるまでexpandし、targetの範囲を返す
+///
+/// 座標なら、その点の周りのmarginの範囲をロードしたタイルが覆うまで広げ、大きさのないAabbを返す。
+/// 根は凸でないので、bboxに収まっていても覆われているとは限らない
+pub fn go_to(
+    controller: &mut TilesController,
+    target: &GoToTarget,
+    margin: f32,
+) -> Result<Aabb, GoToError> {
+    match target {
+        GoToTarget::Coordinate(point) => {
+            let region = Aabb::from_min_max(point - margin, point + margin);
+            let margins = UpdateMargins {
+                load: 0.0,
+                unload: 0.0,
+            };
+            loop {
+                controller.update(&region, margins);
+                if covers(controller, &region) {
+                    break;
+                }
+                expand(controller)?;
+            }
+            Ok(Aabb::from_min_max(*point, *point))
+        }
+        GoToTarget::Address(address) => {
+            let level = address.slots().len();
+            while controller.level() < level {
+                expand(controller)?;
+            }
+            let slots = controller.expansion_path(level);
+            let mut skeleton = controller.skeleton();
+            let mut is_mystic = false;
+            for &slot in slots.iter().chain(address.slots()) {
+                // MysticClusterはeを持たない
+                if is_mystic && slot == 4 {
+                    return Err(GoToError::NoSuchTile(address.to_string()));
+                }
+                skeleton = skeleton.split_into_skeletons()[slot as usize];
+                is_mystic = slot == 7;
+            }
+            if address.mystic_part().is_some() && !(is_mystic && skeleton.level() == 0) {
+                return Err(GoToError::NoSuchTile(address.to_string()));
+            }
+            Ok(skeleton.bbox(is_mystic))
+        }
+    }
+}
+
+fn expand(controller: &mut TilesController) -> Result<(), GoToError> {
+    let level = controller.level();
+    controller.expand();
+    if controller.level() == level {
+        Err(GoToError::TooFar)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Region;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "12.5, -3".parse(),
+            Ok(GoToTarget::Coordinate(Vec2::new(12.5, -3.0)))
+        );
+        assert_eq!(
+            "abch/1".parse(),
+            Ok(GoToTarget::Address(TileAddress::new(
+                vec![0, 1, 2, 7],
+                Some(1)
+            )))
+        );
+        assert!("abz".parse::<GoToTarget>().is_err());
+        assert!("ab/2".parse::<GoToTarget>().is_err());
+        assert!("1,x".parse::<GoToTarget>().is_err());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut navigation = Navigation::default();
+        let center = Vec2::new(3.0, -2.0);
+        navigation.look_at(Vec2::new(100.0, 50.0), Some(10.0), center, 40.0);
+        let view = navigation.view();
+        assert!((view.apply(Vec2::new(100.0, 50.0)) - center).length() < 1e-3);
+        assert_eq!(navigation.scale, 4.0);
+
+        // 回転と拡大は画面の中心を動かさない
+        navigation.rotate(1, center);
+        navigation.zoom(2.0, center);
+        let view = navigation.view();
+        assert!((view.apply(Vec2::new(100.0, 50.0)) - center).length() < 1e-3);
+        assert_eq!(navigation.rotation, Angle::new(1));
+    }
+
+    #[test]
+    fn test_go_to_coordinate() {
+        let mut controller = TilesController::new();
+        let level = controller.level();
+        let point = Vec2::new(3000.0, -2000.0);
+        let target = GoToTarget::Coordinate(point);
+        assert!(!controller.cluster_bbox().contains(point));
+        go_to(&mut controller, &target, 50.0).unwrap();
+        assert!(controller.level() > level);
+
+        // 移動先にタイルがある
+        let region = Aabb::from_min_max(point - 50.0, point + 50.0);
+        controller.update(
+            &region,
+            UpdateMargins {
+                load: 0.0,
+                unload: 0.0,
+            },
+        );
+        assert!(controller.pick(point).is_some());
+
+        // bboxの隅はbboxに収まっていても根のタイルに覆われていない
+        let mut controller = TilesController::new();
+        let level = controller.level();
+        let corner = controller.cluster_bbox().min + 10.0;
+        let region = Aabb::from_min_max(corner - 5.0, corner + 5.0);
+        assert!(controller.cluster_bbox().contains_aabb(&region));
+        go_to(&mut controller, &GoToTarget::Coordinate(corner), 5.0).unwrap();
+        assert!(controller.level() > level);
+        assert!(covers(&controller, &region));
+    }
+
+    #[test]
+    fn test_go_to_address() {
+        let mut controller = TilesController::new();
+        let bbox = controller.cluster_bbox();
+        let center = (bbox.min + bbox.max) * 0.5;
+        let region = Aabb::from_min_max(center - 5.0, center + 5.0);
+        controller.update(
+            &region,
+            UpdateMargins {
+                load: 0.0,
+                unload: 0.0,
+            },
+        );
+        let picked = (0..10)
+            .find_map(|i| controller.pick(center + Vec2::new(i as f32 * 0.3, 0.0)))
+            .unwrap();
+
+        // 選んだタイルのaddressへ行くと、そのタイルの範囲になる
+        let address = picked.address.clone();
+        let found = go_to(&mut controller, &GoToTarget::Address(address), 0.0).unwrap();
+        let vertices = picked.vertices.iter().map(|v| v.to_vec2());
+        for vertex in vertices {
+            assert!(found.expanded(1e-3).contains(vertex));
+        }
+
+        // 根より長いaddressはexpandしてから辿る
+        let mut slots = vec![0; controller.level() + 2];
+        slots[0] = 3;
+        let target = GoToTarget::Address(TileAddress::new(slots, None));
+        go_to(&mut controller, &target, 0.0).unwrap();
+        assert!(controller.level() >= picked.address.slots().len() + 2);
+
+        // MysticClusterはeを持たない
+        let target = "he".parse().unwrap();
+        assert!(matches!(
+            go_to(&mut controller, &target, 0.0),
+            Err(GoToError::NoSuchTile(_))
+        ));
+    }
+}
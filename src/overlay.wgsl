@@ -0,0 +1,32 @@
+// 画面の上に重ねる平行四辺形。座標はクリップ座標で与え、カメラの影響を受けない
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) i_origin: vec2<f32>,
+    @location(3) i_axis_x: vec2<f32>,
+    @location(4) i_axis_y: vec2<f32>,
+    // 線形RGB
+    @location(5) i_color: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(v: Vertex) -> VertexOutput {
+    // 単位正方形を平行四辺形に写す
+    let pos = v.i_origin + v.position.x * v.i_axis_x + v.position.y * v.i_axis_y;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(pos, 0.0, 1.0);
+    out.color = vec4<f32>(v.i_color, 1.0);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
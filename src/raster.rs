@@ -0,0 +1,9 @@
+mod color;
+mod rasterizer;
+mod tiled_export;
+
+pub(crate) use color::hsv_to_rgb;
+pub use color::{group_color, tile_color};
+pub(crate) use rasterizer::{covers, draw_tiles, load_covering};
+pub use rasterizer::{rasterize, rasterize_region, RasterStyle, RasterView};
+pub use tiled_export::{PyramidLayout, TiledExport};
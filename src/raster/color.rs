@@ -0,0 +1,62 @@
+use std::f32::consts::FRAC_PI_3;
+
+use crate::tiles::{Anchor, Spectre};
+
+/// タイルの色（sRGB）
+///
+/// instancing.wgslと同じく、向きで色相、アンカー1の位置で彩度と明度を変える。
+/// 鏡映されたタイルは暖色系にする。画面はsRGBのサーフェスに描かれるので、同じ見た目になるよう変換する
+pub fn tile_color(spectre: &Spectre) -> [u8; 3] {
+    let angle = spectre.rotation().to_radians();
+    let base_hue = if spectre.is_reflected() { 0.52 } else { 3.84 };
+    color_with_hue(spectre, base_hue + angle.sin() * 0.333)
+}
+
+/// グループ（0〜7）で色分けしたタイルの色。instancing.wgslで色分けするときと同じ色になる
+pub fn group_color(spectre: &Spectre, group: u8) -> [u8; 3] {
+    let angle = spectre.rotation().to_radians();
+    color_with_hue(spectre, group as f32 * 0.785 + angle.sin() * 0.1)
+}
+
+fn color_with_hue(spectre: &Spectre, hue: f32) -> [u8; 3] {
+    let position = spectre.coordinate(Anchor::Anchor1).to_vec2();
+    let saturation = (1.666 * position.x).sin() * 0.166 + 0.666;
+    let value = position.y.sin() * 0.166 + 0.833;
+    hsv_to_rgb(hue, saturation, value).map(|c| (linear_to_srgb(c) * 255.0).round() as u8)
+}
+
+/// hueはラジアン（mikageのhsv2rgbと同じ）
+pub(crate) fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [f32; 3] {
+    [5.0, 3.0, 1.0].map(|n: f32| {
+        let k = (n + hue / FRAC_PI_3) % 6.0;
+        value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+    })
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hsv_to_rgb() {
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close(hsv_to_rgb(0.0, 1.0, 1.0), [1.0, 0.0, 0.0]));
+        assert!(close(
+            hsv_to_rgb(2.0 * FRAC_PI_3, 1.0, 1.0),
+            [0.0, 1.0, 0.0]
+        ));
+        assert!(close(
+            hsv_to_rgb(4.0 * FRAC_PI_3, 0.5, 0.5),
+            [0.25, 0.25, 0.5]
+        ));
+    }
+}
@@ -0,0 +1,328 @@
+use glam::Vec2;
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+use super::tile_color;
+use crate::{
+    controller::{TilesController, UpdateMargins},
+    tiles::Spectre,
+    utils::Aabb,
+};
+
+/// 画像に描くワールド座標の範囲と解像度
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterView {
+    /// 画像の左上の角のワールド座標
+    pub top_left: Vec2,
+    /// 1単位あたりのピクセル数
+    pub pixels_per_unit: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RasterView {
+    /// regionが収まる最大の倍率で、regionの中心を画像の中心に合わせる
+    pub fn fit(region: &Aabb, width: u32, height: u32) -> Self {
+        let size = region.max - region.min;
+        let pixels_per_unit = (width as f32 / size.x).min(height as f32 / size.y);
+        let center = (region.min + region.max) * 0.5;
+        let half = Vec2::new(width as f32, -(height as f32)) * 0.5 / pixels_per_unit;
+        Self {
+            top_left: center - half,
+            pixels_per_unit,
+            width,
+            height,
+        }
+    }
+
+    /// 画像に写るワールド座標の範囲
+    pub fn region(&self) -> Aabb {
+        let size = Vec2::new(self.width as f32, self.height as f32) / self.pixels_per_unit;
+        Aabb::new(
+            self.top_left.x,
+            self.top_left.y - size.y,
+            self.top_left.x + size.x,
+            self.top_left.y,
+        )
+    }
+
+    /// 画像の(x, y)を左上とするwidth×heightの部分
+    pub fn window(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            top_left: self.top_left + Vec2::new(x as f32, -(y as f32)) / self.pixels_per_unit,
+            pixels_per_unit: self.pixels_per_unit,
+            width,
+            height,
+        }
+    }
+
+    /// ワールド座標からピクセル座標への変換（y軸は下向き）
+    fn transform(&self) -> Transform {
+        let s = self.pixels_per_unit;
+        Transform::from_row(s, 0.0, 0.0, -s, -self.top_left.x * s, self.top_left.y * s)
+    }
+}
+
+/// 背景と輪郭線の描き方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterStyle {
+    /// 背景色（sRGB、アルファ付き）
+    pub background: [u8; 4],
+    /// 輪郭線の色と、辺の長さを1とした太さ。Noneなら描かない
+    pub stroke: Option<([u8; 4], f32)>,
+}
+
+impl Default for RasterStyle {
+    fn default() -> Self {
+        Self {
+            background: [255, 255, 255, 255],
+            stroke: Some(([40, 40, 40, 255], 0.04)),
+        }
+    }
+}
+
+/// タイルを塗ってPixmapに描く
+///
+/// 色はtile_colorで決める。viewの範囲と重ならないタイルは描かない
+pub fn rasterize<'a>(
+    tiles: impl IntoIterator<Item = &'a Spectre>,
+    view: &RasterView,
+    style: &RasterStyle,
+) -> Pixmap {
+    let mut pixmap = Pixmap::new(view.width, view.height).expect("image size must be non-zero");
+    let [r, g, b, a] = style.background;
+    pixmap.fill(Color::from_rgba8(r, g, b, a));
+    draw_tiles(
+        &mut pixmap,
+        tiles.into_iter().map(|tile| (tile, tile_color(tile))),
+        view.transform(),
+        &view.region(),
+        style,
+    );
+    pixmap
+}
+
+/// 色を指定したタイルをtransformで写してpixmapに描く。regionと重ならないタイルは描かない
+pub(crate) fn draw_tiles<'a>(
+    pixmap: &mut Pixmap,
+    tiles: impl IntoIterator<Item = (&'a Spectre, [u8; 3])>,
+    transform: Transform,
+    region: &Aabb,
+    style: &RasterStyle,
+) {
+    // 輪郭線の分だけ広げた範囲と重なるタイルを描く
+    let margin = style.stroke.map_or(0.0, |(_, width)| width);
+    let visible = Aabb::from_min_max(region.min - margin, region.max + margin);
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+    let stroke = style.stroke.map(|(color, width)| {
+        (
+            color,
+            Stroke {
+                width,
+                ..Default::default()
+            },
+        )
+    });
+    for (tile, [r, g, b]) in tiles {
+        if !tile.bbox().has_intersection(&visible) {
+            continue;
+        }
+        let mut builder = PathBuilder::new();
+        for (i, vertex) in tile.vertices().into_iter().enumerate() {
+            let p = vertex.to_vec2();
+            if i == 0 {
+                builder.move_to(p.x, p.y);
+            } else {
+                builder.line_to(p.x, p.y);
+            }
+        }
+        builder.close();
+        let Some(path) = builder.finish() else {
+            continue;
+        };
+
+        paint.set_color_rgba8(r, g, b, 255);
+        pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+        if let Some(([r, g, b, a], stroke)) = &stroke {
+            paint.set_color_rgba8(*r, *g, *b, *a);
+            pixmap.stroke_path(&path, &paint, stroke, transform, None);
+        }
+    }
+}
+
+/// regionのタイリングをwidth×heightの画像に描く
+///
+/// regionを覆うまでClusterを広げ、画像に写る範囲のタイルだけをロードする
+pub fn rasterize_region(region: &Aabb, width: u32, height: u32, style: &RasterStyle) -> Pixmap {
+    let view = RasterView::fit(region, width, height);
+    let visible = view.region();
+    let mut controller = TilesController::new();
+    load_covering(
+        &mut controller,
+        &visible,
+        UpdateMargins {
+            load: 0.0,
+            unload: 0.0,
+        },
+    );
+    rasterize(controller.spectres_in(&visible), &view, style)
+}
+
+/// regionのタイルをロードする。ロードしたタイルでregionを覆えなければClusterを広げる
+///
+/// 広げても元のClusterのタイルは動かないので、続けて別の範囲を描いても繋がる
+pub(crate) fn load_covering(
+    controller: &mut TilesController,
+    region: &Aabb,
+    margins: UpdateMargins,
+) {
+    loop {
+        controller.update(region, margins);
+        if covers(controller, region) {
+            return;
+        }
+        let level = controller.level();
+        controller.expand();
+        if controller.level() == level {
+            return;
+        }
+    }
+}
+
+/// ロードしたタイルがregionを覆っているかどうか
+///
+/// Clusterは穴のない領域なので、regionの周上の点がすべてタイルに含まれていれば内側も覆われている
+pub(crate) fn covers(controller: &TilesController, region: &Aabb) -> bool {
+    const STEP: f32 = 0.25;
+    let size = region.max - region.min;
+    let corners = [
+        region.min,
+        Vec2::new(region.max.x, region.min.y),
+        region.max,
+        Vec2::new(region.min.x, region.max.y),
+    ];
+    (0..4).all(|i| {
+        let from = corners[i];
+        let to = corners[(i + 1) % 4];
+        let length = if i % 2 == 0 { size.x } else { size.y };
+        let steps = (length / STEP).ceil().max(1.0) as usize;
+        (0..steps).all(|j| {
+            let p = from.lerp(to, j as f32 / steps as f32);
+            // 大きさのないAabbはどのbboxとも交差しないので、少しだけ広げる
+            let probe = Aabb::from_min_max(p - 1e-3, p + 1e-3);
+            controller
+                .spectres_in(&probe)
+                .any(|spectre| spectre.contains(p))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec},
+    };
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/raster/golden/cluster.png");
+
+    fn render_cluster() -> Pixmap {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let view = RasterView::fit(&cluster.bbox(), 160, 120);
+        rasterize(
+            cluster.spectres_in(cluster.bbox()),
+            &view,
+            &RasterStyle::default(),
+        )
+    }
+
+    #[test]
+    fn test_rasterize_region() {
+        // 原点から離れた範囲もタイルで埋まる
+        let region = Aabb::new(300.0, -420.0, 340.0, -400.0);
+        let style = RasterStyle {
+            background: [0, 0, 0, 0],
+            stroke: None,
+        };
+        let pixmap = rasterize_region(&region, 200, 100, &style);
+        // タイルの継ぎ目のアンチエイリアスで僅かに透けるほかは塗られている
+        assert!(pixmap.pixels().iter().all(|p| p.alpha() > 128));
+    }
+
+    #[test]
+    fn test_load_covering() {
+        // 根のClusterで覆える範囲なら広げない
+        let mut controller = TilesController::new();
+        let level = controller.level();
+        let bbox = controller.cluster_bbox();
+        let center = (bbox.min + bbox.max) * 0.5;
+        let region = Aabb::from_min_max(center - 5.0, center + 5.0);
+        load_covering(
+            &mut controller,
+            &region,
+            UpdateMargins::relative_to(&region, 0.0, 1.0),
+        );
+        assert!(covers(&controller, &region));
+        assert_eq!(controller.level(), level);
+    }
+
+    #[test]
+    fn test_fit() {
+        let region = Aabb::new(-2.0, -1.0, 2.0, 1.0);
+        let view = RasterView::fit(&region, 200, 200);
+        assert_eq!(view.pixels_per_unit, 50.0);
+        // 縦は余白が付く
+        assert_eq!(view.region(), Aabb::new(-2.0, -2.0, 2.0, 2.0));
+    }
+
+    /// UPDATE_GOLDEN=1で実行すると期待する画像を書き直す
+    #[test]
+    fn test_golden_image() {
+        let pixmap = render_cluster();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            pixmap.save_png(GOLDEN).unwrap();
+        }
+        let golden = Pixmap::load_png(GOLDEN).expect("golden image is missing");
+        assert_eq!(
+            (golden.width(), golden.height()),
+            (pixmap.width(), pixmap.height())
+        );
+        // アンチエイリアスの実装差で僅かにずれるのは許す
+        let differs = golden
+            .data()
+            .chunks(4)
+            .zip(pixmap.data().chunks(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > 2))
+            .count();
+        assert!(
+            differs * 1000 < golden.width() as usize * golden.height() as usize,
+            "{} pixels differ from the golden image",
+            differs
+        );
+    }
+
+    #[test]
+    fn test_background_outside_tiles() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let view = RasterView::fit(&Aabb::new(-10.0, -10.0, 10.0, 10.0), 40, 40);
+        let style = RasterStyle {
+            background: [0, 0, 0, 0],
+            stroke: None,
+        };
+        let pixmap = rasterize([&tile], &view, &style);
+        // 左上の角は空いていて、タイルの内側は塗られている
+        assert_eq!(pixmap.pixel(0, 0).unwrap().alpha(), 0);
+        let vertices = tile.vertices();
+        let inside = (vertices[0].to_vec2() + vertices[7].to_vec2()) * 0.5;
+        let px = (inside - view.top_left) * Vec2::new(1.0, -1.0) * view.pixels_per_unit;
+        let color = pixmap.pixel(px.x as u32, px.y as u32).unwrap();
+        let [r, g, b] = tile_color(&tile);
+        assert_eq!(
+            (color.red(), color.green(), color.blue(), color.alpha()),
+            (r, g, b, 255)
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use tiny_skia::{Pixmap, PixmapPaint, Transform};
+
+use super::{
+    rasterizer::{load_covering, rasterize},
+    RasterStyle, RasterView,
+};
+use crate::{
+    controller::{TilesController, UpdateMargins},
+    utils::Aabb,
+};
+
+/// 一枚に収まらない大きさの画像を、画像タイルに分けて書き出す
+///
+/// 画像タイルごとに必要なClusterだけをロードし、メモリの上限を超えたものはアンロードする。
+/// ピラミッドは下のlevelの画像タイルを縮小して作るので、保持する画像はlevelあたり数枚で済む。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TiledExport {
+    /// 書き出すワールド座標の範囲
+    pub region: Aabb,
+    /// 1単位あたりのピクセル数
+    pub pixels_per_unit: f32,
+    /// 画像タイルの一辺のピクセル数
+    pub tile_size: u32,
+    pub style: RasterStyle,
+    /// 読み込んだタイルに使ってよいメモリの上限（バイト）
+    pub memory_budget: usize,
+}
+
+/// ピラミッドのファイルの並べ方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PyramidLayout {
+    /// {name}.dziと{name}_files/{level}/{col}_{row}.png。levelは1×1ピクセルが0
+    DeepZoom,
+    /// {z}/{x}/{y}.png。zは全体が一枚の画像タイルに収まるlevelが0で、画像タイルは常に正方形
+    Xyz,
+}
+
+impl TiledExport {
+    pub fn new(region: Aabb, pixels_per_unit: f32) -> Self {
+        Self {
+            region,
+            pixels_per_unit,
+            tile_size: 256,
+            style: RasterStyle::default(),
+            memory_budget: 64 * 1024 * 1024,
+        }
+    }
+
+    /// 画像全体の大きさ（ピクセル）
+    pub fn size(&self) -> (u32, u32) {
+        let size = (self.region.max - self.region.min) * self.pixels_per_unit;
+        (size.x.ceil().max(1.0) as u32, size.y.ceil().max(1.0) as u32)
+    }
+
+    /// ピラミッドをdirに書き出す。DeepZoomではnameがファイル名になる
+    pub fn write_pyramid(&self, dir: &Path, name: &str, layout: PyramidLayout) -> io::Result<()> {
+        let (width, height) = self.size();
+        let levels = self.tile_levels();
+        let mut writer = PyramidWriter {
+            export: self,
+            controller: self.controller(),
+            layout,
+            root: match layout {
+                PyramidLayout::DeepZoom => dir.join(format!("{}_files", name)),
+                PyramidLayout::Xyz => dir.to_path_buf(),
+            },
+            levels,
+            // 画像全体が1×1ピクセルになるlevelからの数
+            top_level: u32::BITS - (width.max(height) - 1).leading_zeros(),
+        };
+        let root = writer.build(0, 0, 0)?;
+
+        if layout == PyramidLayout::DeepZoom {
+            // 一枚に収まってからは、1×1ピクセルになるまで縮小する
+            let mut image = root;
+            for level in (0..writer.top_level - levels).rev() {
+                image = downsample(&image);
+                writer.save(level, 0, 0, &image)?;
+            }
+            fs::write(
+                dir.join(format!("{}.dzi", name)),
+                format!(
+                    concat!(
+                        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                        "\n",
+                        r#"<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" Format="png" Overlap="0" TileSize="{}">"#,
+                        "\n",
+                        r#"  <Size Width="{}" Height="{}"/>"#,
+                        "\n</Image>\n"
+                    ),
+                    self.tile_size, width, height
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 一枚のPNGに繋げて書き出す
+    ///
+    /// 画像タイル一行分ずつ描いて書き出すので、保持するのは幅×tile_sizeの画像だけで済む
+    pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+        let (width, height) = self.size();
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut header = encoder.write_header().map_err(io::Error::other)?;
+        let mut stream = header.stream_writer().map_err(io::Error::other)?;
+
+        let mut controller = self.controller();
+        let view = self.view();
+        let mut strip = vec![0u8; width as usize * self.tile_size as usize * 4];
+        for y in (0..height).step_by(self.tile_size as usize) {
+            let rows = self.tile_size.min(height - y);
+            for x in (0..width).step_by(self.tile_size as usize) {
+                let columns = self.tile_size.min(width - x);
+                let image = self.render(&mut controller, &view.window(x, y, columns, rows));
+                for (row, pixels) in image.pixels().chunks(columns as usize).enumerate() {
+                    let start = (row * width as usize + x as usize) * 4;
+                    for (i, pixel) in pixels.iter().enumerate() {
+                        let color = pixel.demultiply();
+                        strip[start + i * 4..start + i * 4 + 4].copy_from_slice(&[
+                            color.red(),
+                            color.green(),
+                            color.blue(),
+                            color.alpha(),
+                        ]);
+                    }
+                }
+            }
+            stream.write_all(&strip[..(width * rows * 4) as usize])?;
+        }
+        stream.finish().map_err(io::Error::other)
+    }
+
+    /// 画像全体のビュー。画像の左上はregionの左上に合わせる
+    fn view(&self) -> RasterView {
+        let (width, height) = self.size();
+        RasterView {
+            top_left: glam::Vec2::new(self.region.min.x, self.region.max.y),
+            pixels_per_unit: self.pixels_per_unit,
+            width,
+            height,
+        }
+    }
+
+    /// 一番細かい画像タイルのlevel（全体が一枚に収まるlevelを0とする）
+    fn tile_levels(&self) -> u32 {
+        let (width, height) = self.size();
+        let tiles = width.max(height).div_ceil(self.tile_size);
+        u32::BITS - (tiles - 1).leading_zeros()
+    }
+
+    fn controller(&self) -> TilesController {
+        let mut controller = TilesController::new();
+        controller.set_memory_budget(Some(self.memory_budget));
+        controller
+    }
+
+    /// 画像タイルを描く。隣の画像タイルで使うClusterは残るよう、画像タイル一枚分離れてからアンロードする
+    fn render(&self, controller: &mut TilesController, window: &RasterView) -> Pixmap {
+        let region = window.region();
+        load_covering(
+            controller,
+            &region,
+            UpdateMargins::relative_to(&region, 0.0, 1.0),
+        );
+        rasterize(controller.spectres_in(&region), window, &self.style)
+    }
+}
+
+/// ピラミッドを下のlevelから組み立てて書き出す
+struct PyramidWriter<'a> {
+    export: &'a TiledExport,
+    controller: TilesController,
+    layout: PyramidLayout,
+    root: PathBuf,
+    /// 一番細かい画像タイルのlevel
+    levels: u32,
+    /// DeepZoomでの一番細かいlevel
+    top_level: u32,
+}
+
+impl PyramidWriter<'_> {
+    /// levelの(col, row)の画像タイルを、その下の画像タイルを書き出してから作る
+    fn build(&mut self, level: u32, col: u32, row: u32) -> io::Result<Pixmap> {
+        let tile_size = self.export.tile_size;
+        let (width, height) = self.level_size(level);
+        let (x, y) = (col * tile_size, row * tile_size);
+        let (columns, rows) = match self.layout {
+            PyramidLayout::DeepZoom => (tile_size.min(width - x), tile_size.min(height - y)),
+            PyramidLayout::Xyz => (tile_size, tile_size),
+        };
+
+        let image = if level == self.levels {
+            let view = self.export.view();
+            let window = view.window(x, y, columns, rows);
+            self.export.render(&mut self.controller, &window)
+        } else {
+            let (child_width, child_height) = self.level_size(level + 1);
+            // 縮小するとちょうどcolumns×rowsになる大きさ
+            let mut canvas = match self.layout {
+                PyramidLayout::DeepZoom => Pixmap::new(
+                    (child_width - x * 2).min(tile_size * 2),
+                    (child_height - y * 2).min(tile_size * 2),
+                ),
+                PyramidLayout::Xyz => Pixmap::new(tile_size * 2, tile_size * 2),
+            }
+            .unwrap();
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (child_col, child_row) = (col * 2 + dx, row * 2 + dy);
+                let outside = self.layout == PyramidLayout::DeepZoom
+                    && (child_col * tile_size >= child_width
+                        || child_row * tile_size >= child_height);
+                if outside {
+                    continue;
+                }
+                let child = self.build(level + 1, child_col, child_row)?;
+                canvas.draw_pixmap(
+                    (dx * tile_size) as i32,
+                    (dy * tile_size) as i32,
+                    child.as_ref(),
+                    &PixmapPaint::default(),
+                    Transform::identity(),
+                    None,
+                );
+            }
+            downsample(&canvas)
+        };
+        let saved_level = match self.layout {
+            PyramidLayout::DeepZoom => self.top_level - (self.levels - level),
+            PyramidLayout::Xyz => level,
+        };
+        self.save(saved_level, col, row, &image)?;
+        Ok(image)
+    }
+
+    /// levelでの画像全体の大きさ
+    fn level_size(&self, level: u32) -> (u32, u32) {
+        let (width, height) = self.export.size();
+        let scale = 1 << (self.levels - level);
+        (width.div_ceil(scale), height.div_ceil(scale))
+    }
+
+    fn save(&self, level: u32, col: u32, row: u32, image: &Pixmap) -> io::Result<()> {
+        let path = match self.layout {
+            PyramidLayout::DeepZoom => self
+                .root
+                .join(level.to_string())
+                .join(format!("{}_{}.png", col, row)),
+            PyramidLayout::Xyz => self
+                .root
+                .join(level.to_string())
+                .join(col.to_string())
+                .join(format!("{}.png", row)),
+        };
+        fs::create_dir_all(path.parent().unwrap())?;
+        image.save_png(path).map_err(io::Error::other)
+    }
+}
+
+/// 縦横を半分（端数は切り上げ）に縮小する
+fn downsample(image: &Pixmap) -> Pixmap {
+    let width = image.width().div_ceil(2);
+    let height = image.height().div_ceil(2);
+    let mut result = Pixmap::new(width, height).unwrap();
+    let source = image.data();
+    let (source_width, source_height) = (image.width() as usize, image.height() as usize);
+    let target = result.data_mut();
+    for y in 0..height as usize {
+        // 端で足りない画素は端の画素で補う
+        let ys = [y * 2, (y * 2 + 1).min(source_height - 1)];
+        for x in 0..width as usize {
+            let xs = [x * 2, (x * 2 + 1).min(source_width - 1)];
+            for channel in 0..4 {
+                let sum: u32 = ys
+                    .iter()
+                    .flat_map(|&sy| xs.iter().map(move |&sx| (sy * source_width + sx) * 4))
+                    .map(|i| source[i + channel] as u32)
+                    .sum();
+                target[(y * width as usize + x) * 4 + channel] = ((sum + 2) / 4) as u8;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export() -> TiledExport {
+        TiledExport {
+            tile_size: 64,
+            ..TiledExport::new(Aabb::new(-10.0, 5.0, 20.0, 25.0), 10.0)
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spectre-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_size() {
+        let export = export();
+        assert_eq!(export.size(), (300, 200));
+        // 64×8 = 512 >= 300
+        assert_eq!(export.tile_levels(), 3);
+    }
+
+    #[test]
+    fn test_deep_zoom() {
+        let dir = temp_dir("dzi");
+        export()
+            .write_pyramid(&dir, "patch", PyramidLayout::DeepZoom)
+            .unwrap();
+        let files = dir.join("patch_files");
+        // 300×200は9段目、64ピクセルの画像タイルで5×4枚
+        assert_eq!(level_count(&files, 9), 20);
+        let edge = Pixmap::load_png(files.join("9/4_3.png")).unwrap();
+        assert_eq!((edge.width(), edge.height()), (300 - 256, 200 - 192));
+        for level in 0..=6 {
+            assert_eq!(level_count(&files, level), 1);
+        }
+        let single = Pixmap::load_png(files.join("0/0_0.png")).unwrap();
+        assert_eq!((single.width(), single.height()), (1, 1));
+        assert!(fs::read_to_string(dir.join("patch.dzi"))
+            .unwrap()
+            .contains(r#"Width="300""#));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn level_count(files: &Path, level: u32) -> usize {
+        fs::read_dir(files.join(level.to_string())).unwrap().count()
+    }
+
+    #[test]
+    fn test_xyz() {
+        let dir = temp_dir("xyz");
+        export()
+            .write_pyramid(&dir, "patch", PyramidLayout::Xyz)
+            .unwrap();
+        for z in 0..=3 {
+            let count: usize = fs::read_dir(dir.join(z.to_string()))
+                .unwrap()
+                .map(|column| fs::read_dir(column.unwrap().path()).unwrap().count())
+                .sum();
+            assert_eq!(count, 1 << (2 * z));
+        }
+        let root = Pixmap::load_png(dir.join("0/0/0.png")).unwrap();
+        assert_eq!((root.width(), root.height()), (64, 64));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_stitched_png() {
+        let export = export();
+        let mut png = vec![];
+        export.write_png(&mut png).unwrap();
+        let stitched = Pixmap::decode_png(&png).unwrap();
+
+        // 一枚で描いたものと継ぎ目なく一致する
+        let mut controller = export.controller();
+        let whole = export.render(&mut controller, &export.view());
+        assert_eq!(
+            (stitched.width(), stitched.height()),
+            (whole.width(), whole.height())
+        );
+        let differs = stitched
+            .pixels()
+            .iter()
+            .zip(whole.pixels())
+            .filter(|(a, b)| {
+                let (a, b) = (a.demultiply(), b.demultiply());
+                [
+                    a.red().abs_diff(b.red()),
+                    a.green().abs_diff(b.green()),
+                    a.blue().abs_diff(b.blue()),
+                ]
+                .iter()
+                .any(|&d| d > 8)
+            })
+            .count();
+        assert!(differs < 300 * 200 / 100, "{} pixels differ", differs);
+    }
+
+    #[test]
+    fn test_small_memory_budget() {
+        // Clusterを出し入れしながら描いても、画像全体がタイルで覆われる
+        let export = TiledExport {
+            style: RasterStyle {
+                background: [0, 0, 0, 0],
+                stroke: None,
+            },
+            memory_budget: 200 * 1024,
+            ..TiledExport::new(Aabb::new(-60.0, -60.0, 60.0, 60.0), 4.0)
+        };
+        let mut png = vec![];
+        export.write_png(&mut png).unwrap();
+        let image = Pixmap::decode_png(&png).unwrap();
+        let empty = image.pixels().iter().filter(|p| p.alpha() == 0).count();
+        assert_eq!(empty, 0);
+    }
+}
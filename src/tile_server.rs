@@ -0,0 +1,11 @@
+#[cfg(feature = "server")]
+mod http;
+mod service;
+mod tile_grid;
+mod vector_tile;
+
+#[cfg(feature = "server")]
+pub use http::serve;
+pub use service::{TileFormat, TileResponse, TileService};
+pub use tile_grid::TileGrid;
+pub use vector_tile::{encode_vector_tile, LAYER_NAME};
@@ -0,0 +1,41 @@
+use std::{io, sync::Arc};
+
+use tiny_http::{Header, Method, Response, Server};
+
+use super::{TileResponse, TileService};
+
+/// 127.0.0.1:portで地図タイルを配信する。戻らない
+pub fn serve(port: u16, cache_capacity: usize) -> io::Result<()> {
+    let address = format!("127.0.0.1:{}", port);
+    let server = Server::http(&address).map_err(io::Error::other)?;
+    let mut service = TileService::new(format!("http://{}", address), cache_capacity);
+    tracing::info!("Serving tiles at http://{}/tiles.json", address);
+
+    for request in server.incoming_requests() {
+        let response = if *request.method() == Method::Get {
+            service.handle(request.url())
+        } else {
+            TileResponse {
+                status: 405,
+                content_type: "text/plain",
+                body: Arc::new(b"method not allowed".to_vec()),
+            }
+        };
+        tracing::debug!("{} {}", response.status, request.url());
+        let result = request.respond(
+            Response::from_data(response.body.as_slice())
+                .with_status_code(response.status)
+                .with_header(header("Content-Type", response.content_type))
+                // ファイルや別のポートから開いた地図ビューアからも読めるようにする
+                .with_header(header("Access-Control-Allow-Origin", "*")),
+        );
+        if let Err(e) = result {
+            tracing::warn!("Failed to respond: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header must be ASCII")
+}
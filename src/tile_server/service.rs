@@ -0,0 +1,310 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{encode_vector_tile, vector_tile::LAYER_NAME, TileGrid};
+use crate::{
+    controller::{TilesController, UpdateMargins},
+    raster::{load_covering, rasterize, RasterStyle, RasterView},
+};
+
+/// 地図タイルの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileFormat {
+    Png,
+    /// Mapbox Vector Tile
+    Vector,
+}
+
+impl TileFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            TileFormat::Png => "png",
+            TileFormat::Vector => "pbf",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            TileFormat::Png => "image/png",
+            TileFormat::Vector => "application/vnd.mapbox-vector-tile",
+        }
+    }
+}
+
+/// リクエストへの応答
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Arc<Vec<u8>>,
+}
+
+impl TileResponse {
+    fn not_found() -> Self {
+        Self {
+            status: 404,
+            content_type: "text/plain",
+            body: Arc::new(b"not found".to_vec()),
+        }
+    }
+}
+
+/// 地図タイルをTilesControllerから描いて返す
+///
+/// 地図タイルごとに必要なClusterだけをロードし、描いた地図タイルは最近使ったものから
+/// cache_capacity枚まで残す。パスは次のとおり
+///
+/// - `/{z}/{x}/{y}.png`: ラスタータイル
+/// - `/{z}/{x}/{y}.pbf`: ベクタータイル
+/// - `/tiles.json`, `/vector.json`: それぞれのTileJSON
+pub struct TileService {
+    controller: TilesController,
+    grid: TileGrid,
+    /// TileJSONに書く地図タイルのURLの前置部分（例: `http://127.0.0.1:8080`）
+    base_url: String,
+    pub style: RasterStyle,
+    /// ラスタータイルの一辺のピクセル数
+    pub tile_size: u32,
+    cache: TileCache,
+}
+
+impl TileService {
+    /// zoom 0に対応するcluster level
+    const ROOT_LEVEL: usize = 10;
+
+    pub fn new(base_url: impl Into<String>, cache_capacity: usize) -> Self {
+        let mut controller = TilesController::new();
+        while controller.level() < Self::ROOT_LEVEL {
+            controller.expand();
+        }
+        Self {
+            grid: TileGrid::new(&controller),
+            controller,
+            base_url: base_url.into(),
+            style: RasterStyle::default(),
+            tile_size: 256,
+            cache: TileCache::new(cache_capacity),
+        }
+    }
+
+    pub fn grid(&self) -> &TileGrid {
+        &self.grid
+    }
+
+    /// パスに応答する。クエリ文字列は無視する
+    pub fn handle(&mut self, path: &str) -> TileResponse {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        match path {
+            "/tiles.json" => return self.tile_json(TileFormat::Png),
+            "/vector.json" => return self.tile_json(TileFormat::Vector),
+            _ => {}
+        }
+        let Some((format, zoom, x, y)) = parse_tile_path(path) else {
+            return TileResponse::not_found();
+        };
+        match self.tile(format, zoom, x, y) {
+            Some(body) => TileResponse {
+                status: 200,
+                content_type: format.content_type(),
+                body,
+            },
+            None => TileResponse::not_found(),
+        }
+    }
+
+    /// 地図タイルを描く。描かないzoomや範囲外ならNone
+    pub fn tile(&mut self, format: TileFormat, zoom: u32, x: u32, y: u32) -> Option<Arc<Vec<u8>>> {
+        let key = (format, zoom, x, y);
+        if let Some(body) = self.cache.get(&key) {
+            return Some(body);
+        }
+        let region = self.grid.tile_region(zoom, x, y)?;
+        // 隣の地図タイルで使うClusterは残るよう、地図タイル一枚分離れてからアンロードする
+        load_covering(
+            &mut self.controller,
+            &region,
+            UpdateMargins::relative_to(&region, 0.0, 1.0),
+        );
+        let tiles = self.controller.spectres_in(&region);
+        let body = match format {
+            TileFormat::Png => {
+                let view = RasterView {
+                    top_left: glam::Vec2::new(region.min.x, region.max.y),
+                    pixels_per_unit: self.tile_size as f32 / (region.max.x - region.min.x),
+                    width: self.tile_size,
+                    height: self.tile_size,
+                };
+                rasterize(tiles, &view, &self.style)
+                    .encode_png()
+                    .expect("failed to encode tile")
+            }
+            TileFormat::Vector => encode_vector_tile(tiles, &region),
+        };
+        let body = Arc::new(body);
+        self.cache.insert(key, body.clone());
+        Some(body)
+    }
+
+    fn tile_json(&self, format: TileFormat) -> TileResponse {
+        let mut json = serde_json::json!({
+            "tilejson": "3.0.0",
+            "name": "Infinite Spectres",
+            "scheme": "xyz",
+            "tiles": [format!("{}/{{z}}/{{x}}/{{y}}.{}", self.base_url, format.extension())],
+            "minzoom": self.grid.min_zoom(),
+            "maxzoom": self.grid.max_zoom(),
+        });
+        if format == TileFormat::Vector {
+            json["vector_layers"] = serde_json::json!([{
+                "id": LAYER_NAME,
+                "fields": { "rotation": "Number", "reflected": "Boolean" },
+            }]);
+        }
+        TileResponse {
+            status: 200,
+            content_type: "application/json",
+            body: Arc::new(json.to_string().into_bytes()),
+        }
+    }
+}
+
+/// `/{z}/{x}/{y}.{png,pbf}`を読む
+fn parse_tile_path(path: &str) -> Option<(TileFormat, u32, u32, u32)> {
+    let mut parts = path.strip_prefix('/')?.split('/');
+    let zoom = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let (y, extension) = parts.next()?.split_once('.')?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let format = match extension {
+        "png" => TileFormat::Png,
+        "pbf" | "mvt" => TileFormat::Vector,
+        _ => return None,
+    };
+    Some((format, zoom, x, y.parse().ok()?))
+}
+
+type TileKey = (TileFormat, u32, u32, u32);
+
+/// 最近使ったものから一定数を残すキャッシュ
+struct TileCache {
+    capacity: usize,
+    /// 使うたびに増える時刻
+    tick: u64,
+    entries: HashMap<TileKey, (u64, Arc<Vec<u8>>)>,
+}
+
+impl TileCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &TileKey) -> Option<Arc<Vec<u8>>> {
+        self.tick += 1;
+        let (used, body) = self.entries.get_mut(key)?;
+        *used = self.tick;
+        Some(body.clone())
+    }
+
+    fn insert(&mut self, key: TileKey, body: Arc<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (self.tick, body));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_skia::Pixmap;
+
+    #[test]
+    fn test_parse_tile_path() {
+        assert_eq!(
+            parse_tile_path("/3/1/2.png"),
+            Some((TileFormat::Png, 3, 1, 2))
+        );
+        assert_eq!(
+            parse_tile_path("/3/1/2.pbf"),
+            Some((TileFormat::Vector, 3, 1, 2))
+        );
+        assert_eq!(parse_tile_path("/3/1/2.jpg"), None);
+        assert_eq!(parse_tile_path("/3/1/2/4.png"), None);
+        assert_eq!(parse_tile_path("/3/-1/2.png"), None);
+    }
+
+    #[test]
+    fn test_png_tile() {
+        let mut service = TileService::new("http://127.0.0.1:8080", 4);
+        let zoom = service.grid().max_zoom();
+        let center = 1 << (zoom - 1);
+        let path = format!("/{}/{}/{}.png?v=1", zoom, center, center);
+        let response = service.handle(&path);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "image/png");
+        let image = Pixmap::decode_png(&response.body).unwrap();
+        assert_eq!((image.width(), image.height()), (256, 256));
+        // 背景のままの画素は輪郭線の隙間くらいしかない
+        let background = image
+            .pixels()
+            .iter()
+            .filter(|p| (p.red(), p.green(), p.blue()) == (255, 255, 255))
+            .count();
+        assert!(background < 256 * 256 / 10);
+
+        // 2回目はキャッシュから返す
+        assert!(Arc::ptr_eq(&service.handle(&path).body, &response.body));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut service = TileService::new("http://127.0.0.1:8080", 4);
+        let max_zoom = service.grid().max_zoom();
+        assert_eq!(service.handle("/0/0/0.png").status, 404);
+        assert_eq!(
+            service.handle(&format!("/{}/0/0.pbf", max_zoom + 1)).status,
+            404
+        );
+        assert_eq!(service.handle("/favicon.ico").status, 404);
+    }
+
+    #[test]
+    fn test_tile_json() {
+        let mut service = TileService::new("http://127.0.0.1:8080", 4);
+        let response = service.handle("/vector.json");
+        let json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(json["tiles"][0], "http://127.0.0.1:8080/{z}/{x}/{y}.pbf");
+        assert_eq!(json["minzoom"], service.grid().min_zoom());
+        assert_eq!(json["vector_layers"][0]["id"], LAYER_NAME);
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let mut cache = TileCache::new(2);
+        let body = Arc::new(vec![]);
+        cache.insert((TileFormat::Png, 0, 0, 0), body.clone());
+        cache.insert((TileFormat::Png, 0, 0, 1), body.clone());
+        cache.get(&(TileFormat::Png, 0, 0, 0));
+        cache.insert((TileFormat::Png, 0, 0, 2), body);
+        // 最も長く使われていないものから捨てる
+        assert!(cache.get(&(TileFormat::Png, 0, 0, 0)).is_some());
+        assert!(cache.get(&(TileFormat::Png, 0, 0, 1)).is_none());
+        assert!(cache.get(&(TileFormat::Png, 0, 0, 2)).is_some());
+    }
+}
@@ -0,0 +1,118 @@
+use glam::Vec2;
+
+use crate::{analysis::Substitution, controller::TilesController, utils::Aabb};
+
+/// XYZ形式の地図タイルの並び
+///
+/// zoom 0はClusterのbboxを覆う正方形一枚で、zoomが1上がるごとに縦横が2分割される。
+/// Clusterは1 levelごとに辺が√(4+√15)倍になるので、zoomは約1.49ずつcluster levelに対応する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileGrid {
+    /// zoom 0の地図タイルの左上の角
+    top_left: Vec2,
+    /// zoom 0の地図タイルの一辺
+    size: f32,
+    /// zoom 0の地図タイルに対応するcluster level
+    root_level: usize,
+    /// cluster levelが1下がるごとに上がるzoom
+    zooms_per_level: f32,
+}
+
+impl TileGrid {
+    /// 一枚の地図タイルに描くClusterのlevelの上限。これより粗いzoomのタイルは描かない
+    pub const MAX_RENDER_LEVEL: usize = 4;
+    /// 地図タイルの一辺の下限（辺の長さを1とする）
+    const MIN_TILE_SIZE: f32 = 2.0;
+
+    /// controllerのClusterを覆うようにする
+    pub fn new(controller: &TilesController) -> Self {
+        let bbox = controller.cluster_bbox();
+        let size = (bbox.max - bbox.min).max_element();
+        let center = (bbox.min + bbox.max) * 0.5;
+        let growth = Substitution::new().inflation_factor().sqrt();
+        Self {
+            top_left: center + Vec2::new(-size, size) * 0.5,
+            size,
+            root_level: controller.level(),
+            zooms_per_level: growth.log2() as f32,
+        }
+    }
+
+    /// zoomの地図タイル一枚に収まるClusterのlevel
+    pub fn cluster_level(&self, zoom: u32) -> usize {
+        let level = self.root_level as f32 - zoom as f32 / self.zooms_per_level;
+        level.round().max(0.0) as usize
+    }
+
+    /// 地図タイルを描く最も粗いzoom
+    pub fn min_zoom(&self) -> u32 {
+        (0..=self.max_zoom())
+            .find(|&zoom| self.cluster_level(zoom) <= Self::MAX_RENDER_LEVEL)
+            .unwrap_or(self.max_zoom())
+    }
+
+    /// 地図タイルを描く最も細かいzoom
+    pub fn max_zoom(&self) -> u32 {
+        (self.size / Self::MIN_TILE_SIZE).log2().floor().max(0.0) as u32
+    }
+
+    /// zoomの(x, y)の地図タイルが覆うワールド座標の範囲。描かないzoomや範囲外ならNone
+    pub fn tile_region(&self, zoom: u32, x: u32, y: u32) -> Option<Aabb> {
+        if zoom < self.min_zoom() || zoom > self.max_zoom() {
+            return None;
+        }
+        let count = 1u32 << zoom;
+        if x >= count || y >= count {
+            return None;
+        }
+        let side = self.size / count as f32;
+        let top_left = self.top_left + Vec2::new(x as f32, -(y as f32)) * side;
+        Some(Aabb::new(
+            top_left.x,
+            top_left.y - side,
+            top_left.x + side,
+            top_left.y,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_levels() {
+        let mut controller = TilesController::new();
+        for _ in 0..5 {
+            controller.expand();
+        }
+        let grid = TileGrid::new(&controller);
+        assert_eq!(grid.cluster_level(0), controller.level());
+        // 3 zoomでおよそ2 level下がる
+        assert_eq!(grid.cluster_level(3), controller.level() - 2);
+        let min_zoom = grid.min_zoom();
+        assert!(grid.cluster_level(min_zoom) <= TileGrid::MAX_RENDER_LEVEL);
+        assert!(grid.cluster_level(min_zoom - 1) > TileGrid::MAX_RENDER_LEVEL);
+        assert!(min_zoom < grid.max_zoom());
+    }
+
+    #[test]
+    fn test_tile_region() {
+        let mut controller = TilesController::new();
+        for _ in 0..5 {
+            controller.expand();
+        }
+        let grid = TileGrid::new(&controller);
+        let zoom = grid.max_zoom();
+        let count = 1 << zoom;
+        // 四隅の地図タイルを合わせるとzoom 0の正方形になる
+        let first = grid.tile_region(zoom, 0, 0).unwrap();
+        let last = grid.tile_region(zoom, count - 1, count - 1).unwrap();
+        assert!((last.max.x - first.min.x - grid.size).abs() < 1e-2);
+        assert!((first.max.y - last.min.y - grid.size).abs() < 1e-2);
+        assert!(first.max.y > last.max.y);
+        assert!(grid.tile_region(zoom, count, 0).is_none());
+        assert!(grid.tile_region(zoom + 1, 0, 0).is_none());
+        assert!(grid.tile_region(0, 0, 0).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{tiles::Spectre, utils::Aabb};
+
+/// ベクタータイルのレイヤー名
+pub const LAYER_NAME: &str = "spectres";
+/// ベクタータイルの座標の範囲
+const EXTENT: u32 = 4096;
+
+/// regionと重なるタイルをMapbox Vector Tileに符号化する
+///
+/// 一つのレイヤーに、タイルごとにrotation（0〜11）とreflectedを属性に持つポリゴンを入れる
+pub fn encode_vector_tile<'a>(
+    tiles: impl IntoIterator<Item = &'a Spectre>,
+    region: &Aabb,
+) -> Vec<u8> {
+    let size = region.max - region.min;
+    let mut layer = Message::default();
+    layer.uint(15, 2);
+    layer.bytes(1, LAYER_NAME.as_bytes());
+    for tile in tiles {
+        if !tile.bbox().has_intersection(region) {
+            continue;
+        }
+        // 地図タイルの左上を原点とし、y軸は下向き
+        let mut ring: Vec<[i32; 2]> = tile
+            .vertices()
+            .into_iter()
+            .map(|vertex| {
+                let p = vertex.to_vec2();
+                [
+                    ((p.x - region.min.x) / size.x * EXTENT as f32).round() as i32,
+                    ((region.max.y - p.y) / size.y * EXTENT as f32).round() as i32,
+                ]
+            })
+            .collect();
+        ring.dedup();
+        if ring.len() < 3 {
+            continue;
+        }
+        // 外周はy軸下向きの座標で時計回り（面積が正）にする
+        if signed_area(&ring) < 0 {
+            ring.reverse();
+        }
+
+        let mut feature = Message::default();
+        let rotation = tile.rotation().value() as u32;
+        let reflected = tile.is_reflected() as u32;
+        feature.packed(2, &[0, rotation, 1, 12 + reflected]);
+        // 3 = POLYGON
+        feature.uint(3, 3);
+        feature.packed(4, &geometry(&ring));
+        layer.bytes(2, &feature.0);
+    }
+    layer.bytes(3, b"rotation");
+    layer.bytes(3, b"reflected");
+    // 値の表は回転0〜11、false、trueの順
+    for rotation in 0..12 {
+        let mut value = Message::default();
+        value.uint(5, rotation);
+        layer.bytes(4, &value.0);
+    }
+    for reflected in [0, 1] {
+        let mut value = Message::default();
+        value.uint(7, reflected);
+        layer.bytes(4, &value.0);
+    }
+    layer.uint(5, EXTENT as u64);
+
+    let mut tile = Message::default();
+    tile.bytes(3, &layer.0);
+    tile.0
+}
+
+/// 閉じた輪をMoveTo、LineTo、ClosePathのコマンド列にする
+fn geometry(ring: &[[i32; 2]]) -> Vec<u32> {
+    let command = |id: u32, count: usize| id | (count as u32) << 3;
+    let zigzag = |n: i32| ((n << 1) ^ (n >> 31)) as u32;
+    let mut result = Vec::with_capacity(ring.len() * 2 + 3);
+    let mut cursor = [0, 0];
+    for (i, point) in ring.iter().enumerate() {
+        match i {
+            0 => result.push(command(1, 1)),
+            1 => result.push(command(2, ring.len() - 1)),
+            _ => {}
+        }
+        result.push(zigzag(point[0] - cursor[0]));
+        result.push(zigzag(point[1] - cursor[1]));
+        cursor = *point;
+    }
+    result.push(command(7, 1));
+    result
+}
+
+/// 面積の2倍。y軸下向きでは時計回りが正
+fn signed_area(ring: &[[i32; 2]]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let [x0, y0] = ring[i];
+            let [x1, y1] = ring[(i + 1) % ring.len()];
+            x0 as i64 * y1 as i64 - x1 as i64 * y0 as i64
+        })
+        .sum()
+}
+
+/// Protocol Buffersのメッセージ
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.varint((field as u64) << 3);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.varint((field as u64) << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u32]) {
+        let mut packed = Message::default();
+        for &value in values {
+            packed.varint(value as u64);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::{Anchor, SpectreCluster},
+        utils::{Angle, HexVec},
+    };
+
+    /// 長さ付きフィールドを(フィールド番号, 中身)として読む。varintのフィールドは値を8バイトで返す
+    fn fields(mut bytes: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        fn varint(bytes: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            for shift in (0..).step_by(7) {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte < 0x80 {
+                    break;
+                }
+            }
+            value
+        }
+        let mut result = vec![];
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes);
+            let content = match key & 7 {
+                0 => varint(&mut bytes).to_le_bytes().to_vec(),
+                2 => {
+                    let len = varint(&mut bytes) as usize;
+                    let (content, rest) = bytes.split_at(len);
+                    bytes = rest;
+                    content.to_vec()
+                }
+                wire => panic!("unexpected wire type {}", wire),
+            };
+            result.push(((key >> 3) as u32, content));
+        }
+        result
+    }
+
+    #[test]
+    fn test_geometry() {
+        // y軸下向きで時計回りの正方形
+        let ring = [[1, 1], [3, 1], [3, 3], [1, 3]];
+        assert!(signed_area(&ring) > 0);
+        assert_eq!(geometry(&ring), vec![9, 2, 2, 26, 4, 0, 0, 4, 3, 0, 15]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 1);
+        let region = cluster.bbox();
+        let tile = encode_vector_tile(cluster.spectres_in(region), &region);
+
+        let layers = fields(&tile);
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].0, 3);
+        let layer = fields(&layers[0].1);
+        let name = layer.iter().find(|(field, _)| *field == 1).unwrap();
+        assert_eq!(name.1, LAYER_NAME.as_bytes());
+        let features: Vec<_> = layer.iter().filter(|(field, _)| *field == 2).collect();
+        assert_eq!(features.len(), cluster.spectres_in(region).count());
+        let values = layer.iter().filter(|(field, _)| *field == 4).count();
+        assert_eq!(values, 14);
+
+        // 各ポリゴンは一つの輪
+        for (_, feature) in features {
+            let geometry = fields(feature)
+                .into_iter()
+                .find(|(field, _)| *field == 4)
+                .unwrap()
+                .1;
+            assert_eq!(geometry[0], 9);
+            assert_eq!(*geometry.last().unwrap(), 15);
+        }
+    }
+}
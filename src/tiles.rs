@@ -1,22 +1,52 @@
 mod anchor;
+mod cluster_hull;
+mod cluster_node;
+mod cluster_template;
+mod memory_usage;
 mod mystic;
-mod mystic_cluster;
-mod mystic_like;
 mod skeleton;
 mod spectre;
 mod spectre_cluster;
 mod spectre_iter;
-mod spectre_like;
+mod supertile_recognition;
+mod tile_address;
+
+use cluster_hull::cluster_hull;
+use cluster_node::{ClusterNode, Node, NodeId, NONE};
+use cluster_template::{cluster_template, cluster_with_anchor, skeleton_with_anchor};
 
 pub use anchor::Anchor;
+pub use memory_usage::MemoryUsage;
 pub use mystic::Mystic;
-pub use mystic_cluster::MysticCluster;
-pub use mystic_like::MysticLike;
 pub use skeleton::Skeleton;
 pub use spectre::Spectre;
 pub use spectre_cluster::SpectreCluster;
 pub use spectre_iter::SpectreIter;
-pub use spectre_like::SpectreLike;
+pub use supertile_recognition::{RecognitionError, Supertile, SupertileRecognition};
+pub use tile_address::TileAddress;
 
 /// これより細かいClusterは必ずまとめてロードする
 const MIN_PARTIAL_CLUSTER_LEVEL: usize = 4;
+
+/// level nのClusterに含まれる、level 0のSpectreLikeとMysticLikeの数。u64に収まらなければNone
+///
+/// SpectreClusterはSpectreLike 7個とMysticLike 1個、MysticClusterはSpectreLike 6個とMysticLike 1個からなる
+pub(crate) fn cluster_counts(level: usize, is_mystic: bool) -> Option<(u64, u64)> {
+    let mut counts: (u64, u64) = if is_mystic { (0, 1) } else { (1, 0) };
+    for _ in 0..level {
+        let (spectre, mystic) = counts;
+        counts = (
+            spectre
+                .checked_mul(7)?
+                .checked_add(mystic.checked_mul(6)?)?,
+            spectre.checked_add(mystic)?,
+        );
+    }
+    Some(counts)
+}
+
+/// level nのClusterに含まれるタイル数（Mysticは2枚）。u64に収まらなければNone
+pub(crate) fn tile_count(level: usize, is_mystic: bool) -> Option<u64> {
+    let (spectre, mystic) = cluster_counts(level, is_mystic)?;
+    spectre.checked_add(mystic.checked_mul(2)?)
+}
@@ -0,0 +1,127 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::utils::{Angle, ConvexPolygon, HexVec};
+
+use super::{Anchor, Skeleton, Spectre};
+
+/// anchor1を原点に置いたClusterの凸包
+pub(super) struct ClusterHull {
+    /// 厳密な頂点（反時計回り）
+    vertices: Vec<HexVec>,
+    polygon: ConvexPolygon,
+}
+
+impl ClusterHull {
+    pub(super) fn polygon(&self) -> &ConvexPolygon {
+        &self.polygon
+    }
+}
+
+type HullKey = (usize, Angle, bool);
+
+thread_local! {
+    /// (level, anchor1から出る辺の向き, MysticLikeかどうか)ごとの凸包
+    static HULLS: RefCell<HashMap<HullKey, Rc<ClusterHull>>> = RefCell::new(HashMap::new());
+}
+
+/// 指定されたlevelと向きのClusterの凸包を取得する
+///
+/// 同じlevelと向きのClusterは平行移動で重なるので、一度計算したものを使い回す。
+/// 凸包は一つ下のlevelの子の凸包から再帰的に求めるため、タイルを列挙する必要はない。
+pub(super) fn cluster_hull(
+    level: usize,
+    edge_direction: Angle,
+    is_mystic: bool,
+) -> Rc<ClusterHull> {
+    let key = (level, edge_direction, is_mystic);
+    if let Some(hull) = HULLS.with_borrow(|hulls| hulls.get(&key).cloned()) {
+        return hull;
+    }
+
+    let points = if level == 0 {
+        let spectre = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, edge_direction);
+        if is_mystic {
+            let mystic = spectre.into_mystic();
+            let mut points = mystic.lower().vertices();
+            points.extend(mystic.upper().vertices());
+            points
+        } else {
+            spectre.vertices()
+        }
+    } else {
+        let skeleton = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, edge_direction, level);
+        let mut points = vec![];
+        for (i, child) in skeleton.split_into_skeletons().iter().enumerate() {
+            // MysticClusterはeを持たない
+            if is_mystic && i == 4 {
+                continue;
+            }
+            let child_hull = cluster_hull(
+                level - 1,
+                child.edge_direction_from(Anchor::Anchor1),
+                i == 7,
+            );
+            let offset = child.coordinate(Anchor::Anchor1);
+            points.extend(child_hull.vertices.iter().map(|&v| v + offset));
+        }
+        points
+    };
+
+    let vertices = HexVec::convex_hull(&points);
+    let polygon = ConvexPolygon::new(vertices.iter().map(|v| v.to_vec2()).collect());
+    let hull = Rc::new(ClusterHull { vertices, polygon });
+    HULLS.with_borrow_mut(|hulls| hulls.insert(key, hull.clone()));
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::SpectreCluster;
+
+    #[test]
+    fn test_hull_contains_all_tiles() {
+        for level in 1..=3 {
+            for direction in [0, 1, 4, 7] {
+                let direction = Angle::new(direction);
+                let cluster =
+                    SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, direction, level);
+                let vertices: Vec<HexVec> = cluster
+                    .spectres_in(cluster.bbox())
+                    .flat_map(|spectre| spectre.vertices())
+                    .collect();
+                let expected = HexVec::convex_hull(&vertices);
+
+                let hull = cluster_hull(level, direction, false);
+                assert_eq!(hull.vertices, expected, "hull mismatch at level {}", level);
+                assert_eq!(hull.polygon().bbox(), cluster.bbox());
+            }
+        }
+    }
+
+    #[test]
+    fn test_mystic_hull_contains_all_tiles() {
+        // MysticClusterはeを持たないので、SpectreClusterとは別の凸包になる
+        for level in 1..=3 {
+            for direction in [0, 1, 4, 7] {
+                let direction = Angle::new(direction);
+                let skeleton =
+                    Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, direction, level);
+                let cluster = SpectreCluster::with_skeleton(skeleton, true, &skeleton.bbox(true));
+                let vertices: Vec<HexVec> = cluster
+                    .spectres_in(cluster.bbox())
+                    .flat_map(|spectre| spectre.vertices())
+                    .collect();
+                let expected = HexVec::convex_hull(&vertices);
+
+                let hull = cluster_hull(level, direction, true);
+                assert_eq!(hull.vertices, expected, "hull mismatch at level {}", level);
+                assert_eq!(hull.polygon().bbox(), cluster.bbox());
+                assert_ne!(
+                    hull.vertices,
+                    cluster_hull(level, direction, false).vertices
+                );
+            }
+        }
+    }
+}
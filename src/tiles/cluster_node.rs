@@ -0,0 +1,62 @@
+use crate::utils::Aabb;
+
+use super::{Mystic, Skeleton, Spectre};
+
+/// SpectreClusterのノード配列での番号
+pub(super) type NodeId = u32;
+
+/// 子が存在しないことを表す番号（MysticClusterはeを持たない）
+pub(super) const NONE: NodeId = NodeId::MAX;
+
+/// SpectreClusterの木のノード
+pub(super) enum Node {
+    Spectre(Spectre),
+    Mystic(Mystic),
+    Cluster(ClusterNode),
+    /// ロードしていないCluster。boolはMysticLikeの位置にあるかどうか
+    Skeleton(Skeleton, bool),
+    /// 解放済みで再利用を待っている
+    Free,
+}
+
+/// 子をロードしたClusterのノード
+#[derive(Clone, Copy)]
+pub(super) struct ClusterNode {
+    /// 子は[a, b, c, d, e, f, g, h]の順。MysticClusterのeはNONE
+    pub children: [NodeId; 8],
+    pub level: usize,
+    /// MysticLikeの位置にあるかどうか
+    pub is_mystic: bool,
+    pub bbox: Aabb,
+    /// 最後に表示範囲と交差したフレーム番号
+    pub last_visible: u64,
+}
+
+impl Node {
+    pub fn bbox(&self) -> Aabb {
+        match self {
+            Node::Spectre(spectre) => spectre.bbox(),
+            Node::Mystic(mystic) => mystic.bbox(),
+            Node::Cluster(cluster) => cluster.bbox,
+            Node::Skeleton(skeleton, is_mystic) => skeleton.bbox(*is_mystic),
+            Node::Free => Aabb::NULL,
+        }
+    }
+
+    /// このノード自身が持つタイルの数（子孫は含めない）
+    pub fn tiles(&self) -> u64 {
+        match self {
+            Node::Spectre(_) => 1,
+            Node::Mystic(_) => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        match self {
+            Node::Cluster(cluster) => cluster.level,
+            Node::Skeleton(skeleton, _) => skeleton.level(),
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::utils::{Angle, HexVec};
+
+use super::{Anchor, Skeleton, SpectreCluster};
+
+type TemplateKey = (usize, Angle);
+
+thread_local! {
+    /// (level, anchor1から出る辺の向き)ごとの、anchor1を原点に置いたSkeleton
+    static SKELETONS: RefCell<HashMap<TemplateKey, Skeleton>> = RefCell::new(HashMap::new());
+    /// (level, anchor1から出る辺の向き)ごとの、anchor1を原点に置いたSpectreCluster
+    static CLUSTERS: RefCell<HashMap<TemplateKey, Rc<SpectreCluster>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// anchorから出る辺がedge_directionを向くlevelのSkeletonを、テンプレートの平行移動で作る
+pub(super) fn skeleton_with_anchor(
+    anchor: Anchor,
+    coordinate: HexVec,
+    edge_direction: Angle,
+    level: usize,
+) -> Skeleton {
+    let template = skeleton_template(level, anchor1_direction(anchor, edge_direction, level));
+    template.translated(coordinate - template.coordinate(anchor))
+}
+
+/// anchorから出る辺がedge_directionを向くlevelのSpectreClusterを、テンプレートの平行移動で作る
+///
+/// テンプレートはタイルを全て持つので、まとめてロードされる小さいlevelにだけ使う
+pub(super) fn cluster_with_anchor(
+    anchor: Anchor,
+    coordinate: HexVec,
+    edge_direction: Angle,
+    level: usize,
+) -> SpectreCluster {
+    let rotation = anchor1_direction(anchor, edge_direction, level);
+    // 座標はSkeletonのテンプレートから求めればClusterを辿らずに済む
+    let offset = coordinate - skeleton_template(level, rotation).coordinate(anchor);
+    cluster_template(level, rotation).translated(offset)
+}
+
+/// anchorから出る辺がedge_directionを向くときの、anchor1から出る辺の向き
+///
+/// 同じlevelのClusterは回転で重なるので、回転していないテンプレートとの差から求まる
+fn anchor1_direction(anchor: Anchor, edge_direction: Angle, level: usize) -> Angle {
+    edge_direction - skeleton_template(level, Angle::ZERO).edge_direction_from(anchor)
+}
+
+fn skeleton_template(level: usize, rotation: Angle) -> Skeleton {
+    let key = (level, rotation);
+    if let Some(template) = SKELETONS.with_borrow(|templates| templates.get(&key).copied()) {
+        return template;
+    }
+    let template = Skeleton::build(Anchor::Anchor1, HexVec::ZERO, rotation, level);
+    SKELETONS.with_borrow_mut(|templates| templates.insert(key, template));
+    template
+}
+
+pub(super) fn cluster_template(level: usize, rotation: Angle) -> Rc<SpectreCluster> {
+    let key = (level, rotation);
+    if let Some(template) = CLUSTERS.with_borrow(|templates| templates.get(&key).cloned()) {
+        return template;
+    }
+    let template = Rc::new(SpectreCluster::build(
+        Anchor::Anchor1,
+        HexVec::ZERO,
+        rotation,
+        level,
+    ));
+    CLUSTERS.with_borrow_mut(|templates| templates.insert(key, template.clone()));
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANCHORS: [Anchor; 4] = [
+        Anchor::Anchor1,
+        Anchor::Anchor2,
+        Anchor::Anchor3,
+        Anchor::Anchor4,
+    ];
+
+    #[test]
+    fn test_skeleton_template_matches_build() {
+        let coordinate = Skeleton::build(Anchor::Anchor1, HexVec::ZERO, Angle::new(3), 2)
+            .coordinate(Anchor::Anchor3);
+        for level in 1..=6 {
+            for anchor in ANCHORS {
+                for direction in 0..12 {
+                    let direction = Angle::new(direction);
+                    assert_eq!(
+                        skeleton_with_anchor(anchor, coordinate, direction, level),
+                        Skeleton::build(anchor, coordinate, direction, level),
+                        "mismatch at level {}, anchor {:?}",
+                        level,
+                        anchor
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cluster_template_matches_build() {
+        let coordinate = Skeleton::build(Anchor::Anchor1, HexVec::ZERO, Angle::new(5), 3)
+            .coordinate(Anchor::Anchor2);
+        for level in 1..=3 {
+            for anchor in ANCHORS {
+                for direction in [0, 3, 7, 10] {
+                    let direction = Angle::new(direction);
+                    let cached = cluster_with_anchor(anchor, coordinate, direction, level);
+                    let built = SpectreCluster::build(anchor, coordinate, direction, level);
+                    assert_eq!(cached.bbox(), built.bbox());
+                    let spectres = |cluster: &SpectreCluster| {
+                        cluster
+                            .spectres_in(cluster.bbox())
+                            .map(|spectre| {
+                                (spectre.coordinate(Anchor::Anchor1), spectre.rotation())
+                            })
+                            .collect::<Vec<_>>()
+                    };
+                    assert_eq!(spectres(&cached), spectres(&built));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::ops::{Add, AddAssign};
+
+/// 読み込まれているタイルの数と、それを保持するためのメモリ量
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// 読み込まれているタイルの数（Mysticは2枚として数える）
+    pub tiles: u64,
+    /// 使用中のノードのバイト数（解放して再利用を待つノードは含めない）
+    pub bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn new(tiles: u64, bytes: usize) -> Self {
+        Self { tiles, bytes }
+    }
+}
+
+impl Add for MemoryUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            tiles: self.tiles + other.tiles,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
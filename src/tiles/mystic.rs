@@ -14,6 +14,11 @@ impl Mystic {
         Self { lower, upper, bbox }
     }
 
+    /// offsetだけ平行移動したMystic
+    pub fn translated(&self, offset: HexVec) -> Self {
+        Self::new(self.lower.translated(offset), self.upper.translated(offset))
+    }
+
     pub fn coordinate(&self, anchor: Anchor) -> HexVec {
         self.lower.coordinate(anchor)
     }
@@ -1,8 +1,11 @@
-use crate::utils::{Aabb, Angle, HexVec};
+use crate::utils::{Aabb, Angle, ConvexPolygon, HexVec, Region};
 
-use super::{Anchor, Spectre, SpectreCluster, SpectreLike, MIN_PARTIAL_CLUSTER_LEVEL};
+use super::{
+    cluster_hull, skeleton_with_anchor, tile_count, Anchor, Spectre, SpectreCluster,
+    MIN_PARTIAL_CLUSTER_LEVEL,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Skeleton {
     anchor1: HexVec,
     anchor2: HexVec,
@@ -17,7 +20,6 @@ pub struct Skeleton {
     edge_direction_into_anchor4: Angle,
     edge_direction_from_anchor4: Angle,
     level: usize,
-    inherited_bbox: Option<Aabb>,
 }
 
 impl Skeleton {
@@ -26,7 +28,16 @@ impl Skeleton {
         coordinate: impl Into<HexVec>,
         edge_direction: impl Into<Angle>,
         level: usize,
-        inherited_bbox: Option<Aabb>,
+    ) -> Self {
+        skeleton_with_anchor(anchor, coordinate.into(), edge_direction.into(), level)
+    }
+
+    /// テンプレートを使わずに子のチェーンを辿って生成する
+    pub(super) fn build(
+        anchor: Anchor,
+        coordinate: HexVec,
+        edge_direction: Angle,
+        level: usize,
     ) -> Self {
         // 子の循環接続チェーン: children[i] →(from, to)→ children[(i+1)%8]
         const EDGE_CHAIN: [(Anchor, Anchor); 8] = [
@@ -48,14 +59,11 @@ impl Skeleton {
             Anchor::Anchor4 => (0, Anchor::Anchor2, 6), // a→...→g まで6ステップ
         };
 
-        let coordinate = coordinate.into();
-        let edge_direction = edge_direction.into();
-
         // チェーン順に子を構築（座標抽出に必要な g, d, b, a に到達するまで）
         let first: Skeleton = if level == 1 {
             Spectre::with_anchor(start_anchor, coordinate, edge_direction).into()
         } else {
-            Skeleton::with_anchor(start_anchor, coordinate, edge_direction, level - 1, None)
+            Skeleton::with_anchor(start_anchor, coordinate, edge_direction, level - 1)
         };
 
         let mut children = [None; 8];
@@ -89,7 +97,6 @@ impl Skeleton {
             edge_direction_into_anchor4: a.edge_direction_into(Anchor::Anchor2),
             edge_direction_from_anchor4: a.edge_direction_from(Anchor::Anchor2),
             level,
-            inherited_bbox,
         }
     }
 
@@ -135,53 +142,49 @@ impl Skeleton {
         new_skeleton.anchor2 += offset;
         new_skeleton.anchor3 += offset;
         new_skeleton.anchor4 += offset;
-        new_skeleton.inherited_bbox = None;
 
         new_skeleton
     }
 
-    pub fn to_spectre_cluster(&self, bbox: &Aabb) -> SpectreCluster {
+    /// offsetだけ平行移動したSkeleton
+    pub fn translated(&self, offset: HexVec) -> Self {
+        Self {
+            anchor1: self.anchor1 + offset,
+            anchor2: self.anchor2 + offset,
+            anchor3: self.anchor3 + offset,
+            anchor4: self.anchor4 + offset,
+            ..*self
+        }
+    }
+
+    /// regionと交差する部分だけをロードしたClusterに変換する
+    pub fn to_spectre_cluster<R: Region>(&self, region: &R) -> SpectreCluster {
+        SpectreCluster::with_skeleton(*self, false, region)
+    }
+
+    /// bboxと交差するタイルの数を数える
+    ///
+    /// bboxに完全に含まれる部分はClusterに変換せずにlevelから数を求める。
+    /// is_mysticはこのSkeletonがMysticLikeの位置にあるかどうか。
+    /// u64に収まらない数はu64::MAXで頭打ちにする。
+    pub fn count_in<R: Region>(&self, region: &R, is_mystic: bool) -> u64 {
+        if !self.has_intersection(region, is_mystic) {
+            return 0;
+        }
+        if region.contains_aabb(&self.bbox(is_mystic)) {
+            return tile_count(self.level, is_mystic).unwrap_or(u64::MAX);
+        }
         if self.level < MIN_PARTIAL_CLUSTER_LEVEL {
-            // 小さいlevelのSkeletonはそのままClusterに変換
-            return SpectreCluster::with_anchor(
-                Anchor::Anchor1,
-                self.anchor1,
-                self.edge_direction_from_anchor1,
-                self.level,
-            );
+            return SpectreCluster::with_skeleton(*self, is_mystic, region).count_in(region);
         }
 
-        let mut sub_spectre_likes = self
-            .split_into_skeletons()
-            .into_iter()
-            .map(|sub_skeleton| {
-                if sub_skeleton.estimated_bbox().has_intersection(bbox) {
-                    SpectreLike::from(sub_skeleton.to_spectre_cluster(bbox))
-                } else {
-                    SpectreLike::Skeleton(sub_skeleton)
-                }
-            })
-            .collect::<Vec<_>>();
-        let h = sub_spectre_likes.pop().unwrap().into_mystic_like();
-        let g = sub_spectre_likes.pop().unwrap();
-        let f = sub_spectre_likes.pop().unwrap();
-        let e = sub_spectre_likes.pop().unwrap();
-        let d = sub_spectre_likes.pop().unwrap();
-        let c = sub_spectre_likes.pop().unwrap();
-        let b = sub_spectre_likes.pop().unwrap();
-        let a = sub_spectre_likes.pop().unwrap();
-
-        SpectreCluster::new(
-            Box::new(a),
-            Box::new(b),
-            Box::new(c),
-            Box::new(d),
-            Box::new(e),
-            Box::new(f),
-            Box::new(g),
-            Box::new(h),
-            self.level,
-        )
+        // 境界にまたがる場合は一つ下のlevelに分割して数える（MysticClusterはeを持たない）
+        self.split_into_skeletons()
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !(is_mystic && i == 4))
+            .map(|(i, sub_skeleton)| sub_skeleton.count_in(region, i == 7))
+            .fold(0, u64::saturating_add)
     }
 
     pub fn coordinate(&self, anchor: Anchor) -> HexVec {
@@ -211,50 +214,28 @@ impl Skeleton {
         }
     }
 
-    pub fn estimated_bbox(&self) -> Aabb {
-        if let Some(inherited_bbox) = self.inherited_bbox {
-            return inherited_bbox;
-        }
+    /// このSkeletonが表すClusterの凸包
+    ///
+    /// is_mysticはこのSkeletonがMysticLikeの位置にあるかどうか
+    pub fn bounding_polygon(&self, is_mystic: bool) -> ConvexPolygon {
+        cluster_hull(self.level, self.edge_direction_from_anchor1, is_mystic)
+            .polygon()
+            .translated(self.anchor1.to_vec2())
+    }
 
-        let axis2 = self.anchor2 - self.anchor1;
-        let axis4 = self.anchor4 - self.anchor1;
-        let p5 = self.anchor1 + axis2 - axis4 / 2;
-        let p6 = self.anchor1 - axis2 / 2 + axis4 / 4;
-        let p7 = self.anchor1 + axis2 + axis4 / 2;
-
-        let points = [
-            self.anchor1,
-            self.anchor2,
-            self.anchor3,
-            self.anchor4,
-            p5,
-            p6,
-            p7,
-        ];
-        let mut min_x = f32::INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-
-        for p in points {
-            let p = p.to_vec2();
-            min_x = min_x.min(p.x);
-            min_y = min_y.min(p.y);
-            max_x = max_x.max(p.x);
-            max_y = max_y.max(p.y);
-        }
+    /// このSkeletonが表すClusterのbbox
+    pub fn bbox(&self, is_mystic: bool) -> Aabb {
+        let hull_bbox = cluster_hull(self.level, self.edge_direction_from_anchor1, is_mystic)
+            .polygon()
+            .bbox();
+        let offset = self.anchor1.to_vec2();
+        Aabb::from_min_max(hull_bbox.min + offset, hull_bbox.max + offset)
+    }
 
-        let expanded_min_x = min_x - (max_x - min_x) * 0.25;
-        let expanded_min_y = min_y - (max_y - min_y) * 0.25;
-        let expanded_max_x = max_x + (max_x - min_x) * 0.25;
-        let expanded_max_y = max_y + (max_y - min_y) * 0.25;
-
-        Aabb::new(
-            expanded_min_x,
-            expanded_min_y,
-            expanded_max_x,
-            expanded_max_y,
-        )
+    /// このSkeletonが表すClusterの凸包がregionと交差するかどうか
+    pub fn has_intersection<R: Region>(&self, region: &R, is_mystic: bool) -> bool {
+        region.intersects_aabb(&self.bbox(is_mystic))
+            && region.intersects_polygon(&self.bounding_polygon(is_mystic))
     }
 
     pub fn level(&self) -> usize {
@@ -262,7 +243,7 @@ impl Skeleton {
     }
 
     /// 一つ下のlevelのskeletonのリストに変換
-    fn split_into_skeletons(self) -> [Skeleton; 8] {
+    pub fn split_into_skeletons(self) -> [Skeleton; 8] {
         let a = if self.level == 1 {
             Spectre::with_anchor(
                 Anchor::Anchor2,
@@ -276,7 +257,6 @@ impl Skeleton {
                 self.anchor4,
                 self.edge_direction_from(Anchor::Anchor4),
                 self.level - 1,
-                None,
             )
         };
         let b = a.connected_skeleton(Anchor::Anchor3, Anchor::Anchor1);
@@ -320,7 +300,6 @@ impl From<Spectre> for Skeleton {
             edge_direction_into_anchor4,
             edge_direction_from_anchor4,
             level,
-            inherited_bbox: Some(spectre.bbox()),
         }
     }
 }
@@ -342,7 +321,7 @@ mod tests {
         ];
 
         for &(level, coordinate, anchor, edge_direction) in &test_cases {
-            let skeleton = Skeleton::with_anchor(anchor, coordinate, edge_direction, level, None);
+            let skeleton = Skeleton::with_anchor(anchor, coordinate, edge_direction, level);
             let cluster = SpectreCluster::with_anchor(anchor, coordinate, edge_direction, level);
 
             for test_anchor in [
@@ -1,12 +1,14 @@
-use crate::utils::{Aabb, Angle, HexValue, HexVec};
+use glam::Vec2;
+
+use crate::utils::{Aabb, Angle, HexValue, HexVec, Orientation};
 
 use super::{Anchor, Mystic};
 
 /// タイルの形状を表す
 #[derive(Clone, Copy)]
 pub struct Spectre {
-    /// アンカー1から反時計回りに進む辺の向く方向
-    rotation: Angle,
+    /// 向き。rotationはアンカー1から出る辺の向く方向で、鏡映されている場合は頂点が時計回りに並ぶ
+    orientation: Orientation,
     /// アンカー1の座標
     anchor1: HexVec,
     /// bounding box
@@ -19,12 +21,14 @@ impl Spectre {
     }
 
     pub fn edge_direction_from(&self, anchor: Anchor) -> Angle {
-        Self::EDGE_DIRECTIONS[anchor.index()] + self.rotation
+        self.orientation
+            .apply(Self::EDGE_DIRECTIONS[anchor.index()])
     }
 
     pub fn edge_direction_into(&self, anchor: Anchor) -> Angle {
-        Self::EDGE_DIRECTIONS[(anchor.index() + Self::VERTEX_COUNT - 1) % Self::VERTEX_COUNT]
-            + self.rotation
+        self.orientation.apply(
+            Self::EDGE_DIRECTIONS[(anchor.index() + Self::VERTEX_COUNT - 1) % Self::VERTEX_COUNT],
+        )
     }
 
     pub fn bbox(&self) -> Aabb {
@@ -32,7 +36,16 @@ impl Spectre {
     }
 
     pub fn rotation(&self) -> Angle {
-        self.rotation
+        self.orientation.rotation
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// 鏡映されたタイルかどうか
+    pub fn is_reflected(&self) -> bool {
+        self.orientation.reflected
     }
 }
 
@@ -66,10 +79,33 @@ impl Spectre {
         Self::with_vertex(coordinate.into(), anchor.index(), edge_direction.into())
     }
 
+    /// offsetだけ平行移動したSpectre
+    ///
+    /// bboxは平行移動後の頂点から求め直すので、with_anchorで作った場合と一致する
+    pub fn translated(&self, offset: HexVec) -> Self {
+        Self::with_orientation(self.anchor1 + offset, 0, self.orientation)
+    }
+
+    /// centerを中心にorientationで変換したSpectre
+    ///
+    /// 鏡映を含むorientationを使うと鏡像のタイルになる
+    pub fn transformed(&self, center: HexVec, orientation: Orientation) -> Self {
+        Self::with_orientation(
+            self.anchor1.transform(center, orientation),
+            0,
+            orientation.compose(self.orientation),
+        )
+    }
+
     /// Mysticに変換する
+    ///
+    /// 上半分は頂点13を下半分の頂点1に重ね、そこから出る辺を下半分の向きから9だけ回した向きにする。
+    /// 鏡映されたタイルでは上半分も同じように鏡映される
     pub fn into_mystic(self) -> Mystic {
         let lower = self;
-        let upper = Spectre::with_vertex(lower.vertex(1), 13, lower.rotation + Angle::new(9));
+        let relative = Orientation::rotation(Angle::new(9) - Self::EDGE_DIRECTIONS[13]);
+        let upper =
+            Spectre::with_orientation(lower.vertex(1), 13, lower.orientation.compose(relative));
         Mystic::new(lower, upper)
     }
 
@@ -90,6 +126,35 @@ impl Spectre {
         Self::with_anchor(to_anchor, self.vertex(from_anchor.index()), angle)
     }
 
+    /// index番目の頂点での内角
+    ///
+    /// 向きによらず同じなので、Spectreの形だけから決まる
+    pub fn interior_angle(index: usize) -> Angle {
+        let into = Self::EDGE_DIRECTIONS[(index + Self::VERTEX_COUNT - 1) % Self::VERTEX_COUNT];
+        let from = Self::EDGE_DIRECTIONS[index % Self::VERTEX_COUNT];
+        // 左に曲がるほど内角は小さくなる
+        Angle::new(6) - (from - into)
+    }
+
+    /// pointがタイルの内側にあるかどうか（境界上の点は含まないことがある）
+    pub fn contains(&self, point: Vec2) -> bool {
+        if !self.bbox.contains(point) {
+            return false;
+        }
+        let vertices: Vec<Vec2> = self.vertices().into_iter().map(HexVec::to_vec2).collect();
+        let mut inside = false;
+        for i in 0..vertices.len() {
+            let a = vertices[i];
+            let b = vertices[(i + 1) % vertices.len()];
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
     /// 頂点
     pub fn vertices(&self) -> Vec<HexVec> {
         let mut points = Vec::with_capacity(Self::VERTEX_COUNT);
@@ -97,7 +162,7 @@ impl Spectre {
         points.push(p);
 
         for i in 0..Self::VERTEX_COUNT - 1 {
-            let dir = Self::direction_vector(self.rotation, Self::EDGE_DIRECTIONS[i]);
+            let dir = Self::direction_vector(self.orientation, Self::EDGE_DIRECTIONS[i]);
             p += dir;
             points.push(p);
         }
@@ -111,15 +176,20 @@ impl Spectre {
     /// * `index` - 基準点のインデックス
     /// * `edge_direction` - anchor_pointから出る辺の角度
     fn with_vertex(vertex: HexVec, index: usize, edge_direction: Angle) -> Self {
+        let angle = edge_direction - Self::EDGE_DIRECTIONS[index];
+        Self::with_orientation(vertex, index, Orientation::rotation(angle))
+    }
+
+    /// 指定された頂点と向きを基準にSpectreを生成する
+    fn with_orientation(vertex: HexVec, index: usize, orientation: Orientation) -> Self {
         let mut vertices = [HexVec::ZERO; Self::VERTEX_COUNT];
         vertices[index] = vertex;
-        let angle = edge_direction - Self::EDGE_DIRECTIONS[index];
 
         // アンカーから前方の点を配置
-        Self::place_vertices_before(&mut vertices[..index], vertex, angle);
+        Self::place_vertices_before(&mut vertices[..index], vertex, orientation);
 
         // アンカーから後方の点を配置
-        Self::place_vertices_after(&mut vertices[index + 1..], vertex, index, angle);
+        Self::place_vertices_after(&mut vertices[index + 1..], vertex, index, orientation);
 
         // Calculate AABB more efficiently using min/max tracking
         let mut min_x = f32::INFINITY;
@@ -139,23 +209,23 @@ impl Spectre {
         let bbox = Aabb::new(min_x, min_y, max_x, max_y);
 
         Self {
-            rotation: angle,
+            orientation,
             anchor1: vertices[0],
             bbox,
         }
     }
 
     /// 指定された角度の方向ベクトルを計算する
-    fn direction_vector(angle: Angle, direction: Angle) -> HexVec {
-        let total_angle = angle + direction;
+    fn direction_vector(orientation: Orientation, direction: Angle) -> HexVec {
+        let total_angle = orientation.apply(direction);
         HexVec::new(HexValue::cos(total_angle), HexValue::sin(total_angle))
     }
 
     /// アンカーより前方の点を配置する（時計回り）
-    fn place_vertices_before(vertices: &mut [HexVec], start: HexVec, angle: Angle) {
+    fn place_vertices_before(vertices: &mut [HexVec], start: HexVec, orientation: Orientation) {
         let mut p = start;
         for (i, point) in vertices.iter_mut().enumerate().rev() {
-            let dir = Self::direction_vector(angle, Self::EDGE_DIRECTIONS[i]);
+            let dir = Self::direction_vector(orientation, Self::EDGE_DIRECTIONS[i]);
             p -= dir;
             *point = p;
         }
@@ -166,11 +236,11 @@ impl Spectre {
         vertices: &mut [HexVec],
         start: HexVec,
         anchor_index: usize,
-        angle: Angle,
+        orientation: Orientation,
     ) {
         let mut p = start;
         for (i, point) in vertices.iter_mut().enumerate() {
-            let dir = Self::direction_vector(angle, Self::EDGE_DIRECTIONS[anchor_index + i]);
+            let dir = Self::direction_vector(orientation, Self::EDGE_DIRECTIONS[anchor_index + i]);
             p += dir;
             *point = p;
         }
@@ -184,9 +254,80 @@ impl Spectre {
         // Calculate points using a cumulative approach
         let mut p = self.anchor1;
         for i in 0..index {
-            let dir = Self::direction_vector(self.rotation, Self::EDGE_DIRECTIONS[i]);
+            let dir = Self::direction_vector(self.orientation, Self::EDGE_DIRECTIONS[i]);
             p += dir;
         }
         p
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transformed() {
+        let spectre = Spectre::with_anchor(Anchor::Anchor2, HexVec::ZERO, Angle::new(3));
+        let center = spectre.coordinate(Anchor::Anchor4);
+        let reflection = Orientation::new(Angle::new(4), true);
+
+        // 鏡像の頂点は元の頂点を鏡映したもの
+        let mirrored = spectre.transformed(center, reflection);
+        assert!(mirrored.is_reflected());
+        let expected: Vec<HexVec> = spectre
+            .vertices()
+            .into_iter()
+            .map(|p| p.transform(center, reflection))
+            .collect();
+        assert_eq!(mirrored.vertices(), expected);
+        for anchor in [Anchor::Anchor1, Anchor::Anchor3] {
+            assert_eq!(
+                mirrored.edge_direction_from(anchor),
+                reflection.apply(spectre.edge_direction_from(anchor))
+            );
+        }
+
+        // 二回鏡映すると元に戻る
+        let restored = mirrored.transformed(center, reflection);
+        assert!(!restored.is_reflected());
+        assert_eq!(restored.vertices(), spectre.vertices());
+        assert_eq!(restored.bbox(), spectre.bbox());
+    }
+
+    #[test]
+    fn test_reflected_mystic() {
+        let spectre = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::new(5));
+        let center = spectre.coordinate(Anchor::Anchor3);
+        let reflection = Orientation::new(Angle::new(2), true);
+
+        // 鏡映したタイルのMysticは、元のMysticを鏡映したもの
+        let mystic = spectre.into_mystic();
+        let mirrored = spectre.transformed(center, reflection).into_mystic();
+        let expected = mystic.upper().transformed(center, reflection);
+        assert!(mirrored.upper().is_reflected());
+        assert_eq!(mirrored.upper().vertices(), expected.vertices());
+        assert_eq!(mirrored.upper().orientation(), expected.orientation());
+    }
+
+    #[test]
+    fn test_contains() {
+        let spectre = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::new(4));
+        let vertices = spectre.vertices();
+        assert!(spectre.contains((vertices[0].to_vec2() + vertices[7].to_vec2()) * 0.5));
+        assert!(!spectre.contains(spectre.bbox().min - Vec2::ONE));
+        // bboxの角はタイルの外
+        assert!(!spectre.contains(spectre.bbox().min + Vec2::splat(0.01)));
+    }
+
+    #[test]
+    fn test_interior_angle() {
+        // 14角形の内角の和は12直角
+        let sum: u32 = (0..Spectre::VERTEX_COUNT)
+            .map(|i| Spectre::interior_angle(i).value() as u32)
+            .sum();
+        assert_eq!(sum, 12 * 6);
+        assert_eq!(Spectre::interior_angle(1), Angle::new(6));
+        assert_eq!(Spectre::interior_angle(2), Angle::new(4));
+        assert_eq!(Spectre::interior_angle(3), Angle::new(9));
+    }
+}
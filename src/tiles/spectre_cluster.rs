@@ -1,252 +1,750 @@
-use crate::utils::{Aabb, Angle, HexVec};
+use crate::utils::{Aabb, Angle, HexVec, Region};
 
 use super::{
-    Anchor, MysticCluster, MysticLike, Skeleton, SpectreIter, SpectreLike,
-    MIN_PARTIAL_CLUSTER_LEVEL,
+    cluster_template, cluster_with_anchor, tile_count, Anchor, ClusterNode, MemoryUsage, Node,
+    NodeId, Skeleton, Spectre, SpectreIter, MIN_PARTIAL_CLUSTER_LEVEL, NONE,
 };
 
+/// 子の循環接続チェーン: children[i] →(from, to)→ children[(i+1)%8]
+const EDGE_CHAIN: [(Anchor, Anchor); 8] = [
+    (Anchor::Anchor3, Anchor::Anchor1), // a→b
+    (Anchor::Anchor4, Anchor::Anchor2), // b→c
+    (Anchor::Anchor3, Anchor::Anchor1), // c→d
+    (Anchor::Anchor3, Anchor::Anchor1), // d→e
+    (Anchor::Anchor4, Anchor::Anchor2), // e→f
+    (Anchor::Anchor3, Anchor::Anchor1), // f→g
+    (Anchor::Anchor4, Anchor::Anchor4), // g→h
+    (Anchor::Anchor1, Anchor::Anchor1), // h→a
+];
+
+/// タイルの木
+///
+/// ノードを一つの配列に並べ、子は番号で指す。兄弟は連続した番号に確保するので、
+/// まとめてロードした部分は配列上でもまとまり、列挙するときのキャッシュ効率がよい。
+/// Skeletonに戻した部分木の番号は解放し、次にロードする部分木で再利用する
 pub struct SpectreCluster {
-    pub(super) a: Box<SpectreLike>,
-    pub(super) b: Box<SpectreLike>,
-    pub(super) c: Box<SpectreLike>,
-    pub(super) d: Box<SpectreLike>,
-    pub(super) e: Box<SpectreLike>,
-    pub(super) f: Box<SpectreLike>,
-    pub(super) g: Box<SpectreLike>,
-    pub(super) h: Box<MysticLike>,
-    level: usize,
-    bbox: Aabb,
+    pub(super) nodes: Vec<Node>,
+    free: Vec<NodeId>,
+    pub(super) root: NodeId,
+    /// 読み込まれているタイルの数。ノードを置き換えるたびに更新する
+    tiles: u64,
 }
 
 impl SpectreCluster {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        a: impl Into<Box<SpectreLike>>,
-        b: impl Into<Box<SpectreLike>>,
-        c: impl Into<Box<SpectreLike>>,
-        d: impl Into<Box<SpectreLike>>,
-        e: impl Into<Box<SpectreLike>>,
-        f: impl Into<Box<SpectreLike>>,
-        g: impl Into<Box<SpectreLike>>,
-        h: impl Into<Box<MysticLike>>,
+    pub fn with_anchor(
+        anchor: Anchor,
+        coordinate: impl Into<HexVec>,
+        edge_direction: impl Into<Angle>,
         level: usize,
     ) -> Self {
-        let a = a.into();
-        let b = b.into();
-        let c = c.into();
-        let d = d.into();
-        let e = e.into();
-        let f = f.into();
-        let g = g.into();
-        let h = h.into();
-        assert_eq!(h.coordinate(Anchor::Anchor1), a.coordinate(Anchor::Anchor1));
-        assert_eq!(a.coordinate(Anchor::Anchor3), b.coordinate(Anchor::Anchor1));
-        assert_eq!(b.coordinate(Anchor::Anchor4), c.coordinate(Anchor::Anchor2));
-        assert_eq!(c.coordinate(Anchor::Anchor3), d.coordinate(Anchor::Anchor1));
-        assert_eq!(d.coordinate(Anchor::Anchor3), e.coordinate(Anchor::Anchor1));
-        assert_eq!(e.coordinate(Anchor::Anchor4), f.coordinate(Anchor::Anchor2));
-        assert_eq!(f.coordinate(Anchor::Anchor3), g.coordinate(Anchor::Anchor1));
-        assert_eq!(g.coordinate(Anchor::Anchor4), h.coordinate(Anchor::Anchor4));
-
-        // Calculate AABB only for existing parts
-        let mut bbox = Aabb::NULL;
-        bbox = bbox.union(&a.bbox());
-        bbox = bbox.union(&b.bbox());
-        bbox = bbox.union(&c.bbox());
-        bbox = bbox.union(&d.bbox());
-        bbox = bbox.union(&e.bbox());
-        bbox = bbox.union(&f.bbox());
-        bbox = bbox.union(&g.bbox());
-        bbox = bbox.union(&h.bbox());
-
-        Self {
-            a,
-            b,
-            c,
-            d,
-            e,
-            f,
-            g,
-            h,
-            level,
-            bbox,
+        let coordinate = coordinate.into();
+        let edge_direction = edge_direction.into();
+        // まとめてロードされる小さいClusterは、同じ向きのテンプレートを平行移動して作る
+        if level < MIN_PARTIAL_CLUSTER_LEVEL {
+            return cluster_with_anchor(anchor, coordinate, edge_direction, level);
         }
+        let skeleton = Skeleton::with_anchor(anchor, coordinate, edge_direction, level);
+        let mut cluster = Self::empty();
+        cluster.load_all(cluster.root, skeleton, false);
+        cluster
     }
 
-    pub fn with_anchor(
+    /// テンプレートを使わずに子を辿って生成する
+    pub(super) fn build(
         anchor: Anchor,
-        coordinate: impl Into<HexVec>,
-        edge_direction: impl Into<Angle>,
+        coordinate: HexVec,
+        edge_direction: Angle,
         level: usize,
     ) -> Self {
-        // 子の循環接続チェーン: children[i] →(from, to)→ children[(i+1)%8]
-        const EDGE_CHAIN: [(Anchor, Anchor); 8] = [
-            (Anchor::Anchor3, Anchor::Anchor1), // a→b
-            (Anchor::Anchor4, Anchor::Anchor2), // b→c
-            (Anchor::Anchor3, Anchor::Anchor1), // c→d
-            (Anchor::Anchor3, Anchor::Anchor1), // d→e
-            (Anchor::Anchor4, Anchor::Anchor2), // e→f
-            (Anchor::Anchor3, Anchor::Anchor1), // f→g
-            (Anchor::Anchor4, Anchor::Anchor4), // g→h
-            (Anchor::Anchor1, Anchor::Anchor1), // h→a
-        ];
+        let skeleton = Skeleton::build(anchor, coordinate, edge_direction, level);
+        let mut cluster = Self::empty();
+        cluster.build_all(cluster.root, skeleton, false);
+        cluster
+    }
 
-        let (start_idx, start_anchor) = match anchor {
-            Anchor::Anchor1 => (6, Anchor::Anchor3), // g から開始
-            Anchor::Anchor2 => (3, Anchor::Anchor2), // d から開始
-            Anchor::Anchor3 => (1, Anchor::Anchor3), // b から開始
-            Anchor::Anchor4 => (0, Anchor::Anchor2), // a から開始
-        };
+    /// skeletonの表すClusterのうち、regionと交差する子だけをロードしたもの
+    ///
+    /// is_mysticはこのClusterがMysticLikeの位置にあるかどうか
+    pub(super) fn with_skeleton<R: Region>(
+        skeleton: Skeleton,
+        is_mystic: bool,
+        region: &R,
+    ) -> Self {
+        let mut cluster = Self::empty();
+        cluster.load(cluster.root, skeleton, is_mystic, region);
+        cluster
+    }
 
-        let edge_direction: Angle = edge_direction.into();
-        let coordinate: HexVec = coordinate.into();
+    pub fn with_child_a(a: SpectreCluster) -> Self {
+        a.wrapped(0)
+    }
+
+    pub fn with_child_f(f: SpectreCluster) -> Self {
+        f.wrapped(5)
+    }
 
-        // チェーン順に子を構築
-        let mut chain = Vec::with_capacity(8);
-        chain.push(SpectreLike::with_anchor(
-            start_anchor,
-            coordinate,
-            edge_direction,
-            level - 1,
-        ));
-        for i in 0..7 {
-            let (from_anchor, to_anchor) = EDGE_CHAIN[(start_idx + i) % 8];
-            let next = chain.last().unwrap().connected_spectre_like(from_anchor, to_anchor);
-            chain.push(next);
+    /// 根をslotの子とする一つ上のlevelのClusterにする
+    ///
+    /// 根はそのまま同じ番号に残し、兄弟はSkeletonとして置く
+    fn wrapped(mut self, slot: usize) -> Self {
+        let level = self.level() + 1;
+        let mut skeletons = [self.to_skeleton(); 8];
+        for i in slot..slot + 7 {
+            let (from_anchor, to_anchor) = EDGE_CHAIN[i % 8];
+            skeletons[(i + 1) % 8] = skeletons[i % 8].connected_skeleton(from_anchor, to_anchor);
         }
 
-        // チェーン順から [a, b, c, d, e, f, g, h] 順に並べ替え
-        let mut children: [Option<SpectreLike>; 8] = Default::default();
-        for (i, child) in chain.into_iter().enumerate() {
-            children[(start_idx + i) % 8] = Some(child);
+        let child = self.root;
+        let mut children = [NONE; 8];
+        let mut bbox = Aabb::NULL;
+        for (i, skeleton) in skeletons.into_iter().enumerate() {
+            children[i] = if i == slot {
+                child
+            } else {
+                let id = self.alloc();
+                self.replace(id, Node::Skeleton(skeleton, i == 7));
+                id
+            };
+            bbox = bbox.union(&self.nodes[children[i] as usize].bbox());
         }
-        let [a, b, c, d, e, f, g, h] = children.map(|c| c.unwrap());
-        let h = h.into_mystic_like();
-        Self::new(a, b, c, d, e, f, g, h, level)
+        self.root = self.alloc();
+        self.replace(
+            self.root,
+            Node::Cluster(ClusterNode {
+                children,
+                level,
+                is_mystic: false,
+                bbox,
+                last_visible: 0,
+            }),
+        );
+        self
     }
 
-    pub fn with_child_a(a: SpectreCluster) -> Self {
-        let level = a.level() + 1;
-        let a_skeleton = SpectreLike::from(a.to_skeleton());
-        let a = SpectreLike::from(a);
-        let b = a_skeleton.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let c = b.connected_spectre_like(Anchor::Anchor4, Anchor::Anchor2);
-        let d = c.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let e = d.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let f = e.connected_spectre_like(Anchor::Anchor4, Anchor::Anchor2);
-        let g = f.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let h = g.connected_spectre_like(Anchor::Anchor4, Anchor::Anchor4);
-        let h = h.into_mystic_like();
-        Self::new(a, b, c, d, e, f, g, h, level)
+    /// offsetだけ平行移動したCluster
+    pub fn translated(&self, offset: HexVec) -> Self {
+        let mut cluster = Self::empty();
+        let is_mystic =
+            matches!(&self.nodes[self.root as usize], Node::Cluster(root) if root.is_mystic);
+        cluster.copy_translated(cluster.root, self, self.root, offset, is_mystic);
+        cluster
     }
 
-    pub fn with_child_f(f: SpectreCluster) -> Self {
-        let level = f.level() + 1;
-        let f_skeleton = SpectreLike::from(f.to_skeleton());
-        let f = SpectreLike::from(f);
-        let g = f_skeleton.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let h = g.connected_spectre_like(Anchor::Anchor4, Anchor::Anchor4);
-        let a = h.connected_spectre_like(Anchor::Anchor1, Anchor::Anchor1);
-        let b = a.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let c = b.connected_spectre_like(Anchor::Anchor4, Anchor::Anchor2);
-        let d = c.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let e = d.connected_spectre_like(Anchor::Anchor3, Anchor::Anchor1);
-        let h = h.into_mystic_like();
-        Self::new(a, b, c, d, e, f, g, h, level)
-    }
-
-    pub fn connected_cluster(&self, from_anchor: Anchor, to_anchor: Anchor) -> SpectreCluster {
-        // 新しいSpectreの角度を計算
-        // levelによって頂点を合わせる場合に接合する辺の選びかたが変わる
-        let angle = if self.level % 2 == 1 {
-            // 奇数番目のlevelでは新しいClusterを辺が密着するまで時計回りに回転させる
-            self.edge_direction_into(from_anchor).opposite()
+    pub fn to_skeleton(&self) -> Skeleton {
+        self.skeleton_of(self.root)
+    }
+
+    /// loadと交差する子をロードし、unloadと交差しない子をSkeletonに戻す
+    ///
+    /// unloadはloadを含む広い範囲にする。その差の部分にある子はそのまま残すので、
+    /// 境界付近を往復しても同じClusterを作り直さずに済む
+    pub fn update<R: Region>(&mut self, load: &R, unload: &R, frame: u64) {
+        self.update_cluster(self.root, load, unload, frame);
+    }
+
+    /// bboxと交差するタイルの数を数える
+    ///
+    /// bboxに完全に含まれる子はタイルを列挙せずにlevelから数を求める。
+    /// u64に収まらない数はu64::MAXで頭打ちにする
+    pub fn count_in<R: Region>(&self, region: &R) -> u64 {
+        self.count_in_node(self.root, region)
+    }
+
+    pub fn spectres_in<R: Region>(&self, region: R) -> SpectreIter<'_, R> {
+        SpectreIter::new(self, region)
+    }
+
+    /// 読み込まれているタイルの数と、使用中のノードのメモリ量
+    ///
+    /// 木を辿らずに、ロード・アンロードのたびに更新している数から求める
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let nodes = self.nodes.len() - self.free.len();
+        MemoryUsage::new(self.tiles, nodes * size_of::<Node>())
+    }
+
+    /// 最後に表示されたのがframeより前の子孫のうち、最も上位のものを集める
+    ///
+    /// 要素は(最後に表示されたフレーム番号, Skeletonに戻すと解放されるバイト数)。
+    /// loadと交差するClusterは含めない
+    pub fn collect_stale<R: Region>(&self, frame: u64, load: &R, out: &mut Vec<(u64, usize)>) {
+        self.collect_stale_in(self.root, frame, load, out);
+    }
+
+    /// 最後に表示されたのがthreshold以前の子孫をSkeletonに戻す
+    pub fn evict<R: Region>(&mut self, threshold: u64, load: &R) {
+        self.evict_in(self.root, threshold, load);
+    }
+
+    pub fn last_visible(&self) -> u64 {
+        match &self.nodes[self.root as usize] {
+            Node::Cluster(cluster) => cluster.last_visible,
+            _ => 0,
+        }
+    }
+
+    pub fn coordinate(&self, anchor: Anchor) -> HexVec {
+        self.coordinate_of(self.root, anchor)
+    }
+
+    pub fn edge_direction_from(&self, anchor: Anchor) -> Angle {
+        self.edge_direction_from_of(self.root, anchor)
+    }
+
+    pub fn edge_direction_into(&self, anchor: Anchor) -> Angle {
+        self.edge_direction_into_of(self.root, anchor)
+    }
+
+    pub fn bbox(&self) -> Aabb {
+        self.nodes[self.root as usize].bbox()
+    }
+
+    pub fn level(&self) -> usize {
+        self.nodes[self.root as usize].level()
+    }
+}
+
+impl SpectreCluster {
+    /// 根だけを確保した空の木
+    fn empty() -> Self {
+        Self {
+            nodes: vec![Node::Free],
+            free: vec![],
+            root: 0,
+            tiles: 0,
+        }
+    }
+
+    fn alloc(&mut self) -> NodeId {
+        if let Some(id) = self.free.pop() {
+            return id;
+        }
+        self.nodes.push(Node::Free);
+        (self.nodes.len() - 1) as NodeId
+    }
+
+    /// idのノードを置き換え、読み込まれているタイルの数を更新する
+    fn replace(&mut self, id: NodeId, node: Node) -> Node {
+        self.tiles += node.tiles();
+        let old = std::mem::replace(&mut self.nodes[id as usize], node);
+        self.tiles -= old.tiles();
+        old
+    }
+
+    /// idの子孫を全て解放する（id自身は残す）
+    fn free_descendants(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let node = self.replace(current, Node::Free);
+            if let Node::Cluster(cluster) = node {
+                stack.extend(cluster.children.into_iter().filter(|&child| child != NONE));
+            }
+            if current != id {
+                self.free.push(current);
+            }
+        }
+    }
+
+    /// idにskeletonの表すClusterを置き、各子をchildで作る
+    fn insert_cluster(
+        &mut self,
+        id: NodeId,
+        skeleton: Skeleton,
+        is_mystic: bool,
+        mut child: impl FnMut(&mut Self, NodeId, Skeleton, bool),
+    ) {
+        let skeletons = skeleton.split_into_skeletons();
+        // 兄弟を連続した番号に確保してから、それぞれの部分木を作る
+        let children: [NodeId; 8] = std::array::from_fn(|i| {
+            if is_mystic && i == 4 {
+                NONE
+            } else {
+                self.alloc()
+            }
+        });
+        let mut bbox = Aabb::NULL;
+        for (i, &id) in children.iter().enumerate() {
+            if id != NONE {
+                child(self, id, skeletons[i], i == 7);
+                bbox = bbox.union(&self.nodes[id as usize].bbox());
+            }
+        }
+        self.replace(
+            id,
+            Node::Cluster(ClusterNode {
+                children,
+                level: skeleton.level(),
+                is_mystic,
+                bbox,
+                last_visible: 0,
+            }),
+        );
+    }
+
+    /// idにlevel 0のskeletonの表すタイルを置く
+    fn insert_tile(&mut self, id: NodeId, skeleton: Skeleton, is_mystic: bool) {
+        let spectre = Spectre::with_anchor(
+            Anchor::Anchor1,
+            skeleton.coordinate(Anchor::Anchor1),
+            skeleton.edge_direction_from(Anchor::Anchor1),
+        );
+        let node = if is_mystic {
+            Node::Mystic(spectre.into_mystic())
+        } else {
+            Node::Spectre(spectre)
+        };
+        self.replace(id, node);
+    }
+
+    /// idにskeletonの表すClusterを、regionと交差する子だけロードして置く
+    fn load<R: Region>(&mut self, id: NodeId, skeleton: Skeleton, is_mystic: bool, region: &R) {
+        if skeleton.level() < MIN_PARTIAL_CLUSTER_LEVEL {
+            self.load_all(id, skeleton, is_mystic);
+            return;
+        }
+        self.insert_cluster(
+            id,
+            skeleton,
+            is_mystic,
+            |cluster, id, skeleton, is_mystic| {
+                if skeleton.has_intersection(region, is_mystic) {
+                    cluster.load(id, skeleton, is_mystic, region);
+                } else {
+                    cluster.replace(id, Node::Skeleton(skeleton, is_mystic));
+                }
+            },
+        );
+    }
+
+    /// idにskeletonの表すClusterを全てロードして置く
+    fn load_all(&mut self, id: NodeId, skeleton: Skeleton, is_mystic: bool) {
+        match skeleton.level() {
+            0 => self.insert_tile(id, skeleton, is_mystic),
+            level if level < MIN_PARTIAL_CLUSTER_LEVEL => {
+                // テンプレートはanchor1を原点に置いている
+                let template =
+                    cluster_template(level, skeleton.edge_direction_from(Anchor::Anchor1));
+                let offset = skeleton.coordinate(Anchor::Anchor1);
+                self.copy_translated(id, &template, template.root, offset, is_mystic);
+            }
+            _ => self.insert_cluster(id, skeleton, is_mystic, Self::load_all),
+        }
+    }
+
+    /// テンプレートを使わずにload_allする
+    fn build_all(&mut self, id: NodeId, skeleton: Skeleton, is_mystic: bool) {
+        if skeleton.level() == 0 {
+            self.insert_tile(id, skeleton, is_mystic);
         } else {
-            // 偶数番目のlevelでは反時計回りに回転させる
-            let rotation = self.edge_direction_from(to_anchor)
-                - self.edge_direction_into(to_anchor).opposite();
-            self.edge_direction_from(from_anchor) + rotation
+            self.insert_cluster(id, skeleton, is_mystic, Self::build_all);
+        }
+    }
+
+    /// sourceのsource_id以下をoffsetだけ平行移動してidに写す
+    fn copy_translated(
+        &mut self,
+        id: NodeId,
+        source: &SpectreCluster,
+        source_id: NodeId,
+        offset: HexVec,
+        is_mystic: bool,
+    ) {
+        let node = match &source.nodes[source_id as usize] {
+            Node::Spectre(spectre) => Node::Spectre(spectre.translated(offset)),
+            Node::Mystic(mystic) => Node::Mystic(mystic.translated(offset)),
+            Node::Skeleton(skeleton, _) => Node::Skeleton(skeleton.translated(offset), is_mystic),
+            Node::Free => Node::Free,
+            Node::Cluster(cluster) => {
+                let children: [NodeId; 8] = std::array::from_fn(|i| {
+                    if cluster.children[i] == NONE || (is_mystic && i == 4) {
+                        NONE
+                    } else {
+                        self.alloc()
+                    }
+                });
+                let mut bbox = Aabb::NULL;
+                for (i, &child) in children.iter().enumerate() {
+                    if child != NONE {
+                        self.copy_translated(child, source, cluster.children[i], offset, i == 7);
+                        bbox = bbox.union(&self.nodes[child as usize].bbox());
+                    }
+                }
+                Node::Cluster(ClusterNode {
+                    children,
+                    is_mystic,
+                    bbox,
+                    ..*cluster
+                })
+            }
         };
+        self.replace(id, node);
+    }
 
-        SpectreCluster::with_anchor(to_anchor, self.coordinate(from_anchor), angle, self.level)
+    fn update_cluster<R: Region>(&mut self, id: NodeId, load: &R, unload: &R, frame: u64) {
+        let Node::Cluster(cluster) = &mut self.nodes[id as usize] else {
+            return;
+        };
+        cluster.last_visible = frame;
+        if cluster.level < MIN_PARTIAL_CLUSTER_LEVEL {
+            return;
+        }
+        let children = cluster.children;
+        let mut bbox = Aabb::NULL;
+        for child in children.into_iter().filter(|&child| child != NONE) {
+            self.update_child(child, load, unload, frame);
+            bbox = bbox.union(&self.nodes[child as usize].bbox());
+        }
+        if let Node::Cluster(cluster) = &mut self.nodes[id as usize] {
+            cluster.bbox = bbox;
+        }
     }
 
-    pub fn into_mystic_cluster(self) -> MysticCluster {
-        MysticCluster::new(
-            self.a, self.b, self.c, self.d, self.f, self.g, self.h, self.level,
-        )
+    fn update_child<R: Region>(&mut self, id: NodeId, load: &R, unload: &R, frame: u64) {
+        match self.nodes[id as usize] {
+            Node::Cluster(cluster) => {
+                if load.intersects_aabb(&cluster.bbox) {
+                    self.update_cluster(id, load, unload, frame);
+                    return;
+                }
+                // unloadの範囲にあるうちは表示されていなくても残しておく
+                if unload.intersects_aabb(&cluster.bbox) {
+                    return;
+                }
+                self.unload(id);
+            }
+            Node::Skeleton(skeleton, is_mystic) => {
+                if !skeleton.has_intersection(load, is_mystic) {
+                    return;
+                }
+                self.load(id, skeleton, is_mystic, load);
+                self.update_cluster(id, load, unload, frame);
+            }
+            _ => {}
+        }
     }
 
-    pub fn to_skeleton(&self) -> Skeleton {
+    /// idの子孫を解放してSkeletonに戻す
+    fn unload(&mut self, id: NodeId) {
+        let Node::Cluster(cluster) = self.nodes[id as usize] else {
+            return;
+        };
+        let skeleton = self.skeleton_of(id);
+        self.free_descendants(id);
+        self.replace(id, Node::Skeleton(skeleton, cluster.is_mystic));
+    }
+
+    fn count_in_node<R: Region>(&self, id: NodeId, region: &R) -> u64 {
+        match &self.nodes[id as usize] {
+            Node::Spectre(spectre) => region.intersects_aabb(&spectre.bbox()) as u64,
+            Node::Mystic(mystic) => {
+                region.intersects_aabb(&mystic.lower().bbox()) as u64
+                    + region.intersects_aabb(&mystic.upper().bbox()) as u64
+            }
+            Node::Cluster(cluster) => {
+                if !region.intersects_aabb(&cluster.bbox) {
+                    return 0;
+                }
+                if region.contains_aabb(&cluster.bbox) {
+                    return tile_count(cluster.level, cluster.is_mystic).unwrap_or(u64::MAX);
+                }
+                cluster
+                    .children
+                    .iter()
+                    .filter(|&&child| child != NONE)
+                    .map(|&child| self.count_in_node(child, region))
+                    .fold(0, u64::saturating_add)
+            }
+            Node::Skeleton(skeleton, is_mystic) => skeleton.count_in(region, *is_mystic),
+            Node::Free => 0,
+        }
+    }
+
+    /// idの部分木で読み込まれているタイルの数と、使用中のノードのメモリ量
+    fn memory_usage_of(&self, id: NodeId) -> MemoryUsage {
+        let node = &self.nodes[id as usize];
+        let usage = MemoryUsage::new(node.tiles(), size_of::<Node>());
+        match node {
+            Node::Cluster(cluster) => cluster
+                .children
+                .iter()
+                .filter(|&&child| child != NONE)
+                .fold(usage, |usage, &child| usage + self.memory_usage_of(child)),
+            _ => usage,
+        }
+    }
+
+    fn collect_stale_in<R: Region>(
+        &self,
+        id: NodeId,
+        frame: u64,
+        load: &R,
+        out: &mut Vec<(u64, usize)>,
+    ) {
+        let Node::Cluster(cluster) = &self.nodes[id as usize] else {
+            return;
+        };
+        // updateはこのlevelより下を辿らないので、子のlast_visibleは更新されない
+        if cluster.level < MIN_PARTIAL_CLUSTER_LEVEL {
+            return;
+        }
+        for &child in cluster.children.iter().filter(|&&child| child != NONE) {
+            let Node::Cluster(child_cluster) = &self.nodes[child as usize] else {
+                continue;
+            };
+            if child_cluster.last_visible < frame && !load.intersects_aabb(&child_cluster.bbox) {
+                // 子自身のノードはSkeletonとして残る
+                let bytes = self.memory_usage_of(child).bytes - size_of::<Node>();
+                out.push((child_cluster.last_visible, bytes));
+            } else {
+                self.collect_stale_in(child, frame, load, out);
+            }
+        }
+    }
+
+    fn evict_in<R: Region>(&mut self, id: NodeId, threshold: u64, load: &R) {
+        let Node::Cluster(cluster) = self.nodes[id as usize] else {
+            return;
+        };
+        if cluster.level < MIN_PARTIAL_CLUSTER_LEVEL {
+            return;
+        }
+        for child in cluster.children.into_iter().filter(|&child| child != NONE) {
+            let Node::Cluster(child_cluster) = &self.nodes[child as usize] else {
+                continue;
+            };
+            // loadと交差するClusterは古くても戻さない
+            if child_cluster.last_visible <= threshold && !load.intersects_aabb(&child_cluster.bbox)
+            {
+                self.unload(child);
+            } else {
+                self.evict_in(child, threshold, load);
+            }
+        }
+    }
+
+    fn skeleton_of(&self, id: NodeId) -> Skeleton {
+        if let Node::Skeleton(skeleton, _) = self.nodes[id as usize] {
+            return skeleton;
+        }
         Skeleton::with_anchor(
             Anchor::Anchor1,
-            self.coordinate(Anchor::Anchor1),
-            self.edge_direction_from(Anchor::Anchor1),
-            self.level,
-            Some(self.bbox()),
+            self.coordinate_of(id, Anchor::Anchor1),
+            self.edge_direction_from_of(id, Anchor::Anchor1),
+            self.nodes[id as usize].level(),
         )
     }
 
-    pub fn update(&mut self, bbox: &Aabb) {
-        if self.level < MIN_PARTIAL_CLUSTER_LEVEL {
-            return;
+    fn coordinate_of(&self, id: NodeId, anchor: Anchor) -> HexVec {
+        match &self.nodes[id as usize] {
+            Node::Spectre(spectre) => spectre.coordinate(anchor),
+            Node::Mystic(mystic) => mystic.coordinate(anchor),
+            Node::Skeleton(skeleton, _) => skeleton.coordinate(anchor),
+            Node::Cluster(cluster) => {
+                let (index, child_anchor) = anchor_child(anchor);
+                self.coordinate_of(cluster.children[index], child_anchor)
+            }
+            Node::Free => unreachable!("freed node has no coordinate"),
         }
-        self.a.update(bbox);
-        self.b.update(bbox);
-        self.c.update(bbox);
-        self.d.update(bbox);
-        self.e.update(bbox);
-        self.f.update(bbox);
-        self.g.update(bbox);
-        self.h.update(bbox);
-        let mut bbox = Aabb::NULL;
-        bbox = bbox.union(&self.a.bbox());
-        bbox = bbox.union(&self.b.bbox());
-        bbox = bbox.union(&self.c.bbox());
-        bbox = bbox.union(&self.d.bbox());
-        bbox = bbox.union(&self.e.bbox());
-        bbox = bbox.union(&self.f.bbox());
-        bbox = bbox.union(&self.g.bbox());
-        bbox = bbox.union(&self.h.bbox());
-        self.bbox = bbox;
     }
 
-    pub fn spectres_in(&self, bbox: Aabb) -> SpectreIter<'_> {
-        SpectreIter::new(self, bbox)
+    fn edge_direction_from_of(&self, id: NodeId, anchor: Anchor) -> Angle {
+        match &self.nodes[id as usize] {
+            Node::Spectre(spectre) => spectre.edge_direction_from(anchor),
+            Node::Mystic(mystic) => mystic.edge_direction_from(anchor),
+            Node::Skeleton(skeleton, _) => skeleton.edge_direction_from(anchor),
+            Node::Cluster(cluster) => {
+                let (index, child_anchor) = anchor_child(anchor);
+                self.edge_direction_from_of(cluster.children[index], child_anchor)
+            }
+            Node::Free => unreachable!("freed node has no edge direction"),
+        }
     }
 
-    pub fn coordinate(&self, anchor: Anchor) -> HexVec {
-        match anchor {
-            Anchor::Anchor1 => self.g.coordinate(Anchor::Anchor3),
-            Anchor::Anchor2 => self.d.coordinate(Anchor::Anchor2),
-            Anchor::Anchor3 => self.b.coordinate(Anchor::Anchor3),
-            Anchor::Anchor4 => self.a.coordinate(Anchor::Anchor2),
+    fn edge_direction_into_of(&self, id: NodeId, anchor: Anchor) -> Angle {
+        match &self.nodes[id as usize] {
+            Node::Spectre(spectre) => spectre.edge_direction_into(anchor),
+            Node::Mystic(mystic) => mystic.edge_direction_into(anchor),
+            Node::Skeleton(skeleton, _) => skeleton.edge_direction_into(anchor),
+            Node::Cluster(cluster) => {
+                let (index, child_anchor) = anchor_child(anchor);
+                self.edge_direction_into_of(cluster.children[index], child_anchor)
+            }
+            Node::Free => unreachable!("freed node has no edge direction"),
         }
     }
+}
 
-    pub fn edge_direction_from(&self, anchor: Anchor) -> Angle {
-        match anchor {
-            Anchor::Anchor1 => self.g.edge_direction_from(Anchor::Anchor3),
-            Anchor::Anchor2 => self.d.edge_direction_from(Anchor::Anchor2),
-            Anchor::Anchor3 => self.b.edge_direction_from(Anchor::Anchor3),
-            Anchor::Anchor4 => self.a.edge_direction_from(Anchor::Anchor2),
+/// Clusterのアンカーを決める子の番号とそのアンカー
+fn anchor_child(anchor: Anchor) -> (usize, Anchor) {
+    match anchor {
+        Anchor::Anchor1 => (6, Anchor::Anchor3),
+        Anchor::Anchor2 => (3, Anchor::Anchor2),
+        Anchor::Anchor3 => (1, Anchor::Anchor3),
+        Anchor::Anchor4 => (0, Anchor::Anchor2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{Circle, HalfPlane};
+    use glam::Vec2;
+
+    #[test]
+    fn test_count_in_matches_enumeration() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 4);
+        let bboxes = [
+            cluster.bbox(),
+            Aabb::new(-10.0, -10.0, 10.0, 10.0),
+            Aabb::new(-30.0, -5.0, 40.0, 25.0),
+            Aabb::new(1000.0, 1000.0, 1010.0, 1010.0),
+        ];
+        for bbox in bboxes {
+            assert_eq!(
+                cluster.count_in(&bbox),
+                cluster.spectres_in(bbox).count() as u64,
+                "count mismatch for {:?}",
+                bbox
+            );
         }
     }
 
-    pub fn edge_direction_into(&self, anchor: Anchor) -> Angle {
-        match anchor {
-            Anchor::Anchor1 => self.g.edge_direction_into(Anchor::Anchor3),
-            Anchor::Anchor2 => self.d.edge_direction_into(Anchor::Anchor2),
-            Anchor::Anchor3 => self.b.edge_direction_into(Anchor::Anchor3),
-            Anchor::Anchor4 => self.a.edge_direction_into(Anchor::Anchor2),
+    #[test]
+    fn test_spectres_in_region() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 4);
+        let circle = Circle::new(Vec2::new(5.0, 5.0), 20.0);
+        let in_circle = cluster.spectres_in(circle).count();
+        let in_bbox = cluster.spectres_in(circle.bbox()).count();
+        assert!(0 < in_circle && in_circle < in_bbox);
+        assert_eq!(cluster.count_in(&circle), in_circle as u64);
+    }
+
+    #[test]
+    fn test_spectres_in_half_plane() {
+        // 有界でない範囲でも、bboxを使わずに交差するタイルだけを辿る
+        let mut cluster =
+            SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 4);
+        let bbox = cluster.bbox();
+        let half_plane = HalfPlane::new((bbox.min + bbox.max) * 0.5, Vec2::new(1.0, 2.0));
+        let expected = cluster
+            .spectres_in(bbox)
+            .filter(|spectre| half_plane.intersects_aabb(&spectre.bbox()))
+            .count();
+        let in_half_plane = cluster.spectres_in(half_plane).count();
+        assert!(0 < in_half_plane && in_half_plane < cluster.spectres_in(bbox).count());
+        assert_eq!(in_half_plane, expected);
+        assert_eq!(cluster.count_in(&half_plane), expected as u64);
+
+        // 半平面でロード・アンロードしても、内側のタイルは残る
+        cluster.update(&half_plane, &half_plane.expanded(10.0), 1);
+        assert_eq!(cluster.spectres_in(half_plane).count(), expected);
+    }
+
+    #[test]
+    fn test_count_in_skeleton() {
+        // 一部だけロードされたClusterでも全てロードした場合と同じ数になる
+        let full = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 5);
+        let partial = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 5)
+            .to_spectre_cluster(&Aabb::new(-5.0, -5.0, 5.0, 5.0));
+        let bbox = Aabb::new(-60.0, -40.0, 50.0, 70.0);
+        assert_eq!(
+            partial.count_in(&bbox),
+            full.spectres_in(bbox).count() as u64
+        );
+    }
+
+    #[test]
+    fn test_address_outside_iteration() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let mut iter = cluster.spectres_in(cluster.bbox());
+        // nextを呼ぶ前と、列挙し終えた後は位置を持たない
+        assert_eq!(iter.address(), None);
+        while iter.next().is_some() {
+            assert_eq!(iter.address().unwrap().slots().len(), 2);
         }
+        assert_eq!(iter.address(), None);
     }
 
-    pub fn bbox(&self) -> Aabb {
-        self.bbox
+    #[test]
+    fn test_memory_usage() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 4);
+        let usage = cluster.memory_usage();
+        assert_eq!(usage.tiles, tile_count(4, false).unwrap());
+        assert!(usage.bytes > 0);
     }
 
-    pub fn level(&self) -> usize {
-        self.level
+    impl SpectreCluster {
+        fn child(&self, slot: usize) -> NodeId {
+            let Node::Cluster(cluster) = &self.nodes[self.root as usize] else {
+                panic!("root is not loaded");
+            };
+            cluster.children[slot]
+        }
+    }
+
+    #[test]
+    fn test_evict_stale_clusters() {
+        let mut cluster =
+            SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 5);
+        // 一部だけ表示範囲に入れたClusterは、表示されなかった子だけが古くなる
+        let bbox = Aabb::new(-5.0, -5.0, 5.0, 5.0);
+        let a = cluster.child(0);
+        cluster.update_cluster(a, &bbox, &bbox, 1);
+        let mut stale = vec![];
+        cluster.collect_stale(1, &Aabb::NULL, &mut stale);
+        assert_eq!(stale.len(), 7);
+        assert!(stale.iter().all(|&(last_visible, _)| last_visible == 0));
+
+        let before = cluster.memory_usage();
+        cluster.evict(0, &Aabb::NULL);
+        let after = cluster.memory_usage();
+        let freed: usize = stale.iter().map(|&(_, bytes)| bytes).sum();
+        assert_eq!(before.bytes - after.bytes, freed);
+        assert_eq!(after, cluster.memory_usage_of(cluster.root));
+        assert_eq!(after.tiles, cluster.memory_usage_of(a).tiles);
+        assert_eq!(
+            cluster.count_in(&cluster.bbox()),
+            tile_count(5, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_update_reuses_nodes() {
+        let mut cluster = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 8)
+            .to_spectre_cluster(&Aabb::NULL);
+        // 往復しながらパンしても、解放した番号を再利用するので配列は伸び続けない
+        let mut sizes = vec![];
+        for (frame, x) in [0.0, 150.0, 300.0, 150.0, 0.0, 150.0, 300.0]
+            .into_iter()
+            .enumerate()
+        {
+            let load = Aabb::new(x - 20.0, -20.0, x + 20.0, 20.0);
+            cluster.update(&load, &load.expanded(100.0), frame as u64);
+            sizes.push(cluster.nodes.len());
+        }
+        assert_eq!(sizes[2], sizes[6]);
+        // 解放し忘れたノードがなければ、数えているメモリ量は木を辿った結果と一致する
+        assert_eq!(
+            cluster.memory_usage(),
+            cluster.memory_usage_of(cluster.root)
+        );
+    }
+
+    #[test]
+    fn test_update_hysteresis() {
+        let near = Aabb::new(-5.0, -5.0, 5.0, 5.0);
+        let far = Aabb::new(500.0, 500.0, 510.0, 510.0);
+        let mut cluster = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 7)
+            .to_spectre_cluster(&near);
+        let loaded = cluster.memory_usage().tiles;
+
+        // unloadの範囲にまだnearが入っているので、nearのタイルは残る
+        cluster.update(&far, &far.expanded(1000.0), 1);
+        assert!(cluster.memory_usage().tiles > loaded);
+
+        // unloadの範囲から外れるとSkeletonに戻る
+        cluster.update(&far, &far.expanded(10.0), 2);
+        let only_far = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 7)
+            .to_spectre_cluster(&far);
+        assert_eq!(cluster.memory_usage().tiles, only_far.memory_usage().tiles);
     }
 }
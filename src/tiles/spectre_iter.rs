@@ -1,166 +1,92 @@
-use crate::utils::Aabb;
+use crate::utils::{Aabb, Region};
 
-use super::{Mystic, MysticCluster, MysticLike, Spectre, SpectreCluster, SpectreLike};
+use super::{Node, NodeId, Spectre, SpectreCluster, TileAddress, NONE};
 
 #[derive(Clone)]
-enum Node<'a> {
-    SpectreCluster(&'a SpectreCluster),
-    MysticCluster(&'a MysticCluster),
-    Spectre(&'a Spectre),
-    Mystic(&'a Mystic),
+pub struct SpectreIter<'a, R: Region = Aabb> {
+    cluster: &'a SpectreCluster,
+    /// 辿っている親と、次に調べる子のインデックス
+    parents: Vec<(NodeId, usize)>,
+    region: R,
 }
 
-impl<'a> Node<'a> {
-    fn get_child(&self, index: usize) -> Option<Node<'a>> {
-        match self {
-            Node::SpectreCluster(cluster) => match index {
-                0 => match &*cluster.a {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                1 => match &*cluster.b {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                2 => match &*cluster.c {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                3 => match &*cluster.d {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                4 => match &*cluster.e {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                5 => match &*cluster.f {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                6 => match &*cluster.g {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                7 => match &*cluster.h {
-                    MysticLike::Mystic(mystic) => Some(Node::Mystic(mystic)),
-                    MysticLike::Cluster(cluster) => Some(Node::MysticCluster(cluster)),
-                    MysticLike::Skeleton(_) => None,
-                },
-                _ => None,
-            },
-            Node::MysticCluster(cluster) => match index {
-                0 => match &*cluster.a {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                1 => match &*cluster.b {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                2 => match &*cluster.c {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                3 => match &*cluster.d {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                4 => match &*cluster.f {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                5 => match &*cluster.g {
-                    SpectreLike::Spectre(spectre) => Some(Node::Spectre(spectre)),
-                    SpectreLike::Cluster(cluster) => Some(Node::SpectreCluster(cluster)),
-                    SpectreLike::Skeleton(_) => None,
-                },
-                6 => match &*cluster.h {
-                    MysticLike::Mystic(mystic) => Some(Node::Mystic(mystic)),
-                    MysticLike::Cluster(cluster) => Some(Node::MysticCluster(cluster)),
-                    MysticLike::Skeleton(_) => None,
-                },
-                _ => None,
-            },
-            Node::Spectre(_) => None,
-            Node::Mystic(mystic) => match index {
-                0 => Some(Node::Spectre(mystic.lower())),
-                1 => Some(Node::Spectre(mystic.upper())),
-                _ => None,
-            },
+impl<'a, R: Region> SpectreIter<'a, R> {
+    pub fn new(root: &'a SpectreCluster, region: R) -> SpectreIter<'a, R> {
+        SpectreIter {
+            cluster: root,
+            parents: vec![(root.root, 0)],
+            region,
         }
     }
 
-    fn num_children(&self) -> usize {
-        match self {
-            Node::SpectreCluster(_) => 8,
-            Node::MysticCluster(_) => 7,
-            Node::Spectre(_) => 0,
-            Node::Mystic(_) => 2,
-        }
+    /// 直前に返したSpectreがMysticの一部かどうか
+    pub fn in_mystic(&self) -> bool {
+        matches!(
+            self.parents.last(),
+            Some(&(id, _)) if matches!(self.cluster.nodes[id as usize], Node::Mystic(_))
+        )
     }
 
-    fn bbox(&self) -> Aabb {
-        match self {
-            Node::SpectreCluster(cluster) => cluster.bbox(),
-            Node::MysticCluster(cluster) => cluster.bbox(),
-            Node::Spectre(spectre) => spectre.bbox(),
-            Node::Mystic(mystic) => mystic.bbox(),
+    /// 直前に返したSpectreの、根のClusterからの位置
+    ///
+    /// まだnextを呼んでいないときと、最後まで列挙し終えたときはNone
+    pub fn address(&self) -> Option<TileAddress> {
+        if self.parents.is_empty() || self.parents.iter().any(|&(_, index)| index == 0) {
+            return None;
         }
-    }
-}
-
-impl<'a> From<&'a SpectreCluster> for Node<'a> {
-    fn from(cluster: &'a SpectreCluster) -> Self {
-        Node::SpectreCluster(cluster)
-    }
-}
-
-#[derive(Clone)]
-pub struct SpectreIter<'a> {
-    parents: Vec<(Node<'a>, usize)>,
-    bbox: Aabb,
-}
-
-impl<'a> SpectreIter<'a> {
-    pub fn new(root: &'a SpectreCluster, bbox: Aabb) -> SpectreIter<'a> {
-        SpectreIter {
-            parents: vec![(root.into(), 0)],
-            bbox,
+        let mut slots = Vec::with_capacity(self.parents.len());
+        let mut mystic_part = None;
+        // 各親には直前に辿った子の次のインデックスが積まれている
+        for &(id, index) in &self.parents {
+            let child = (index - 1) as u8;
+            match self.cluster.nodes[id as usize] {
+                // MysticClusterもeの位置を空けているので、インデックスがそのまま位置になる
+                Node::Cluster(_) => slots.push(child),
+                Node::Mystic(_) => mystic_part = Some(child),
+                _ => {}
+            }
         }
+        Some(TileAddress::new(slots, mystic_part))
     }
 }
 
-impl<'a> Iterator for SpectreIter<'a> {
+impl<'a, R: Region> Iterator for SpectreIter<'a, R> {
     type Item = &'a Spectre;
 
     fn next(&mut self) -> Option<Self::Item> {
-        'outer: while let Some((parent, index)) = self.parents.pop() {
-            for i in index..parent.num_children() {
-                if let Some(child) = parent.get_child(i)
-                    && child.bbox().has_intersection(&self.bbox)
-                {
-                    if let Node::Spectre(spectre) = child {
-                        self.parents.push((parent, i + 1));
-                        return Some(spectre);
-                    } else {
-                        self.parents.push((parent, i + 1));
+        let nodes = &self.cluster.nodes;
+        'outer: while let Some((id, index)) = self.parents.pop() {
+            match &nodes[id as usize] {
+                Node::Cluster(cluster) => {
+                    for i in index..cluster.children.len() {
+                        let child = cluster.children[i];
+                        if child == NONE {
+                            continue;
+                        }
+                        let node = &nodes[child as usize];
+                        if matches!(node, Node::Skeleton(..))
+                            || !self.region.intersects_aabb(&node.bbox())
+                        {
+                            continue;
+                        }
+                        self.parents.push((id, i + 1));
+                        if let Node::Spectre(spectre) = node {
+                            return Some(spectre);
+                        }
                         self.parents.push((child, 0));
                         continue 'outer;
                     }
                 }
+                Node::Mystic(mystic) => {
+                    let halves = [mystic.lower(), mystic.upper()];
+                    for (i, spectre) in halves.into_iter().enumerate().skip(index) {
+                        if self.region.intersects_aabb(&spectre.bbox()) {
+                            self.parents.push((id, i + 1));
+                            return Some(spectre);
+                        }
+                    }
+                }
+                _ => {}
             }
         }
         None
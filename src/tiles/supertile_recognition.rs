@@ -0,0 +1,714 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use crate::utils::{Aabb, Angle, HexVec, Orientation};
+
+use super::{Anchor, Skeleton, Spectre, TileAddress};
+
+/// 上位のlevelをいくつまで探すか
+const MAX_LEVEL: usize = 16;
+
+/// タイルの位置と向き
+type TileKey = (HexVec, Orientation);
+
+/// 復元された上位タイル
+#[derive(Debug, Clone)]
+pub struct Supertile {
+    pub skeleton: Skeleton,
+    /// MysticClusterかどうか。eにあたるタイルがパッチになければ決まらない
+    pub is_mystic: Option<bool>,
+    /// 含まれるタイル（入力のインデックス）
+    pub tiles: Vec<usize>,
+}
+
+/// パッチが正しいSpectreのタイリングの一部でない理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecognitionError {
+    /// 同じ位置と向きのタイルが重複している
+    DuplicateTile(usize),
+    /// 鏡映されたタイルは扱えない（パッチ全体を鏡映してから渡す）
+    ReflectedTile(usize),
+    /// どの上位タイルに入れても周りのタイルと重なる
+    NoConsistentParent { level: usize, tile: usize },
+}
+
+impl std::fmt::Display for RecognitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecognitionError::DuplicateTile(tile) => write!(f, "tile {} is duplicated", tile),
+            RecognitionError::ReflectedTile(tile) => write!(f, "tile {} is reflected", tile),
+            RecognitionError::NoConsistentParent { level, tile } => write!(
+                f,
+                "no level {} supertile containing tile {} fits the patch",
+                level, tile
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecognitionError {}
+
+/// タイルの集合から復元した上位タイルの階層
+///
+/// Skeleton::to_spectre_clusterの逆で、各タイルがどのlevelのClusterのどの位置にあるかを求める。
+/// 下のlevelから、パッチと矛盾しない上位タイルが一つに決まるものだけをまとめていくので、
+/// パッチの端のように位置が決まらないところではそこで復元を止める。
+pub struct SupertileRecognition {
+    tiles: Vec<Spectre>,
+    addresses: Vec<TileAddress>,
+    tile_roots: Vec<usize>,
+    roots: Vec<Supertile>,
+}
+
+impl SupertileRecognition {
+    /// 重なりなく並んだタイルから上位タイルを復元する
+    pub fn recognize(tiles: Vec<Spectre>) -> Result<Self, RecognitionError> {
+        let patch = Patch::new(&tiles)?;
+
+        // level 0ではタイルそのもの。Mysticの上側かもしれないタイルは下側の位置も候補にする
+        let mut units: Vec<Unit> = tiles
+            .iter()
+            .enumerate()
+            .map(|(i, &tile)| Unit {
+                roles: vec![
+                    Role::new(Skeleton::from(tile), 0..8, Some(0)),
+                    Role::new(Skeleton::from(mystic_lower_of(tile)), 7..8, Some(1)),
+                ],
+                supertile: Supertile {
+                    skeleton: Skeleton::from(tile),
+                    is_mystic: None,
+                    tiles: vec![i],
+                },
+            })
+            .collect();
+        // 下のlevelから積んだ各タイルの位置（mystic_partは最初だけ）
+        let mut slots: Vec<Vec<u8>> = vec![vec![]; tiles.len()];
+        let mut mystic_parts: Vec<Option<u8>> = vec![None; tiles.len()];
+        let mut roots = vec![];
+        // eの位置に入る上位タイルが一つに決まらず、一つ上のlevelで決めるもの
+        let mut pending: Vec<(Supertile, Vec<usize>)> = vec![];
+
+        for level in 0..MAX_LEVEL {
+            let grouping = patch.group(&units, level)?;
+            // MysticClusterはeを持たないので、hの位置に入らなかった上位タイルのeになる
+            for (supertile, claimants) in pending.drain(..) {
+                let owners: Vec<usize> = claimants
+                    .into_iter()
+                    .filter(|&c| !matches!(grouping.assignments[c], Some((_, 7, _))))
+                    .collect();
+                if let [owner] = owners[..] {
+                    for &tile in &supertile.tiles {
+                        slots[tile].push(4);
+                    }
+                    units[owner].supertile.tiles.extend(supertile.tiles);
+                    units[owner].supertile.is_mystic = Some(false);
+                } else {
+                    roots.push(supertile);
+                }
+            }
+            if grouping.parents.is_empty() {
+                break;
+            }
+
+            let mut next: Vec<Unit> = grouping
+                .parents
+                .into_iter()
+                .map(|(skeleton, is_mystic)| Unit {
+                    roles: vec![Role::new(
+                        skeleton,
+                        match is_mystic {
+                            Some(true) => 7..8,
+                            Some(false) => 0..7,
+                            None => 0..8,
+                        },
+                        None,
+                    )],
+                    supertile: Supertile {
+                        skeleton,
+                        is_mystic,
+                        tiles: vec![],
+                    },
+                })
+                .collect();
+            let mut claimants: HashMap<usize, Vec<usize>> = grouping.pending.into_iter().collect();
+            for (id, (unit, assignment)) in units.into_iter().zip(grouping.assignments).enumerate()
+            {
+                let Some((parent, slot, part)) = assignment else {
+                    match claimants.remove(&id) {
+                        Some(claimants) => pending.push((unit.supertile, claimants)),
+                        // 上位タイルが決まらなかったものはここで止める
+                        None => roots.push(unit.supertile),
+                    }
+                    continue;
+                };
+                for &tile in &unit.supertile.tiles {
+                    slots[tile].push(slot as u8);
+                    if level == 0 {
+                        mystic_parts[tile] = part;
+                    }
+                }
+                next[parent].supertile.tiles.extend(unit.supertile.tiles);
+            }
+            units = next;
+        }
+        roots.extend(units.into_iter().map(|unit| unit.supertile));
+        roots.extend(pending.into_iter().map(|(supertile, _)| supertile));
+
+        let mut tile_roots = vec![0; tiles.len()];
+        for (i, root) in roots.iter().enumerate() {
+            for &tile in &root.tiles {
+                tile_roots[tile] = i;
+            }
+        }
+        let addresses = slots
+            .into_iter()
+            .zip(mystic_parts)
+            .map(|(mut slots, part)| {
+                slots.reverse();
+                TileAddress::new(slots, part)
+            })
+            .collect();
+        Ok(Self {
+            tiles,
+            addresses,
+            tile_roots,
+            roots,
+        })
+    }
+
+    pub fn tiles(&self) -> &[Spectre] {
+        &self.tiles
+    }
+
+    /// 上位タイルが決まらなかったところで止めた、階層の根
+    pub fn roots(&self) -> &[Supertile] {
+        &self.roots
+    }
+
+    /// タイルの根からの位置
+    pub fn address(&self, tile: usize) -> &TileAddress {
+        &self.addresses[tile]
+    }
+
+    /// タイルを含む根
+    pub fn root_of(&self, tile: usize) -> &Supertile {
+        &self.roots[self.tile_roots[tile]]
+    }
+
+    /// 復元できた最も上のlevel
+    pub fn level(&self) -> usize {
+        self.roots
+            .iter()
+            .map(|root| root.skeleton.level())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// 上位タイルのどこに入りうるか
+struct Role {
+    /// このlevelでの形
+    skeleton: Skeleton,
+    /// 入りうる子の位置（0〜7がa〜h）
+    slots: std::ops::Range<usize>,
+    /// Mysticの下側なら0、上側なら1（level 0のみ）
+    mystic_part: Option<u8>,
+}
+
+impl Role {
+    fn new(skeleton: Skeleton, slots: std::ops::Range<usize>, mystic_part: Option<u8>) -> Self {
+        Self {
+            skeleton,
+            slots,
+            mystic_part,
+        }
+    }
+}
+
+/// あるlevelでまとめられたタイル
+struct Unit {
+    roles: Vec<Role>,
+    supertile: Supertile,
+}
+
+/// 上位タイルの候補
+struct Candidate {
+    skeleton: Skeleton,
+    /// eの位置がパッチの他のタイルと重なるか
+    e_conflict: bool,
+    /// e以外の位置に入るunitの(unit, 子の位置, mystic_part)
+    members: Vec<(usize, usize, Option<u8>)>,
+    /// eの位置に入りうるunit
+    e_members: Vec<usize>,
+}
+
+/// あるlevelのunitを一つ上のlevelにまとめた結果
+struct Grouping {
+    /// 上位タイルの(形, MysticClusterかどうか)
+    parents: Vec<(Skeleton, Option<bool>)>,
+    /// 各unitの(上位タイル, 子の位置, mystic_part)
+    assignments: Vec<Option<(usize, usize, Option<u8>)>>,
+    /// 複数の上位タイルのeの位置に入りうるunitと、その上位タイル
+    pending: Vec<(usize, Vec<usize>)>,
+}
+
+/// 入力のタイルを引けるようにしたもの
+struct Patch {
+    bbox: Aabb,
+    tiles: HashMap<TileKey, usize>,
+    /// 反時計回りの有向辺と、それを持つタイル
+    edges: HashMap<(HexVec, HexVec), usize>,
+    /// 各タイルの頂点とbbox
+    shapes: Vec<(Vec<HexVec>, Aabb)>,
+    /// GRID_SIZE四方の区画ごとに、bboxが重なるタイル
+    grid: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Patch {
+    const GRID_SIZE: f32 = 4.0;
+
+    fn new(tiles: &[Spectre]) -> Result<Self, RecognitionError> {
+        let mut patch = Self {
+            bbox: Aabb::NULL,
+            tiles: HashMap::new(),
+            edges: HashMap::new(),
+            shapes: vec![],
+            grid: HashMap::new(),
+        };
+        for (i, tile) in tiles.iter().enumerate() {
+            if tile.is_reflected() {
+                return Err(RecognitionError::ReflectedTile(i));
+            }
+            if patch.tiles.insert(key(tile), i).is_some() {
+                return Err(RecognitionError::DuplicateTile(i));
+            }
+            let vertices = tile.vertices();
+            for edge in edges(&vertices) {
+                patch.edges.insert(edge, i);
+            }
+            patch.shapes.push((vertices, tile.bbox()));
+            patch.bbox = patch.bbox.union(&tile.bbox());
+            for cell in cells(&tile.bbox()) {
+                patch.grid.entry(cell).or_default().push(i);
+            }
+        }
+        Ok(patch)
+    }
+
+    /// levelのunitを一つ上のlevelにまとめる
+    ///
+    /// パッチが正しければ、unitの本当の上位タイルは必ず候補に残る。
+    /// 候補が一つしか残らないunitから決めていき、決めたunitを含む他の候補を除いていく。
+    /// MysticClusterのeの位置には隣のClusterの子がちょうど重なるので、
+    /// eに入るかどうかはe以外の子を決めてから、上位タイルがhに入るかどうかで決める。
+    fn group(&self, units: &[Unit], level: usize) -> Result<Grouping, RecognitionError> {
+        let mut indices: HashMap<Skeleton, Option<usize>> = HashMap::new();
+        let mut candidates: Vec<Candidate> = vec![];
+        // 各unitの(候補, eの位置かどうか)
+        let mut unit_candidates: Vec<Vec<(usize, bool)>> = vec![vec![]; units.len()];
+        for (id, unit) in units.iter().enumerate() {
+            for role in &unit.roles {
+                for slot in role.slots.clone() {
+                    let Some(parent) = parent_of(&role.skeleton, slot) else {
+                        continue;
+                    };
+                    let index = *indices.entry(parent).or_insert_with(|| {
+                        let candidate = self.evaluate(&parent)?;
+                        candidates.push(candidate);
+                        Some(candidates.len() - 1)
+                    });
+                    let Some(index) = index else {
+                        continue;
+                    };
+                    let candidate = &mut candidates[index];
+                    if slot == 4 {
+                        if candidate.e_conflict {
+                            continue;
+                        }
+                        candidate.e_members.push(id);
+                    } else {
+                        let part = if slot == 7 { role.mystic_part } else { None };
+                        candidate.members.push((id, slot, part));
+                    }
+                    unit_candidates[id].push((index, slot == 4));
+                }
+            }
+        }
+        if let Some(id) = unit_candidates.iter().position(Vec::is_empty) {
+            return Err(RecognitionError::NoConsistentParent {
+                level: level + 1,
+                tile: units[id].supertile.tiles[0],
+            });
+        }
+
+        let mut alive = vec![true; candidates.len()];
+        let mut alive_counts: Vec<usize> = unit_candidates.iter().map(Vec::len).collect();
+        let mut forced: Vec<usize> = (0..units.len())
+            .filter(|&id| alive_counts[id] == 1)
+            .collect();
+        let mut accepted: Vec<Option<usize>> = vec![None; candidates.len()];
+        let mut parents = vec![];
+        let mut assignments = vec![None; units.len()];
+        while let Some(id) = forced.pop() {
+            if assignments[id].is_some() || alive_counts[id] != 1 {
+                continue;
+            }
+            let Some(&(chosen, is_e)) = unit_candidates[id].iter().find(|&&(c, _)| alive[c]) else {
+                continue;
+            };
+            let candidate = &candidates[chosen];
+            let parent = parents.len();
+            accepted[chosen] = Some(parent);
+            alive[chosen] = false;
+            parents.push((candidate.skeleton, candidate.e_conflict.then_some(true)));
+            let mut assigned: Vec<usize> = candidate.members.iter().map(|m| m.0).collect();
+            for &(member, slot, part) in &candidate.members {
+                assignments[member] = Some((parent, slot, part));
+            }
+            if is_e {
+                assignments[id] = Some((parent, 4, None));
+                parents[parent].1 = Some(false);
+                assigned.push(id);
+            }
+            // 決まったunitをe以外の位置に含む他の候補は使えなくなる
+            for member in assigned {
+                for &(other, is_e) in &unit_candidates[member] {
+                    if is_e || !alive[other] {
+                        continue;
+                    }
+                    alive[other] = false;
+                    let other = &candidates[other];
+                    let members = other.members.iter().map(|m| m.0);
+                    for m in members.chain(other.e_members.iter().copied()) {
+                        alive_counts[m] -= 1;
+                        if alive_counts[m] == 1 && assignments[m].is_none() {
+                            forced.push(m);
+                        }
+                    }
+                }
+            }
+        }
+
+        // 残ったunitは、決まった上位タイルのeの位置に入るか調べる
+        let mut pending = vec![];
+        for id in 0..units.len() {
+            if assignments[id].is_some() {
+                continue;
+            }
+            let alive_candidates: Vec<(usize, bool)> = unit_candidates[id]
+                .iter()
+                .copied()
+                .filter(|&(c, _)| alive[c] || accepted[c].is_some())
+                .collect();
+            // e以外の位置に入る候補が残っていれば決まらない
+            if alive_candidates.iter().any(|&(c, is_e)| !is_e && alive[c]) {
+                continue;
+            }
+            let claimants: Vec<usize> = alive_candidates
+                .iter()
+                .filter_map(|&(c, _)| accepted[c])
+                .collect();
+            match claimants[..] {
+                [] => {}
+                [parent] if claimants.len() == alive_candidates.len() => {
+                    assignments[id] = Some((parent, 4, None));
+                    parents[parent].1 = Some(false);
+                }
+                _ if claimants.len() == alive_candidates.len() => pending.push((id, claimants)),
+                _ => {}
+            }
+        }
+        Ok(Grouping {
+            parents,
+            assignments,
+            pending,
+        })
+    }
+
+    /// 上位タイルの候補がパッチと矛盾しないか調べる
+    ///
+    /// 候補に含まれるはずのタイルは、パッチにあるか、パッチのどのタイルとも重ならない必要がある。
+    /// MysticClusterはeを持たないので、eの位置だけはパッチのタイルと重なっていてもよい。
+    fn evaluate(&self, parent: &Skeleton) -> Option<Candidate> {
+        let cluster = parent.to_spectre_cluster(&self.bbox);
+        let mut iter = cluster.spectres_in(self.bbox);
+        let mut e_conflict = false;
+        while let Some(tile) = iter.next() {
+            if self.tiles.contains_key(&key(tile)) || !self.overlaps(tile) {
+                continue;
+            }
+            if iter
+                .address()
+                .is_none_or(|address| address.slots().first() != Some(&4))
+            {
+                return None;
+            }
+            e_conflict = true;
+        }
+        Some(Candidate {
+            skeleton: *parent,
+            e_conflict,
+            members: vec![],
+            e_members: vec![],
+        })
+    }
+
+    /// パッチにないタイルが、パッチのタイルと重なるかどうか
+    fn overlaps(&self, tile: &Spectre) -> bool {
+        let vertices = tile.vertices();
+        // 同じ向きの辺を持つタイルは辺の同じ側にある
+        if edges(&vertices).any(|edge| self.edges.contains_key(&edge)) {
+            return true;
+        }
+        let bbox = tile.bbox();
+        let nearby: HashSet<usize> = cells(&bbox)
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        nearby.into_iter().any(|other| {
+            let (other_vertices, other_bbox) = &self.shapes[other];
+            bbox.has_intersection(other_bbox) && interiors_overlap(&vertices, other_vertices)
+        })
+    }
+}
+
+fn key(tile: &Spectre) -> TileKey {
+    (tile.coordinate(Anchor::Anchor1), tile.orientation())
+}
+
+/// 多角形の辺を頂点の順に
+fn edges(polygon: &[HexVec]) -> impl Iterator<Item = (HexVec, HexVec)> + '_ {
+    (0..polygon.len()).map(|i| (polygon[i], polygon[(i + 1) % polygon.len()]))
+}
+
+/// 二つの多角形（凸とは限らない）の内部が重なるかどうか。辺や頂点で接するだけなら重ならない
+///
+/// 辺が交差するか、一方の頂点か辺の中点が他方の内部にあれば重なる。座標は厳密に比べる
+fn interiors_overlap(a: &[HexVec], b: &[HexVec]) -> bool {
+    edges_cross(a, b) || has_point_inside(a, b) || has_point_inside(b, a)
+}
+
+/// 辺どうしが端点以外で交差するかどうか
+fn edges_cross(a: &[HexVec], b: &[HexVec]) -> bool {
+    let side = |p: HexVec, q: HexVec, r: HexVec| (q - p).cross_sign(r - p);
+    // 厳密な判定は重いので、bboxが離れている辺の組は浮動小数点数で除く
+    let boxes = |polygon: &[HexVec]| -> Vec<Aabb> {
+        edges(polygon)
+            .map(|(p, q)| {
+                let (p, q) = (p.to_vec2(), q.to_vec2());
+                Aabb::from_min_max(p.min(q) - 1e-3, p.max(q) + 1e-3)
+            })
+            .collect()
+    };
+    let (a_boxes, b_boxes) = (boxes(a), boxes(b));
+    edges(a).zip(&a_boxes).any(|((p, q), a_box)| {
+        edges(b).zip(&b_boxes).any(|((r, s), b_box)| {
+            if !a_box.has_intersection(b_box) {
+                return false;
+            }
+            let (r_side, s_side) = (side(p, q, r), side(p, q, s));
+            let (p_side, q_side) = (side(r, s, p), side(r, s, q));
+            r_side != Ordering::Equal
+                && r_side == s_side.reverse()
+                && p_side != Ordering::Equal
+                && p_side == q_side.reverse()
+        })
+    })
+}
+
+/// aの頂点か辺の中点が、bの内部（境界を除く）にあるかどうか
+fn has_point_inside(a: &[HexVec], b: &[HexVec]) -> bool {
+    // 中点を格子点にするため、座標を2倍して比べる
+    let doubled: Vec<HexVec> = b.iter().map(|&v| v * 2).collect();
+    edges(a).any(|(p, q)| strictly_inside(&doubled, p * 2) || strictly_inside(&doubled, p + q))
+}
+
+/// 多角形の内部（境界を除く）に点があるかどうか
+fn strictly_inside(polygon: &[HexVec], point: HexVec) -> bool {
+    let mut winding = 0;
+    for (a, b) in edges(polygon) {
+        let upward = a.y <= point.y && b.y > point.y;
+        let downward = a.y > point.y && b.y <= point.y;
+        let on_box = a.x.min(b.x) <= point.x
+            && point.x <= a.x.max(b.x)
+            && a.y.min(b.y) <= point.y
+            && point.y <= a.y.max(b.y);
+        // 外積は重いので、必要な辺だけで求める
+        if !(upward || downward || on_box) {
+            continue;
+        }
+        let side = (b - a).cross_sign(point - a);
+        if side == Ordering::Equal && on_box {
+            return false;
+        }
+        if upward && side == Ordering::Greater {
+            winding += 1;
+        } else if downward && side == Ordering::Less {
+            winding -= 1;
+        }
+    }
+    winding != 0
+}
+
+fn cells(bbox: &Aabb) -> impl Iterator<Item = (i32, i32)> {
+    let min = (bbox.min / Patch::GRID_SIZE).floor();
+    let max = (bbox.max / Patch::GRID_SIZE).floor();
+    (min.x as i32..=max.x as i32)
+        .flat_map(move |x| (min.y as i32..=max.y as i32).map(move |y| (x, y)))
+}
+
+/// childがslotの位置に入る一つ上のlevelのSkeleton
+fn parent_of(child: &Skeleton, slot: usize) -> Option<Skeleton> {
+    let level = child.level() + 1;
+    let template = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, level);
+    let rotation = child.edge_direction_from(Anchor::Anchor1)
+        - template.split_into_skeletons()[slot].edge_direction_from(Anchor::Anchor1);
+    let parent = Skeleton::with_anchor(Anchor::Anchor1, HexVec::ZERO, rotation, level);
+    let offset = child.coordinate(Anchor::Anchor1)
+        - parent.split_into_skeletons()[slot].coordinate(Anchor::Anchor1);
+    let parent = parent.translated(offset);
+    (parent.split_into_skeletons()[slot] == *child).then_some(parent)
+}
+
+/// tileを上側とするMysticの下側
+fn mystic_lower_of(tile: Spectre) -> Spectre {
+    // 下側と上側の向きの差は一定なので、回転していないMysticから求める
+    let upper = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO)
+        .into_mystic()
+        .upper()
+        .rotation();
+    let lower = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, tile.rotation() - upper);
+    let offset =
+        tile.coordinate(Anchor::Anchor1) - lower.into_mystic().upper().coordinate(Anchor::Anchor1);
+    lower.translated(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tiles::SpectreCluster,
+        utils::{Circle, Region},
+    };
+
+    fn tiles_in<R: Region>(cluster: &SpectreCluster, region: R) -> Vec<(Spectre, TileAddress)> {
+        let mut iter = cluster.spectres_in(region);
+        let mut tiles = vec![];
+        while let Some(spectre) = iter.next() {
+            if let Some(address) = iter.address() {
+                tiles.push((*spectre, address));
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn test_interiors_overlap() {
+        let tile = Spectre::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO);
+        let vertices = tile.vertices();
+        // タイリングの隣どうしのタイルは辺や頂点を共有するだけで重ならない
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let tiles: Vec<Vec<HexVec>> = cluster
+            .spectres_in(cluster.bbox())
+            .map(|tile| tile.vertices())
+            .collect();
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                assert!(!interiors_overlap(a, b));
+            }
+        }
+
+        // 頂点4を頂点0に重ねたタイルは一部だけ重なり、どちらの頂点0と頂点7の中点も他方の外にある
+        let shifted = tile.translated(vertices[0] - vertices[4]).vertices();
+        assert!(interiors_overlap(&vertices, &shifted));
+        assert!(interiors_overlap(&shifted, &vertices));
+        let midpoint = |v: &[HexVec]| v[0] + v[7];
+        let doubled = |v: &[HexVec]| v.iter().map(|&p| p * 2).collect::<Vec<_>>();
+        assert!(!strictly_inside(&doubled(&vertices), midpoint(&shifted)));
+        assert!(!strictly_inside(&doubled(&shifted), midpoint(&vertices)));
+    }
+
+    #[test]
+    fn test_recognize_whole_cluster() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor2, HexVec::ZERO, Angle::new(4), 3);
+        let mut tiles = tiles_in(&cluster, cluster.bbox());
+        // 入力の順序によらない
+        tiles.reverse();
+        let recognition =
+            SupertileRecognition::recognize(tiles.iter().map(|(tile, _)| *tile).collect()).unwrap();
+
+        // 端のタイルは隣のClusterに入る可能性が残るので、根はいくつかに分かれる
+        assert_eq!(recognition.level(), 3);
+        let root = recognition
+            .roots()
+            .iter()
+            .max_by_key(|root| root.tiles.len())
+            .unwrap();
+        assert_eq!(root.skeleton, cluster.to_skeleton());
+        assert!(root.tiles.len() * 2 > tiles.len());
+        assert_suffix(&recognition, &tiles);
+    }
+
+    #[test]
+    fn test_recognize_partial_patch() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 4);
+        let center = cluster.coordinate(Anchor::Anchor3).to_vec2() * 0.5;
+        let tiles = tiles_in(&cluster, Circle::new(center, 12.0));
+        let recognition =
+            SupertileRecognition::recognize(tiles.iter().map(|(tile, _)| *tile).collect()).unwrap();
+
+        assert!(recognition.level() >= 2);
+        assert_suffix(&recognition, &tiles);
+    }
+
+    /// 復元できたところまでは元の位置の末尾と一致する
+    fn assert_suffix(recognition: &SupertileRecognition, tiles: &[(Spectre, TileAddress)]) {
+        for (i, (_, address)) in tiles.iter().enumerate() {
+            let recognized = recognition.address(i);
+            let depth = recognized.slots().len();
+            assert_eq!(
+                recognized.slots(),
+                &address.slots()[address.slots().len() - depth..]
+            );
+            if depth > 0 {
+                assert_eq!(recognized.mystic_part(), address.mystic_part());
+            }
+        }
+    }
+
+    #[test]
+    fn test_reject_illegal_patch() {
+        let cluster = SpectreCluster::with_anchor(Anchor::Anchor1, HexVec::ZERO, Angle::ZERO, 2);
+        let mut tiles: Vec<Spectre> = tiles_in(&cluster, cluster.bbox())
+            .into_iter()
+            .map(|(tile, _)| tile)
+            .collect();
+
+        // 穴が空いていても正しいパッチ
+        let removed = tiles.remove(20);
+        assert!(SupertileRecognition::recognize(tiles.clone()).is_ok());
+
+        // 向きを変えたタイルを置くと周りと重なる
+        let rotated = Spectre::with_anchor(
+            Anchor::Anchor1,
+            removed.coordinate(Anchor::Anchor1),
+            removed.rotation() + Angle::new(2),
+        );
+        let mut illegal = tiles.clone();
+        illegal.push(rotated);
+        assert!(matches!(
+            SupertileRecognition::recognize(illegal),
+            Err(RecognitionError::NoConsistentParent { .. })
+        ));
+
+        let mut duplicated = tiles.clone();
+        duplicated.push(tiles[3]);
+        assert_eq!(
+            SupertileRecognition::recognize(duplicated).err(),
+            Some(RecognitionError::DuplicateTile(tiles.len()))
+        );
+    }
+}
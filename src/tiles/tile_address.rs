@@ -0,0 +1,35 @@
+/// 根のClusterからタイルまでの道順
+///
+/// slotsは上のlevelから順に、各Clusterの子の位置（0〜7がa〜h）を並べたもの。
+/// Mysticを構成するタイルの場合は、mystic_partが下側なら0、上側なら1になる。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileAddress {
+    slots: Vec<u8>,
+    mystic_part: Option<u8>,
+}
+
+impl TileAddress {
+    pub fn new(slots: Vec<u8>, mystic_part: Option<u8>) -> Self {
+        Self { slots, mystic_part }
+    }
+
+    pub fn slots(&self) -> &[u8] {
+        &self.slots
+    }
+
+    pub fn mystic_part(&self) -> Option<u8> {
+        self.mystic_part
+    }
+}
+
+impl std::fmt::Display for TileAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &slot in &self.slots {
+            write!(f, "{}", (b'a' + slot) as char)?;
+        }
+        if let Some(part) = self.mystic_part {
+            write!(f, "/{}", part)?;
+        }
+        Ok(())
+    }
+}
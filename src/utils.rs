@@ -1,9 +1,19 @@
 mod aabb;
 mod angle;
+mod circle;
+mod convex_polygon;
+mod half_plane;
 mod hex_value;
 mod hex_vec;
+mod orientation;
+mod region;
 
 pub use aabb::Aabb;
 pub use angle::Angle;
+pub use circle::Circle;
+pub use convex_polygon::ConvexPolygon;
+pub use half_plane::HalfPlane;
 pub use hex_value::HexValue;
 pub use hex_vec::HexVec;
+pub use orientation::Orientation;
+pub use region::Region;
@@ -57,6 +57,14 @@ impl Aabb {
             && self.min.y <= point.y
             && point.y <= self.max.y
     }
+
+    /// otherが完全に含まれるかどうか
+    pub fn contains_aabb(&self, other: &Self) -> bool {
+        self.min.x <= other.min.x
+            && other.max.x <= self.max.x
+            && self.min.y <= other.min.y
+            && other.max.y <= self.max.y
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +124,15 @@ mod tests {
         assert!(bbox4.is_empty());
     }
 
+    #[test]
+    fn test_contains_aabb() {
+        let bbox = Aabb::new(0.0, 0.0, 4.0, 4.0);
+        assert!(bbox.contains_aabb(&Aabb::new(1.0, 1.0, 3.0, 3.0)));
+        assert!(bbox.contains_aabb(&bbox));
+        assert!(!bbox.contains_aabb(&Aabb::new(1.0, 1.0, 5.0, 3.0)));
+        assert!(!bbox.contains_aabb(&Aabb::new(-1.0, -1.0, 1.0, 1.0)));
+    }
+
     #[test]
     fn test_null() {
         // NULLの値が正しく定義されているか
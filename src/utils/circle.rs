@@ -0,0 +1,90 @@
+use glam::Vec2;
+
+use super::{convex_polygon::corners, Aabb, ConvexPolygon, Region};
+
+/// 円
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+
+    pub fn bbox(&self) -> Aabb {
+        Aabb::from_min_max(
+            self.center - Vec2::splat(self.radius),
+            self.center + Vec2::splat(self.radius),
+        )
+    }
+}
+
+impl Region for Circle {
+    fn bbox(&self) -> Option<Aabb> {
+        Some(Circle::bbox(self))
+    }
+
+    fn intersects_aabb(&self, bbox: &Aabb) -> bool {
+        // bbox内で中心に最も近い点までの距離で判定する
+        let nearest = self.center.clamp(bbox.min, bbox.max);
+        !bbox.is_empty() && self.center.distance_squared(nearest) < self.radius * self.radius
+    }
+
+    fn contains_aabb(&self, bbox: &Aabb) -> bool {
+        corners(bbox).iter().all(|&p| self.contains(p))
+    }
+
+    fn intersects_polygon(&self, polygon: &ConvexPolygon) -> bool {
+        if !self.intersects_aabb(&polygon.bbox()) {
+            return false;
+        }
+        if polygon.contains(self.center) {
+            return true;
+        }
+        // 中心が外側にある場合は最も近い辺までの距離で判定する
+        polygon.edges().any(|(p, q)| {
+            let edge = q - p;
+            let t = ((self.center - p).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+            self.center.distance_squared(p + edge * t) < self.radius * self.radius
+        })
+    }
+
+    fn expanded(&self, margin: f32) -> Self {
+        Circle::new(self.center, self.radius + margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersects_aabb() {
+        let circle = Circle::new(Vec2::ZERO, 1.0);
+        assert!(circle.intersects_aabb(&Aabb::new(0.5, 0.5, 2.0, 2.0)));
+        // 角は円の外にある
+        assert!(!circle.intersects_aabb(&Aabb::new(0.8, 0.8, 2.0, 2.0)));
+        assert!(circle.contains_aabb(&Aabb::new(-0.5, -0.5, 0.5, 0.5)));
+        assert!(!circle.contains_aabb(&Aabb::new(-0.5, -0.5, 0.8, 0.8)));
+    }
+
+    #[test]
+    fn test_intersects_polygon() {
+        let circle = Circle::new(Vec2::ZERO, 1.0);
+        let triangle = ConvexPolygon::new(vec![
+            Vec2::new(0.9, -1.0),
+            Vec2::new(3.0, -1.0),
+            Vec2::new(0.9, 1.0),
+        ]);
+        assert!(circle.intersects_polygon(&triangle));
+        let far = triangle.translated(Vec2::new(0.5, 0.0));
+        assert!(!circle.intersects_polygon(&far));
+    }
+}
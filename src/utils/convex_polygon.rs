@@ -0,0 +1,242 @@
+use glam::Vec2;
+
+use super::{Aabb, Angle, HexVec, Orientation};
+
+/// 凸多角形（頂点は反時計回り）
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvexPolygon {
+    vertices: Vec<Vec2>,
+    bbox: Aabb,
+}
+
+impl ConvexPolygon {
+    /// 反時計回りに並んだ凸多角形の頂点から生成する
+    pub fn new(vertices: Vec<Vec2>) -> Self {
+        let bbox = vertices
+            .iter()
+            .fold(Aabb::NULL, |bbox, &p| bbox.union(&Aabb::from_min_max(p, p)));
+        Self { vertices, bbox }
+    }
+
+    /// 点の集合の凸包として生成する
+    pub fn hull_of(points: &[HexVec]) -> Self {
+        Self::new(
+            HexVec::convex_hull(points)
+                .into_iter()
+                .map(HexVec::to_vec2)
+                .collect(),
+        )
+    }
+
+    pub fn vertices(&self) -> &[Vec2] {
+        &self.vertices
+    }
+
+    pub fn bbox(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// bboxと同じ範囲の多角形
+    pub fn from_aabb(bbox: &Aabb) -> Self {
+        Self::new(corners(bbox).to_vec())
+    }
+
+    /// 原点を中心に回転した多角形
+    pub fn rotated(&self, angle: Angle) -> Self {
+        let rotation = Vec2::from_angle(angle.to_radians());
+        Self::new(self.vertices.iter().map(|&p| rotation.rotate(p)).collect())
+    }
+
+    /// 原点を中心にorientationで変換した多角形
+    pub fn transformed(&self, orientation: Orientation) -> Self {
+        let rotation = Vec2::from_angle(orientation.rotation.to_radians());
+        let mut vertices: Vec<Vec2> = self
+            .vertices
+            .iter()
+            .map(|&p| {
+                let p = if orientation.reflected {
+                    Vec2::new(p.x, -p.y)
+                } else {
+                    p
+                };
+                rotation.rotate(p)
+            })
+            .collect();
+        // 鏡映すると頂点が時計回りに並ぶので反時計回りに戻す
+        if orientation.reflected {
+            vertices.reverse();
+        }
+        Self::new(vertices)
+    }
+
+    /// 平行移動した多角形
+    pub fn translated(&self, offset: Vec2) -> Self {
+        Self {
+            vertices: self.vertices.iter().map(|&p| p + offset).collect(),
+            bbox: Aabb::from_min_max(self.bbox.min + offset, self.bbox.max + offset),
+        }
+    }
+
+    /// 各辺を外側にmarginだけ平行移動した多角形
+    pub fn expanded(&self, margin: f32) -> Self {
+        let n = self.vertices.len();
+        Self::new(
+            (0..n)
+                .map(|i| {
+                    let prev = self.vertices[(i + n - 1) % n];
+                    let p = self.vertices[i];
+                    let next = self.vertices[(i + 1) % n];
+                    // 反時計回りなのでperpの逆向きが外向きの法線
+                    let n0 = -(p - prev).perp().normalize();
+                    let n1 = -(next - p).perp().normalize();
+                    // 隣り合う二辺をそれぞれmarginだけ動かしたときの交点
+                    p + (n0 + n1) * (margin / (1.0 + n0.dot(n1)))
+                })
+                .collect(),
+        )
+    }
+
+    /// bboxと交差するかどうか（分離軸判定）
+    pub fn has_intersection(&self, bbox: &Aabb) -> bool {
+        // bboxの軸はAabb同士の判定で済んでいるので、多角形の辺の法線だけを調べる
+        self.bbox.has_intersection(bbox) && !separated_by_edges(&self.vertices, &corners(bbox))
+    }
+
+    /// 凸多角形同士が交差するかどうか（分離軸判定）
+    pub fn has_intersection_with_polygon(&self, other: &ConvexPolygon) -> bool {
+        self.bbox.has_intersection(&other.bbox)
+            && !separated_by_edges(&self.vertices, &other.vertices)
+            && !separated_by_edges(&other.vertices, &self.vertices)
+    }
+
+    /// 点が多角形に含まれるかどうか（境界を含む）
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.edges()
+            .all(|(p, q)| (q - p).perp().dot(point - p) >= 0.0)
+    }
+
+    /// 辺を(始点, 終点)の組で列挙する
+    pub fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (p, self.vertices[(i + 1) % self.vertices.len()]))
+    }
+}
+
+/// bboxの四隅（反時計回り）
+pub(super) fn corners(bbox: &Aabb) -> [Vec2; 4] {
+    [
+        bbox.min,
+        Vec2::new(bbox.max.x, bbox.min.y),
+        bbox.max,
+        Vec2::new(bbox.min.x, bbox.max.y),
+    ]
+}
+
+/// 反時計回りの凸多角形aの辺の法線のいずれかでbと分離できるかどうか
+fn separated_by_edges(a: &[Vec2], b: &[Vec2]) -> bool {
+    for (i, &p) in a.iter().enumerate() {
+        let q = a[(i + 1) % a.len()];
+        // 反時計回りなので法線は内側を向き、aの射影は[0, a_max]になる
+        let normal = (q - p).perp();
+        let a_max = a.iter().map(|v| normal.dot(*v - p)).fold(0.0, f32::max);
+        let (b_min, b_max) = b
+            .iter()
+            .map(|v| normal.dot(*v - p))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), d| {
+                (min.min(d), max.max(d))
+            });
+        if b_max <= 0.0 || a_max <= b_min {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond() -> ConvexPolygon {
+        ConvexPolygon::new(vec![
+            Vec2::new(0.0, -2.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(0.0, 2.0),
+            Vec2::new(-2.0, 0.0),
+        ])
+    }
+
+    #[test]
+    fn test_bbox() {
+        assert_eq!(diamond().bbox(), Aabb::new(-2.0, -2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn test_has_intersection() {
+        let polygon = diamond();
+        assert!(polygon.has_intersection(&Aabb::new(-0.5, -0.5, 0.5, 0.5)));
+        assert!(polygon.has_intersection(&Aabb::new(0.5, 0.5, 3.0, 3.0)));
+        // bbox同士は交差するが多角形とは交差しない
+        assert!(!polygon.has_intersection(&Aabb::new(1.5, 1.5, 3.0, 3.0)));
+        assert!(!polygon.has_intersection(&Aabb::new(3.0, 3.0, 4.0, 4.0)));
+    }
+
+    #[test]
+    fn test_has_intersection_with_polygon() {
+        let polygon = diamond();
+        let near = polygon.translated(Vec2::new(3.0, 0.0));
+        let far = polygon.translated(Vec2::new(3.0, 3.0));
+        assert!(polygon.has_intersection_with_polygon(&near));
+        assert!(!polygon.has_intersection_with_polygon(&far));
+    }
+
+    #[test]
+    fn test_rotated() {
+        let square = ConvexPolygon::from_aabb(&Aabb::new(-1.0, -1.0, 1.0, 1.0));
+        let rotated = square.rotated(Angle::new(3));
+        // 90度回転しても同じ正方形になる
+        for (p, q) in rotated
+            .vertices()
+            .iter()
+            .zip(square.vertices().iter().cycle().skip(1))
+        {
+            assert!(p.distance(*q) < 1e-6);
+        }
+        let rotated = square.rotated(Angle::new(1));
+        assert!(rotated.contains(Vec2::ZERO));
+        assert!(rotated.bbox().max.x > 1.3);
+    }
+
+    #[test]
+    fn test_expanded() {
+        let square = ConvexPolygon::from_aabb(&Aabb::new(-1.0, -1.0, 1.0, 1.0));
+        assert_eq!(square.expanded(0.5).bbox(), Aabb::new(-1.5, -1.5, 1.5, 1.5));
+        let expanded = diamond().expanded(1.0);
+        assert!(expanded.contains(Vec2::new(1.5, 1.5)));
+        assert!(!expanded.contains(Vec2::new(2.0, 2.0)));
+    }
+
+    #[test]
+    fn test_transformed() {
+        // x軸より上にある三角形
+        let triangle = ConvexPolygon::new(vec![
+            Vec2::new(0.0, 1.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(0.0, 3.0),
+        ]);
+        let reflected = triangle.transformed(Orientation::new(Angle::ZERO, true));
+        assert!(reflected.contains(Vec2::new(0.5, -1.5)));
+        assert!(!reflected.contains(Vec2::new(0.5, 1.5)));
+        // 反時計回りに並んでいるので交差判定にも使える
+        assert!(reflected.has_intersection(&Aabb::new(0.2, -2.0, 0.8, -1.2)));
+    }
+
+    #[test]
+    fn test_contains() {
+        let polygon = diamond();
+        assert!(polygon.contains(Vec2::ZERO));
+        assert!(polygon.contains(Vec2::new(1.0, 1.0)));
+        assert!(!polygon.contains(Vec2::new(1.5, 1.5)));
+    }
+}
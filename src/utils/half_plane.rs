@@ -0,0 +1,89 @@
+use glam::Vec2;
+
+use super::{convex_polygon::corners, Aabb, ConvexPolygon, Region};
+
+/// 半平面
+///
+/// pointを通りnormalの向く側を表す。
+/// 他のRegionと同じく、含むかどうかは境界を含めて、交差するかどうかは境界を除いて判定する
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HalfPlane {
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+impl HalfPlane {
+    pub fn new(point: Vec2, normal: Vec2) -> Self {
+        Self { point, normal }
+    }
+
+    /// 境界からの符号付きの距離（normalの長さ倍）
+    fn signed_distance(&self, p: Vec2) -> f32 {
+        self.normal.dot(p - self.point)
+    }
+}
+
+impl Region for HalfPlane {
+    /// 半平面は有界でない
+    fn bbox(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn intersects_aabb(&self, bbox: &Aabb) -> bool {
+        !bbox.is_empty() && corners(bbox).iter().any(|&p| self.signed_distance(p) > 0.0)
+    }
+
+    fn contains_aabb(&self, bbox: &Aabb) -> bool {
+        corners(bbox)
+            .iter()
+            .all(|&p| self.signed_distance(p) >= 0.0)
+    }
+
+    fn intersects_polygon(&self, polygon: &ConvexPolygon) -> bool {
+        polygon
+            .vertices()
+            .iter()
+            .any(|&p| self.signed_distance(p) > 0.0)
+    }
+
+    fn expanded(&self, margin: f32) -> Self {
+        HalfPlane::new(self.point - self.normal.normalize() * margin, self.normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_plane() {
+        // x + y >= 0
+        let half_plane = HalfPlane::new(Vec2::ZERO, Vec2::new(1.0, 1.0));
+        assert!(half_plane.intersects_aabb(&Aabb::new(-2.0, -2.0, 0.5, 0.5)));
+        assert!(!half_plane.intersects_aabb(&Aabb::new(-2.0, -2.0, -1.0, 0.5)));
+        assert!(half_plane.contains_aabb(&Aabb::new(0.0, 0.0, 1.0, 1.0)));
+        assert!(!half_plane.contains_aabb(&Aabb::new(-1.0, 0.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_boundary() {
+        // Aabb同士と同じく、境界で接するだけなら交差しないが、境界上にあれば含む
+        let half_plane = HalfPlane::new(Vec2::ZERO, Vec2::X);
+        let touching = Aabb::new(-1.0, 0.0, 0.0, 1.0);
+        let on_boundary = Aabb::new(0.0, 0.0, 1.0, 1.0);
+        assert!(!half_plane.intersects_aabb(&touching));
+        assert!(!on_boundary.intersects_aabb(&touching));
+        assert!(half_plane.contains_aabb(&on_boundary));
+        assert!(Aabb::new(0.0, -1.0, 2.0, 2.0).contains_aabb(&on_boundary));
+
+        let triangle = ConvexPolygon::new(vec![
+            Vec2::new(-1.0, 0.0),
+            Vec2::new(0.0, -1.0),
+            Vec2::new(0.0, 1.0),
+        ]);
+        assert!(!half_plane.intersects_polygon(&triangle));
+        assert!(!on_boundary.intersects_polygon(&triangle));
+        assert!(half_plane.expanded(0.5).intersects_polygon(&triangle));
+        assert_eq!(half_plane.bbox(), None);
+    }
+}
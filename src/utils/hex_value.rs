@@ -4,7 +4,7 @@ use super::Angle;
 
 /// 正六角形のタイリングに適した実数値を表現する型
 /// i/2 + j*√3/2 の形で値を保持する
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub struct HexValue {
     /// 有理数部分の分子（分母は2で固定）
@@ -106,6 +106,58 @@ impl HexValue {
     pub fn to_f32(self) -> f32 {
         self.rational as f32 / 2.0 + self.irrational as f32 * 3.0_f32.sqrt() / 2.0
     }
+
+    /// f64に変換
+    pub fn to_f64(self) -> f64 {
+        self.rational as f64 / 2.0 + self.irrational as f64 * 3.0_f64.sqrt() / 2.0
+    }
+}
+
+impl HexValue {
+    /// p + q√3 の符号を厳密に求める。|q| < 2^126 であること
+    pub(crate) fn sign_of(p: i128, q: i128) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (p.cmp(&0), q.cmp(&0)) {
+            (Ordering::Equal, q_sign) => q_sign,
+            (p_sign, Ordering::Equal) => p_sign,
+            (p_sign, q_sign) if p_sign == q_sign => p_sign,
+            // 符号が異なる場合は p^2 と 3q^2 を比較する
+            (p_sign, _) => {
+                let (p, q) = (p.unsigned_abs(), q.unsigned_abs());
+                match wide_mul(p, p).cmp(&wide_mul(3 * q, q)) {
+                    Ordering::Greater => p_sign,
+                    Ordering::Less => p_sign.reverse(),
+                    Ordering::Equal => Ordering::Equal,
+                }
+            }
+        }
+    }
+}
+
+/// 128ビットに収まらない積を(上位, 下位)の128ビットずつで求める
+fn wide_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & LOW);
+    let (b1, b0) = (b >> 64, b & LOW);
+    let (mid, mid_carry) = (a0 * b1).overflowing_add(a1 * b0);
+    let (low, low_carry) = (a0 * b0).overflowing_add(mid << 64);
+    let high = a1 * b1 + (mid >> 64) + ((mid_carry as u128) << 64) + low_carry as u128;
+    (high, low)
+}
+
+impl PartialOrd for HexValue {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HexValue {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Self::sign_of(
+            self.rational as i128 - other.rational as i128,
+            self.irrational as i128 - other.irrational as i128,
+        )
+    }
 }
 
 impl Add for HexValue {
@@ -189,6 +241,19 @@ mod tests {
         assert_eq!(HexValue::ZERO.irrational, 0);
     }
 
+    #[test]
+    fn test_sign_of_large() {
+        use std::cmp::Ordering;
+        assert_eq!(wide_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(wide_mul(1 << 64, 1 << 64), (1, 0));
+        // p^2も3q^2も128ビットに収まらない
+        assert_eq!(HexValue::sign_of(1 << 65, -(1 << 64)), Ordering::Greater);
+        assert_eq!(
+            HexValue::sign_of(1 << 65, -((1 << 64) + (1 << 63))),
+            Ordering::Less
+        );
+    }
+
     #[test]
     fn test_cos() {
         // 0度 = cos(0°) = 1
@@ -269,6 +334,24 @@ mod tests {
         assert_eq!(b, HexValue::new(2, 2));
     }
 
+    #[test]
+    fn test_ord() {
+        // 1 < √3/2 * 2 = √3
+        assert!(HexValue::new(2, 0) < HexValue::new(0, 2));
+        // 2 > √3
+        assert!(HexValue::new(4, 0) > HexValue::new(0, 2));
+        // -1/2 + √3/2 > 0
+        assert!(HexValue::new(-1, 1) > HexValue::ZERO);
+        // 7/2 - 2√3 > 0 (3.5 > 3.46...)
+        assert!(HexValue::new(7, -4) > HexValue::ZERO);
+        // 3/2 - √3 < 0
+        assert!(HexValue::new(3, -2) < HexValue::ZERO);
+        assert_eq!(
+            HexValue::new(3, 1).cmp(&HexValue::new(3, 1)),
+            std::cmp::Ordering::Equal
+        );
+    }
+
     #[test]
     fn test_display() {
         assert_eq!(HexValue::new(1, 2).to_string(), "1/2 + 1 * √3");
@@ -1,9 +1,12 @@
-use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::{
+    cmp::Ordering,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
 
-use super::{Angle, HexValue};
+use super::{Angle, HexValue, Orientation};
 
 /// 正六角形のタイリングに適した2次元ベクトル
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct HexVec {
     pub x: HexValue,
     pub y: HexValue,
@@ -65,6 +68,72 @@ impl HexVec {
         // 中心点を加算して元の座標系に戻す
         center + rotated
     }
+
+    /// x軸について鏡映した点
+    pub fn reflect(self) -> Self {
+        Self::new(self.x, -self.y)
+    }
+
+    /// 点をcenterを中心にorientationで変換する（鏡映してから回転）
+    pub fn transform(self, center: Self, orientation: Orientation) -> Self {
+        let relative = self - center;
+        let relative = if orientation.reflected {
+            relative.reflect()
+        } else {
+            relative
+        };
+        center + relative.rotate(Self::ZERO, orientation.rotation)
+    }
+
+    /// 外積 self × other の符号を厳密に求める
+    pub fn cross_sign(self, other: Self) -> Ordering {
+        let (ax, ay) = (self.x, self.y);
+        let (bx, by) = (other.x, other.y);
+        // (r1 + i1√3)(r2 + i2√3) = r1r2 + 3i1i2 + (r1i2 + i1r2)√3
+        // i32どうしの積を足し合わせるとi64に収まらないことがあるので、i128で計算する
+        let mul = |a: HexValue, b: HexValue| {
+            let (ar, ai) = (a.rational as i128, a.irrational as i128);
+            let (br, bi) = (b.rational as i128, b.irrational as i128);
+            (ar * br + 3 * ai * bi, ar * bi + ai * br)
+        };
+        let (p1, q1) = mul(ax, by);
+        let (p2, q2) = mul(ay, bx);
+        HexValue::sign_of(p1 - p2, q1 - q2)
+    }
+
+    /// 点の集合の凸包を反時計回りに求める
+    ///
+    /// 座標を厳密に扱うため、同一直線上の点は取り除かれる
+    pub fn convex_hull(points: &[HexVec]) -> Vec<HexVec> {
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.x.cmp(&b.x).then(a.y.cmp(&b.y)));
+        points.dedup();
+        if points.len() < 3 {
+            return points;
+        }
+
+        // Andrew's monotone chain
+        let mut hull: Vec<HexVec> = Vec::with_capacity(points.len() * 2);
+        for pass in 0..2 {
+            let start = hull.len();
+            for &p in points.iter() {
+                while hull.len() >= start + 2 {
+                    let a = hull[hull.len() - 2];
+                    let b = hull[hull.len() - 1];
+                    if (b - a).cross_sign(p - a) == Ordering::Greater {
+                        break;
+                    }
+                    hull.pop();
+                }
+                hull.push(p);
+            }
+            hull.pop();
+            if pass == 0 {
+                points.reverse();
+            }
+        }
+        hull
+    }
 }
 
 impl Add for HexVec {
@@ -199,6 +268,40 @@ mod tests {
         assert_eq!(rotated.y, HexValue::new(-1, 0)); // -1/2
     }
 
+    #[test]
+    fn test_convex_hull() {
+        let v = |x: i32, y: i32| HexVec::new(HexValue::new(x, 0), HexValue::new(y, 0));
+        let points = [v(0, 0), v(4, 0), v(4, 4), v(0, 4), v(2, 2), v(2, 0)];
+        let hull = HexVec::convex_hull(&points);
+        assert_eq!(hull, vec![v(0, 0), v(4, 0), v(4, 4), v(0, 4)]);
+
+        // √3を含む座標
+        let points = [
+            HexVec::ZERO,
+            HexVec::new(HexValue::new(2, 0), HexValue::ZERO),
+            HexVec::new(HexValue::new(1, 0), HexValue::new(0, 1)),
+            HexVec::new(HexValue::new(1, 0), HexValue::new(0, -1)),
+            HexVec::new(HexValue::new(1, 0), HexValue::ZERO),
+        ];
+        let hull = HexVec::convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+    }
+
+    #[test]
+    fn test_cross_sign_large() {
+        let max = HexValue::new(i32::MAX, i32::MAX);
+        let x = HexVec::new(max, HexValue::ZERO);
+        let y = HexVec::new(HexValue::ZERO, max);
+        assert_eq!(x.cross_sign(y), Ordering::Greater);
+        assert_eq!(y.cross_sign(x), Ordering::Less);
+
+        let v = HexVec::new(max, HexValue::new(i32::MIN, i32::MAX));
+        assert_eq!(v.cross_sign(v), Ordering::Equal);
+        // 有理部と無理部の符号が異なる、ほぼ平行なベクトル
+        let w = HexVec::new(max, HexValue::new(i32::MIN, i32::MAX - 1));
+        assert_eq!(v.cross_sign(w), Ordering::Less);
+    }
+
     #[test]
     fn test_rotate_special_vectors() {
         let center = HexVec::ZERO;
@@ -217,4 +320,32 @@ mod tests {
         assert_eq!(rotated.x, HexValue::new(-1, 0)); // -1/2
         assert_eq!(rotated.y, HexValue::new(0, 1)); // √3/2
     }
+
+    #[test]
+    fn test_transform() {
+        let point = HexVec::new(HexValue::new(2, 0), HexValue::ZERO);
+        let center = HexVec::new(HexValue::new(4, 2), HexValue::new(-2, 0));
+
+        // 30度の直線についての鏡映
+        let reflection = Orientation::new(Angle::new(2), true);
+        let reflected = point.transform(HexVec::ZERO, reflection);
+        assert_eq!(
+            reflected,
+            HexVec::new(HexValue::new(1, 0), HexValue::new(0, 1))
+        );
+        assert_eq!(reflected.transform(HexVec::ZERO, reflection), point);
+
+        // 回転のみの場合はrotateと同じ
+        let rotation = Orientation::rotation(Angle::new(5));
+        assert_eq!(
+            point.transform(center, rotation),
+            point.rotate(center, Angle::new(5))
+        );
+        assert_eq!(
+            point
+                .transform(center, reflection)
+                .transform(center, reflection),
+            point
+        );
+    }
 }
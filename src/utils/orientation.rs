@@ -0,0 +1,104 @@
+use super::Angle;
+
+/// 向き（回転と鏡映）
+///
+/// # Details
+/// x軸について鏡映してから（reflectedの場合のみ）rotationだけ回転する変換を表します。
+/// 鏡映を含む向きは、角度rotation/2の直線についての鏡映と同じです。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Orientation {
+    pub rotation: Angle,
+    pub reflected: bool,
+}
+
+impl Orientation {
+    /// 恒等変換
+    pub const IDENTITY: Self = Self::new(Angle::ZERO, false);
+
+    pub const fn new(rotation: Angle, reflected: bool) -> Self {
+        Self {
+            rotation,
+            reflected,
+        }
+    }
+
+    /// 回転のみの向き
+    pub const fn rotation(rotation: Angle) -> Self {
+        Self::new(rotation, false)
+    }
+
+    /// 方向を変換する
+    pub fn apply(self, direction: Angle) -> Angle {
+        if self.reflected {
+            -direction + self.rotation
+        } else {
+            direction + self.rotation
+        }
+    }
+
+    /// otherを適用してからselfを適用する向き
+    pub fn compose(self, other: Self) -> Self {
+        Self::new(
+            self.apply(other.rotation),
+            self.reflected != other.reflected,
+        )
+    }
+
+    /// 逆変換
+    pub fn inverse(self) -> Self {
+        if self.reflected {
+            // 直線についての鏡映は自分自身が逆変換
+            self
+        } else {
+            Self::rotation(-self.rotation)
+        }
+    }
+}
+
+impl From<Angle> for Orientation {
+    fn from(rotation: Angle) -> Self {
+        Self::rotation(rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let rotation = Orientation::rotation(Angle::new(3));
+        assert_eq!(rotation.apply(Angle::new(1)), Angle::new(4));
+        let reflection = Orientation::new(Angle::new(3), true);
+        assert_eq!(reflection.apply(Angle::new(1)), Angle::new(2));
+        assert_eq!(
+            reflection.apply(reflection.apply(Angle::new(1))),
+            Angle::new(1)
+        );
+    }
+
+    #[test]
+    fn test_compose_and_inverse() {
+        for rotation in 0..12 {
+            for reflected in [false, true] {
+                let orientation = Orientation::new(Angle::new(rotation), reflected);
+                assert_eq!(
+                    orientation.compose(orientation.inverse()),
+                    Orientation::IDENTITY
+                );
+                assert_eq!(
+                    orientation.inverse().compose(orientation),
+                    Orientation::IDENTITY
+                );
+                let other = Orientation::new(Angle::new(5), true);
+                for direction in 0..12 {
+                    let direction = Angle::new(direction);
+                    assert_eq!(
+                        orientation.compose(other).apply(direction),
+                        orientation.apply(other.apply(direction))
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use glam::Vec2;
+
+use super::{convex_polygon::corners, Aabb, ConvexPolygon};
+
+/// タイルを取得・ロードする範囲
+///
+/// SpectreIterやSpectreCluster::updateは、この範囲と交差する部分だけを辿る
+pub trait Region {
+    /// 範囲を囲むbbox。有界でない範囲ではNone
+    fn bbox(&self) -> Option<Aabb>;
+
+    /// bboxと交差するかどうか。Aabb同士と同じく、境界で接するだけなら交差しない
+    fn intersects_aabb(&self, bbox: &Aabb) -> bool;
+
+    /// bboxを完全に含むかどうか。境界上にあるbboxも含む
+    fn contains_aabb(&self, bbox: &Aabb) -> bool;
+
+    /// 凸多角形と交差するかどうか。境界で接するだけなら交差しない
+    fn intersects_polygon(&self, polygon: &ConvexPolygon) -> bool;
+
+    /// 境界を外側にmarginだけ広げた範囲
+    fn expanded(&self, margin: f32) -> Self
+    where
+        Self: Sized;
+}
+
+impl Region for Aabb {
+    fn bbox(&self) -> Option<Aabb> {
+        Some(*self)
+    }
+
+    fn intersects_aabb(&self, bbox: &Aabb) -> bool {
+        self.has_intersection(bbox)
+    }
+
+    fn contains_aabb(&self, bbox: &Aabb) -> bool {
+        Aabb::contains_aabb(self, bbox)
+    }
+
+    fn intersects_polygon(&self, polygon: &ConvexPolygon) -> bool {
+        polygon.has_intersection(self)
+    }
+
+    fn expanded(&self, margin: f32) -> Self {
+        Aabb::from_min_max(
+            self.min - Vec2::splat(margin),
+            self.max + Vec2::splat(margin),
+        )
+    }
+}
+
+impl Region for ConvexPolygon {
+    fn bbox(&self) -> Option<Aabb> {
+        Some(ConvexPolygon::bbox(self))
+    }
+
+    fn intersects_aabb(&self, bbox: &Aabb) -> bool {
+        self.has_intersection(bbox)
+    }
+
+    fn contains_aabb(&self, bbox: &Aabb) -> bool {
+        corners(bbox).iter().all(|&p| self.contains(p))
+    }
+
+    fn intersects_polygon(&self, polygon: &ConvexPolygon) -> bool {
+        self.has_intersection_with_polygon(polygon)
+    }
+
+    fn expanded(&self, margin: f32) -> Self {
+        ConvexPolygon::expanded(self, margin)
+    }
+}